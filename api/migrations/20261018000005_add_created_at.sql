@@ -0,0 +1,5 @@
+ALTER TABLE habits ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE themes ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX habits_created_at_idx ON habits (created_at DESC);
+CREATE INDEX themes_created_at_idx ON themes (created_at DESC);
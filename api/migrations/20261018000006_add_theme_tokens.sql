@@ -0,0 +1,2 @@
+-- Design tokens compiled into CSS by the API; `css` becomes the raw override
+ALTER TABLE themes ADD COLUMN tokens JSONB;
@@ -0,0 +1,17 @@
+-- Trackers (local or opened from share links) that use a theme
+CREATE TABLE theme_usage (
+    theme_id UUID NOT NULL REFERENCES themes(id) ON DELETE CASCADE,
+    tracker_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (theme_id, tracker_id)
+);
+
+-- Anonymous up-votes; `voter` is a salted hash of the client IP
+CREATE TABLE theme_votes (
+    theme_id UUID NOT NULL REFERENCES themes(id) ON DELETE CASCADE,
+    voter TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (theme_id, voter)
+);
+
+CREATE INDEX theme_votes_voter_idx ON theme_votes (voter, created_at);
@@ -0,0 +1,16 @@
+ALTER TABLE habits ADD COLUMN category TEXT NOT NULL DEFAULT '';
+
+-- Slugs are hyphenated, so split them into words before indexing.
+ALTER TABLE habits ADD COLUMN search TSVECTOR GENERATED ALWAYS AS (
+    to_tsvector('simple', name || ' ' || replace(slug, '-', ' '))
+) STORED;
+
+CREATE INDEX IF NOT EXISTS habits_search_idx ON habits USING GIN (search);
+
+CREATE TABLE IF NOT EXISTS habit_tags (
+    habit_id UUID NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (habit_id, tag)
+);
+
+CREATE INDEX IF NOT EXISTS habit_tags_tag_idx ON habit_tags (tag);
@@ -0,0 +1,26 @@
+-- The server's VAPID key pair, generated on first start unless VAPID_PRIVATE_KEY is set.
+CREATE TABLE IF NOT EXISTS vapid_keys (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    private_key TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One row per browser and tracker. Trackers live in the browser, so the
+-- subscription carries what the milestone schedule needs.
+CREATE TABLE IF NOT EXISTS push_subscriptions (
+    endpoint TEXT NOT NULL,
+    tracker_id UUID NOT NULL,
+    p256dh TEXT NOT NULL,
+    auth TEXT NOT NULL,
+    habit_name TEXT NOT NULL,
+    habit_slug TEXT NOT NULL,
+    unit_name TEXT NOT NULL,
+    start_date TIMESTAMPTZ NOT NULL,
+    units_per_day DOUBLE PRECISION NOT NULL,
+    quiet_start SMALLINT NOT NULL,
+    quiet_end SMALLINT NOT NULL,
+    utc_offset_minutes INTEGER NOT NULL,
+    last_notified TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (endpoint, tracker_id)
+);
@@ -0,0 +1,29 @@
+-- Trackers live in the browser; these are the copies shared with groups.
+-- Only the browser holding the secret may change or withdraw its copy.
+CREATE TABLE IF NOT EXISTS trackers (
+    id UUID PRIMARY KEY,
+    habit_id UUID NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+    user_name TEXT NOT NULL,
+    start_date TIMESTAMPTZ NOT NULL,
+    units_per_day DOUBLE PRECISION NOT NULL,
+    secret_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS groups (
+    id UUID PRIMARY KEY,
+    -- Unguessable; knowing it is what lets someone see and join the group.
+    invite_code TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    habit_id UUID NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS group_members (
+    group_id UUID NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    tracker_id UUID NOT NULL REFERENCES trackers(id) ON DELETE CASCADE,
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (group_id, tracker_id)
+);
+
+CREATE INDEX IF NOT EXISTS group_members_tracker_id ON group_members (tracker_id);
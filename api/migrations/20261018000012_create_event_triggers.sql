@@ -0,0 +1,42 @@
+-- Shared trackers carry their latest relapse so groups can show it.
+ALTER TABLE trackers ADD COLUMN IF NOT EXISTS last_relapse_at TIMESTAMPTZ;
+
+-- Changes are announced on the `app_events` channel as {"kind", "id"}; the
+-- API's listener loads the row and fans it out to `/api/events` streams.
+-- NOTIFY is delivered on commit, so the row (and a new habit's tags) is
+-- visible by then, and repeats within one transaction arrive once.
+CREATE OR REPLACE FUNCTION notify_app_event() RETURNS trigger AS $$
+DECLARE
+    row_id UUID;
+BEGIN
+    IF TG_TABLE_NAME = 'group_members' THEN
+        row_id := CASE WHEN TG_OP = 'DELETE' THEN OLD.group_id ELSE NEW.group_id END;
+    ELSIF TG_TABLE_NAME = 'habit_icons' THEN
+        row_id := NEW.habit_id;
+    ELSE
+        row_id := NEW.id;
+    END IF;
+    PERFORM pg_notify('app_events', json_build_object('kind', TG_ARGV[0], 'id', row_id)::text);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS habits_notify ON habits;
+CREATE TRIGGER habits_notify AFTER INSERT OR UPDATE ON habits
+    FOR EACH ROW EXECUTE FUNCTION notify_app_event('habit');
+
+DROP TRIGGER IF EXISTS habit_icons_notify ON habit_icons;
+CREATE TRIGGER habit_icons_notify AFTER INSERT OR UPDATE ON habit_icons
+    FOR EACH ROW EXECUTE FUNCTION notify_app_event('habit');
+
+DROP TRIGGER IF EXISTS themes_notify ON themes;
+CREATE TRIGGER themes_notify AFTER INSERT OR UPDATE ON themes
+    FOR EACH ROW EXECUTE FUNCTION notify_app_event('theme');
+
+DROP TRIGGER IF EXISTS trackers_notify ON trackers;
+CREATE TRIGGER trackers_notify AFTER INSERT OR UPDATE ON trackers
+    FOR EACH ROW EXECUTE FUNCTION notify_app_event('tracker');
+
+DROP TRIGGER IF EXISTS group_members_notify ON group_members;
+CREATE TRIGGER group_members_notify AFTER INSERT OR DELETE ON group_members
+    FOR EACH ROW EXECUTE FUNCTION notify_app_event('group');
@@ -0,0 +1,18 @@
+-- A shared tracker is on its habit's leaderboard while it has a nickname.
+-- Entries are addressed by `leaderboard_id`, never the tracker id, which
+-- would let anyone follow the tracker's name and changes over `/api/events`.
+ALTER TABLE trackers ADD COLUMN IF NOT EXISTS leaderboard_nickname TEXT;
+ALTER TABLE trackers ADD COLUMN IF NOT EXISTS leaderboard_id UUID UNIQUE;
+
+CREATE INDEX IF NOT EXISTS trackers_leaderboard_idx ON trackers (habit_id) WHERE leaderboard_nickname IS NOT NULL;
+
+-- Reports of leaderboard entries; `reporter` is a salted hash of the client IP.
+-- Entries with enough reports drop off the leaderboard.
+CREATE TABLE IF NOT EXISTS leaderboard_flags (
+    tracker_id UUID NOT NULL REFERENCES trackers(id) ON DELETE CASCADE,
+    reporter TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tracker_id, reporter)
+);
+
+CREATE INDEX IF NOT EXISTS leaderboard_flags_reporter_idx ON leaderboard_flags (reporter, created_at);
@@ -0,0 +1,18 @@
+-- Messages and reactions left on published trackers by people opening their
+-- share links. `sender` is a salted hash of the client IP. The tracker's
+-- owner may hide or delete any of them.
+CREATE TABLE IF NOT EXISTS encouragements (
+    id UUID PRIMARY KEY,
+    tracker_id UUID NOT NULL REFERENCES trackers(id) ON DELETE CASCADE,
+    message TEXT NOT NULL DEFAULT '',
+    reaction TEXT NOT NULL DEFAULT '',
+    sender_name TEXT NOT NULL DEFAULT '',
+    sender TEXT NOT NULL,
+    hidden BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS encouragements_tracker_idx ON encouragements (tracker_id, created_at);
+CREATE INDEX IF NOT EXISTS encouragements_sender_idx ON encouragements (sender, created_at);
+-- Each sender gives each reaction once per tracker.
+CREATE UNIQUE INDEX IF NOT EXISTS encouragements_reaction_idx ON encouragements (tracker_id, sender, reaction) WHERE reaction <> '';
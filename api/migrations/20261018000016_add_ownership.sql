@@ -0,0 +1,11 @@
+-- Habits and themes belong to the account that created them. Rows from
+-- before accounts existed have no owner, and only admins may change them.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE habits ADD COLUMN IF NOT EXISTS created_by UUID REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE themes ADD COLUMN IF NOT EXISTS created_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+-- Admins may hide a habit from the library, or merge it into a duplicate.
+-- Both stay listed, so trackers kept in browsers still find them.
+ALTER TABLE habits ADD COLUMN IF NOT EXISTS hidden BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE habits ADD COLUMN IF NOT EXISTS merged_into UUID REFERENCES habits(id) ON DELETE SET NULL;
@@ -0,0 +1,7 @@
+-- Usage counts distinct clients, keyed like theme_votes.voter, so one client
+-- can't inflate a theme by inventing tracker ids. Rows from before count once each.
+ALTER TABLE theme_usage ADD COLUMN client TEXT;
+UPDATE theme_usage SET client = tracker_id::text;
+ALTER TABLE theme_usage ALTER COLUMN client SET NOT NULL;
+
+CREATE INDEX theme_usage_client_idx ON theme_usage (client, created_at);
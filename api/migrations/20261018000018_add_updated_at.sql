@@ -0,0 +1,6 @@
+ALTER TABLE habits ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE themes ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- Nothing recorded edits so far, so everything dates from its creation.
+UPDATE habits SET updated_at = created_at;
+UPDATE themes SET updated_at = created_at;
@@ -0,0 +1,28 @@
+-- Deleted habits and themes are announced too, as `habit_removed` and
+-- `theme_removed`, so open pages drop them. The row is gone by the time the
+-- listener hears of it, so these carry only the id.
+CREATE OR REPLACE FUNCTION notify_app_event() RETURNS trigger AS $$
+DECLARE
+    row_id UUID;
+BEGIN
+    IF TG_TABLE_NAME = 'group_members' THEN
+        row_id := CASE WHEN TG_OP = 'DELETE' THEN OLD.group_id ELSE NEW.group_id END;
+    ELSIF TG_TABLE_NAME = 'habit_icons' THEN
+        row_id := NEW.habit_id;
+    ELSIF TG_OP = 'DELETE' THEN
+        row_id := OLD.id;
+    ELSE
+        row_id := NEW.id;
+    END IF;
+    PERFORM pg_notify('app_events', json_build_object('kind', TG_ARGV[0], 'id', row_id)::text);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS habits_removed_notify ON habits;
+CREATE TRIGGER habits_removed_notify AFTER DELETE ON habits
+    FOR EACH ROW EXECUTE FUNCTION notify_app_event('habit_removed');
+
+DROP TRIGGER IF EXISTS themes_removed_notify ON themes;
+CREATE TRIGGER themes_removed_notify AFTER DELETE ON themes
+    FOR EACH ROW EXECUTE FUNCTION notify_app_event('theme_removed');
@@ -0,0 +1,57 @@
+//! Who may change what. Habits and themes belong to the account that created
+//! them, and admins may change anything. These run as route middleware, so
+//! the handlers behind them can take the permission for granted.
+
+use axum::{
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::auth::AuthUser;
+use crate::{api_error, internal_error, ApiErrorResponse, AppState};
+
+pub async fn require_habit_owner(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<uuid::Uuid>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiErrorResponse> {
+    require_owner(&state, &user, "habits", id).await?;
+    Ok(next.run(request).await)
+}
+
+pub async fn require_theme_owner(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<uuid::Uuid>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiErrorResponse> {
+    require_owner(&state, &user, "themes", id).await?;
+    Ok(next.run(request).await)
+}
+
+pub async fn require_admin(user: AuthUser, request: Request, next: Next) -> Result<Response, ApiErrorResponse> {
+    if !user.is_admin {
+        return Err(api_error(StatusCode::FORBIDDEN, "forbidden", None, "Only admins may do that"));
+    }
+    Ok(next.run(request).await)
+}
+
+/// Rows created before accounts existed have no owner; only admins may change those.
+async fn require_owner(state: &AppState, user: &AuthUser, table: &'static str, id: uuid::Uuid) -> Result<(), ApiErrorResponse> {
+    let owner: Option<Option<uuid::Uuid>> = sqlx::query_scalar(&format!("SELECT created_by FROM {} WHERE id = $1", table))
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(internal_error)?;
+    match owner {
+        None => Err(api_error(StatusCode::NOT_FOUND, "not_found", None, "Not found")),
+        Some(owner) if user.is_admin || owner == Some(user.id) => Ok(()),
+        Some(_) => Err(api_error(StatusCode::FORBIDDEN, "forbidden", None, "Only its creator or an admin may change this")),
+    }
+}
+
@@ -0,0 +1,127 @@
+//! Moderating the habit library. Only admins reach these; see `access`.
+
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+
+use crate::{api_error, internal_error, ApiErrorResponse, AppState};
+
+fn not_found(field: Option<&'static str>) -> ApiErrorResponse {
+    api_error(StatusCode::NOT_FOUND, "not_found", field, "No such habit")
+}
+
+#[derive(Deserialize)]
+pub struct Visibility {
+    hidden: bool,
+}
+
+#[derive(Deserialize)]
+pub struct MergeRequest {
+    into: uuid::Uuid,
+}
+
+/// Hides a habit from the library, or brings it back.
+pub async fn set_habit_hidden(
+    State(state): State<AppState>,
+    Path(id): Path<uuid::Uuid>,
+    Json(visibility): Json<Visibility>,
+) -> Result<StatusCode, ApiErrorResponse> {
+    let updated = sqlx::query("UPDATE habits SET hidden = $2 WHERE id = $1")
+        .bind(id)
+        .bind(visibility.hidden)
+        .execute(&state.db)
+        .await
+        .map_err(internal_error)?;
+    if updated.rows_affected() == 0 {
+        return Err(not_found(None));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Merges a duplicate habit into another. Shared trackers, groups and tags
+/// move over; the duplicate stays behind pointing at its replacement, so
+/// browsers still holding trackers for it can follow along.
+pub async fn merge_habit(
+    State(state): State<AppState>,
+    Path(id): Path<uuid::Uuid>,
+    Json(merge): Json<MergeRequest>,
+) -> Result<StatusCode, ApiErrorResponse> {
+    let invalid = |message: &str| api_error(StatusCode::BAD_REQUEST, "invalid", Some("into"), message);
+    if merge.into == id {
+        return Err(invalid("A habit can't be merged into itself"));
+    }
+    let mut tx = state.db.begin().await.map_err(internal_error)?;
+
+    let lock = "SELECT merged_into FROM habits WHERE id = $1 FOR UPDATE";
+    let source: Option<Option<uuid::Uuid>> = sqlx::query_scalar(lock)
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    if source.is_none() {
+        return Err(not_found(None));
+    }
+    let target: Option<Option<uuid::Uuid>> = sqlx::query_scalar(lock)
+        .bind(merge.into)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    match target {
+        None => return Err(not_found(Some("into"))),
+        Some(Some(_)) => return Err(invalid("That habit was itself merged away")),
+        Some(None) => {}
+    }
+
+    for sql in [
+        "UPDATE trackers SET habit_id = $2 WHERE habit_id = $1",
+        "UPDATE groups SET habit_id = $2 WHERE habit_id = $1",
+        "INSERT INTO habit_tags (habit_id, tag) SELECT $2, tag FROM habit_tags WHERE habit_id = $1 ON CONFLICT DO NOTHING",
+        // Earlier duplicates of this one follow it.
+        "UPDATE habits SET merged_into = $2 WHERE merged_into = $1",
+        "UPDATE habits SET merged_into = $2 WHERE id = $1",
+    ] {
+        sqlx::query(sql)
+            .bind(id)
+            .bind(merge.into)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    }
+    tx.commit().await.map_err(internal_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn insert_habit(state: &AppState) -> uuid::Uuid {
+        let id = uuid::Uuid::new_v4();
+        sqlx::query("INSERT INTO habits (id, slug, name, icon, unit_name) VALUES ($1, $2, 'Test', '🧪', 'units')")
+            .bind(id)
+            .bind(format!("test-{}", id))
+            .execute(&state.db)
+            .await
+            .unwrap();
+        id
+    }
+
+    #[tokio::test]
+    async fn missing_habits_are_not_found() {
+        let Some(state) = crate::test_state().await else { return };
+        let habit = insert_habit(&state).await;
+        let missing = uuid::Uuid::new_v4();
+
+        let hide = |id| set_habit_hidden(State(state.clone()), Path(id), Json(Visibility { hidden: true }));
+        assert_eq!(hide(missing).await.map_err(|(status, _)| status), Err(StatusCode::NOT_FOUND));
+        assert_eq!(hide(habit).await.map_err(|(status, _)| status), Ok(StatusCode::NO_CONTENT));
+
+        let merge = |id, into| merge_habit(State(state.clone()), Path(id), Json(MergeRequest { into }));
+        assert_eq!(merge(missing, habit).await.map_err(|(status, _)| status), Err(StatusCode::NOT_FOUND));
+        assert_eq!(merge(habit, missing).await.map_err(|(status, _)| status), Err(StatusCode::NOT_FOUND));
+        let duplicate = insert_habit(&state).await;
+        assert_eq!(merge(duplicate, habit).await.map_err(|(status, _)| status), Ok(StatusCode::NO_CONTENT));
+    }
+}
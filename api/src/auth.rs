@@ -0,0 +1,228 @@
+//! Passwordless accounts. Asking to log in mails a one-time link to
+//! `/login#<token>`; the page there trades the token for a session cookie.
+//! Keeping the token in the fragment, and redeeming it with a POST, stops
+//! mail scanners that prefetch links from using it up.
+
+use axum::{
+    extract::{FromRequestParts, Json, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{AppendHeaders, IntoResponse},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::secrets::{hash_secret, random_token};
+use crate::mail::Mail;
+use crate::{api_error, internal_error, ApiErrorResponse, AppState};
+
+const SESSION_COOKIE: &str = "session";
+const LOGIN_TOKEN_MINUTES: i64 = 15;
+const SESSION_DAYS: i64 = 30;
+const LOGINS_PER_HOUR: i64 = 5;
+const MAX_EMAIL_LEN: usize = 254;
+
+/// The logged-in user. Handlers take it as an argument to require a session;
+/// requests without one are turned away with 401.
+#[derive(Clone, Serialize, FromRow)]
+pub struct AuthUser {
+    pub id: uuid::Uuid,
+    pub email: String,
+    /// Admins may change anyone's habits and themes, and hide or merge habits.
+    pub is_admin: bool,
+}
+
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = ApiErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let unauthorized = || api_error(StatusCode::UNAUTHORIZED, "unauthorized", None, "Log in to do that");
+        let token = session_token(&parts.headers).ok_or_else(unauthorized)?;
+        sqlx::query_as::<_, AuthUser>(
+            "SELECT u.id, u.email, u.is_admin FROM sessions s JOIN users u ON u.id = s.user_id \
+             WHERE s.token_hash = $1 AND s.expires_at > NOW()"
+        )
+        .bind(hash_secret(token))
+        .fetch_optional(&state.db)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(unauthorized)
+    }
+}
+
+/// The session cookie's value, if the request carries one.
+fn session_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, value)| *name == SESSION_COOKIE && !value.is_empty())
+        .map(|(_, value)| value)
+}
+
+fn session_cookie(state: &AppState, token: &str, max_age: i64) -> String {
+    let secure = if state.public_url.starts_with("https://") { "; Secure" } else { "" };
+    format!("{}={}; HttpOnly; SameSite=Lax; Path=/; Max-Age={}{}", SESSION_COOKIE, token, max_age, secure)
+}
+
+/// Lowercases and trims an address, or `None` when it can't be one.
+fn normalize_email(email: &str) -> Option<String> {
+    let email = email.trim().to_lowercase();
+    let (local, domain) = email.split_once('@')?;
+    let valid = email.len() <= MAX_EMAIL_LEN
+        && !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email.chars().any(|c| c.is_whitespace() || c.is_control() || "<>,;\"".contains(c));
+    valid.then_some(email)
+}
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    email: String,
+}
+
+/// Mails a login link. Unknown addresses get one too: the first login creates
+/// the account.
+pub async fn request_login(
+    State(state): State<AppState>,
+    Json(request): Json<LoginRequest>,
+) -> Result<StatusCode, ApiErrorResponse> {
+    let email = normalize_email(&request.email)
+        .ok_or_else(|| api_error(StatusCode::BAD_REQUEST, "invalid", Some("email"), "Enter a valid email address"))?;
+
+    let recent: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM login_tokens WHERE email = $1 AND created_at > NOW() - INTERVAL '1 hour'"
+    )
+    .bind(&email)
+    .fetch_one(&state.db)
+    .await
+    .map_err(internal_error)?;
+    if recent >= LOGINS_PER_HOUR {
+        return Err(api_error(StatusCode::TOO_MANY_REQUESTS, "rate_limited", None, "Too many login links, try again later"));
+    }
+
+    let token = random_token(32);
+    sqlx::query("INSERT INTO login_tokens (token_hash, email, expires_at) VALUES ($1, $2, NOW() + make_interval(mins => $3))")
+        .bind(hash_secret(&token))
+        .bind(&email)
+        .bind(LOGIN_TOKEN_MINUTES as i32)
+        .execute(&state.db)
+        .await
+        .map_err(internal_error)?;
+
+    let mail = Mail {
+        to: email,
+        subject: "Your login link".to_string(),
+        body: format!(
+            "Open this link to log in to You, Quitter!:\n\n{}/login#{}\n\n\
+             It works once, for the next {} minutes. If you didn't ask for it, ignore this email.\n",
+            state.public_url, token, LOGIN_TOKEN_MINUTES
+        ),
+    };
+    state.mailer.send(mail).await.map_err(|e| {
+        println!("Failed to send login link: {}", e);
+        api_error(StatusCode::BAD_GATEWAY, "mail_failed", None, "Couldn't send the login link, try again later")
+    })?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[derive(Deserialize)]
+pub struct SessionRequest {
+    token: String,
+}
+
+/// Redeems a login link for a session cookie, creating the account on first
+/// login. Admin rights follow `ADMIN_EMAILS` as of each login.
+pub async fn create_session(
+    State(state): State<AppState>,
+    Json(request): Json<SessionRequest>,
+) -> Result<impl IntoResponse, ApiErrorResponse> {
+    let mut tx = state.db.begin().await.map_err(internal_error)?;
+    let email: String = sqlx::query_scalar(
+        "UPDATE login_tokens SET used_at = NOW() \
+         WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW() RETURNING email"
+    )
+    .bind(hash_secret(&request.token))
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?
+    .ok_or_else(|| api_error(StatusCode::UNAUTHORIZED, "link_expired", None, "This login link has expired or was already used"))?;
+
+    let user = sqlx::query_as::<_, AuthUser>(
+        "INSERT INTO users (id, email, is_admin) VALUES ($1, $2, $3) \
+         ON CONFLICT (email) DO UPDATE SET is_admin = EXCLUDED.is_admin \
+         RETURNING id, email, is_admin"
+    )
+    .bind(uuid::Uuid::new_v4())
+    .bind(&email)
+    .bind(state.admin_emails.contains(&email))
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    let session = random_token(32);
+    sqlx::query("INSERT INTO sessions (token_hash, user_id, expires_at) VALUES ($1, $2, NOW() + make_interval(days => $3))")
+        .bind(hash_secret(&session))
+        .bind(user.id)
+        .bind(SESSION_DAYS as i32)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    // Spent and expired links are of no further use.
+    sqlx::query("DELETE FROM login_tokens WHERE expires_at < NOW() - INTERVAL '1 day'")
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    let cookie = session_cookie(&state, &session, SESSION_DAYS * 24 * 60 * 60);
+    Ok((AppendHeaders([(header::SET_COOKIE, cookie)]), Json(user)))
+}
+
+pub async fn me(user: AuthUser) -> Json<AuthUser> {
+    Json(user)
+}
+
+/// Ends this browser's session. Logging out without one is not an error.
+pub async fn logout(State(state): State<AppState>, headers: HeaderMap) -> Result<impl IntoResponse, ApiErrorResponse> {
+    if let Some(token) = session_token(&headers) {
+        sqlx::query("DELETE FROM sessions WHERE token_hash = $1 OR expires_at < NOW()")
+            .bind(hash_secret(token))
+            .execute(&state.db)
+            .await
+            .map_err(internal_error)?;
+    }
+    Ok((AppendHeaders([(header::SET_COOKIE, session_cookie(&state, "", 0))]), StatusCode::NO_CONTENT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn session_cookie_is_found_among_others() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(session_token(&headers), None);
+        headers.append(header::COOKIE, HeaderValue::from_static("theme=dark; session="));
+        assert_eq!(session_token(&headers), None);
+        headers.append(header::COOKIE, HeaderValue::from_static("a=1;session=abc; b=2"));
+        assert_eq!(session_token(&headers), Some("abc"));
+    }
+
+    #[test]
+    fn emails_are_normalized_or_rejected() {
+        assert_eq!(normalize_email("  Sam@Example.COM "), Some("sam@example.com".to_string()));
+        assert_eq!(normalize_email("a.b+tag@mail.example.org"), Some("a.b+tag@mail.example.org".to_string()));
+        assert_eq!(normalize_email("sam"), None);
+        assert_eq!(normalize_email("@example.com"), None);
+        assert_eq!(normalize_email("sam@localhost"), None);
+        assert_eq!(normalize_email("sam@a@b.com"), None);
+        assert_eq!(normalize_email("sam @example.com"), None);
+        assert_eq!(normalize_email("sam@example.com\r\nBcc: x@y.z"), None);
+    }
+}
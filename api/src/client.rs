@@ -0,0 +1,29 @@
+use axum::http::HeaderMap;
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, SocketAddr};
+
+/// The caller's IP. Proxy headers are only honoured when `TRUST_PROXY_HEADERS`
+/// is set, since anyone can send them when the app is reached directly.
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr, trust_proxy_headers: bool) -> IpAddr {
+    if trust_proxy_headers {
+        let forwarded = headers
+            .get("fly-client-ip")
+            .or_else(|| headers.get("x-forwarded-for"))
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .and_then(|v| v.trim().parse().ok());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+    peer.ip()
+}
+
+/// A stable, non-reversible identifier for an anonymous client.
+pub fn anonymous_id(ip: IpAddr, salt: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(salt.as_bytes())
+        .chain_update(ip.to_string().as_bytes())
+        .finalize();
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,203 @@
+//! Encouragement on published trackers: short messages and reactions from
+//! people who opened a share link, moderated by the tracker's owner.
+
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Json, Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use prost::Message as _;
+use serde::{Deserialize, Serialize};
+use shared::tracker::Encouragement;
+use sqlx::FromRow;
+use std::net::SocketAddr;
+
+use crate::groups::secret_header;
+use crate::secrets::hash_secret;
+use crate::{api_error, client, internal_error, ApiErrorResponse, AppState};
+
+const SENT_PER_HOUR: i64 = 10;
+const MAX_MESSAGES: i64 = 50;
+
+#[derive(Serialize, FromRow)]
+pub struct Message {
+    id: uuid::Uuid,
+    message: String,
+    #[serde(rename = "from")]
+    sender_name: String,
+    created_at: DateTime<Utc>,
+    hidden: bool,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ReactionCount {
+    reaction: String,
+    count: i64,
+}
+
+#[derive(Serialize)]
+pub struct Encouragements {
+    messages: Vec<Message>,
+    reactions: Vec<ReactionCount>,
+}
+
+#[derive(Deserialize)]
+pub struct Visibility {
+    hidden: bool,
+}
+
+fn not_found() -> ApiErrorResponse {
+    api_error(StatusCode::NOT_FOUND, "not_found", None, "No such published tracker")
+}
+
+/// The newest messages, hidden ones only for the owner, and reaction counts.
+async fn load(db: &sqlx::PgPool, tracker_id: uuid::Uuid, include_hidden: bool) -> Result<Encouragements, ApiErrorResponse> {
+    let messages = sqlx::query_as::<_, Message>(
+        "SELECT id, message, sender_name, created_at, hidden FROM encouragements \
+         WHERE tracker_id = $1 AND message <> '' AND (NOT hidden OR $2) \
+         ORDER BY created_at DESC LIMIT $3"
+    )
+    .bind(tracker_id)
+    .bind(include_hidden)
+    .bind(MAX_MESSAGES)
+    .fetch_all(db)
+    .await
+    .map_err(internal_error)?;
+
+    let reactions = sqlx::query_as::<_, ReactionCount>(
+        "SELECT reaction, COUNT(*) AS count FROM encouragements \
+         WHERE tracker_id = $1 AND reaction <> '' AND NOT hidden \
+         GROUP BY reaction ORDER BY count DESC, reaction"
+    )
+    .bind(tracker_id)
+    .fetch_all(db)
+    .await
+    .map_err(internal_error)?;
+    Ok(Encouragements { messages, reactions })
+}
+
+async fn published_tracker(db: &sqlx::PgPool, entry_id: uuid::Uuid) -> Result<uuid::Uuid, ApiErrorResponse> {
+    sqlx::query_scalar("SELECT id FROM trackers WHERE leaderboard_id = $1")
+        .bind(entry_id)
+        .fetch_optional(db)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)
+}
+
+/// Checks the caller holds the tracker's secret.
+async fn require_owner(db: &sqlx::PgPool, tracker_id: uuid::Uuid, headers: &HeaderMap) -> Result<(), ApiErrorResponse> {
+    let hash: String = sqlx::query_scalar("SELECT secret_hash FROM trackers WHERE id = $1")
+        .bind(tracker_id)
+        .fetch_optional(db)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+    if secret_header(headers).map(hash_secret) != Some(hash) {
+        return Err(api_error(StatusCode::FORBIDDEN, "forbidden", None, "This tracker belongs to another browser"));
+    }
+    Ok(())
+}
+
+/// What visitors see under a published tracker.
+pub async fn list(
+    State(state): State<AppState>,
+    Path(entry_id): Path<uuid::Uuid>,
+) -> Result<Json<Encouragements>, ApiErrorResponse> {
+    let tracker_id = published_tracker(&state.db, entry_id).await?;
+    Ok(Json(load(&state.db, tracker_id, false).await?))
+}
+
+/// Leaves a message or reaction. Answers with what visitors now see.
+pub async fn send(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(entry_id): Path<uuid::Uuid>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<Encouragements>), ApiErrorResponse> {
+    let proto = Encouragement::decode(body)
+        .map_err(|_| api_error(StatusCode::BAD_REQUEST, "malformed", None, "Malformed encouragement"))?;
+    let encouragement = proto
+        .normalized()
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, "invalid", e.field(), e.to_string()))?;
+    let tracker_id = published_tracker(&state.db, entry_id).await?;
+
+    let ip = client::client_ip(&headers, peer, state.trust_proxy_headers);
+    let sender = client::anonymous_id(ip, &state.vote_salt);
+    let recent: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM encouragements WHERE sender = $1 AND created_at > NOW() - INTERVAL '1 hour'"
+    )
+    .bind(&sender)
+    .fetch_one(&state.db)
+    .await
+    .map_err(internal_error)?;
+    if recent >= SENT_PER_HOUR {
+        return Err(api_error(StatusCode::TOO_MANY_REQUESTS, "rate_limited", None, "That's a lot of encouragement! Try again later"));
+    }
+
+    // A repeated reaction is already counted.
+    sqlx::query(
+        "INSERT INTO encouragements (id, tracker_id, message, reaction, sender_name, sender) \
+         VALUES ($1, $2, $3, $4, $5, $6) \
+         ON CONFLICT (tracker_id, sender, reaction) WHERE reaction <> '' DO NOTHING"
+    )
+    .bind(uuid::Uuid::new_v4())
+    .bind(tracker_id)
+    .bind(&encouragement.message)
+    .bind(&encouragement.reaction)
+    .bind(&encouragement.from)
+    .bind(&sender)
+    .execute(&state.db)
+    .await
+    .map_err(internal_error)?;
+
+    Ok((StatusCode::CREATED, Json(load(&state.db, tracker_id, false).await?)))
+}
+
+/// Everything left on the owner's tracker, hidden messages included.
+pub async fn list_own(
+    State(state): State<AppState>,
+    Path(tracker_id): Path<uuid::Uuid>,
+    headers: HeaderMap,
+) -> Result<Json<Encouragements>, ApiErrorResponse> {
+    require_owner(&state.db, tracker_id, &headers).await?;
+    Ok(Json(load(&state.db, tracker_id, true).await?))
+}
+
+pub async fn set_hidden(
+    State(state): State<AppState>,
+    Path((tracker_id, id)): Path<(uuid::Uuid, uuid::Uuid)>,
+    headers: HeaderMap,
+    Json(visibility): Json<Visibility>,
+) -> Result<StatusCode, ApiErrorResponse> {
+    require_owner(&state.db, tracker_id, &headers).await?;
+    let updated = sqlx::query("UPDATE encouragements SET hidden = $3 WHERE id = $1 AND tracker_id = $2")
+        .bind(id)
+        .bind(tracker_id)
+        .bind(visibility.hidden)
+        .execute(&state.db)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    if updated == 0 {
+        return Err(api_error(StatusCode::NOT_FOUND, "not_found", None, "No such message"));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete(
+    State(state): State<AppState>,
+    Path((tracker_id, id)): Path<(uuid::Uuid, uuid::Uuid)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiErrorResponse> {
+    require_owner(&state.db, tracker_id, &headers).await?;
+    sqlx::query("DELETE FROM encouragements WHERE id = $1 AND tracker_id = $2")
+        .bind(id)
+        .bind(tracker_id)
+        .execute(&state.db)
+        .await
+        .map_err(internal_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
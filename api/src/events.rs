@@ -0,0 +1,223 @@
+//! Live updates over server-sent events. Postgres triggers announce changes on
+//! the `app_events` channel, so every API instance hears writes made through
+//! any of them; each instance's listener loads a changed row once and fans it
+//! out to its open `/api/events` streams.
+
+use std::{collections::HashSet, convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::{groups::SharedTracker, AppState, Habit, Theme, HABIT_COLUMNS, THEME_COLUMNS};
+
+const CHANNEL: &str = "app_events";
+/// Changes a slow stream may fall behind by before it is told to resync.
+const BUFFER: usize = 256;
+/// Trackers or groups one stream may follow.
+const MAX_FOLLOWED: usize = 100;
+
+/// What the triggers send: the changed row's kind and id.
+#[derive(Deserialize)]
+struct Notification {
+    kind: String,
+    id: uuid::Uuid,
+}
+
+/// A change as loaded by the listener, shared by every stream.
+pub enum Change {
+    Habit(Habit),
+    HabitRemoved(uuid::Uuid),
+    Theme(Theme),
+    ThemeRemoved(uuid::Uuid),
+    Tracker { tracker: SharedTracker, groups: Vec<String> },
+    Group { invite_code: String },
+}
+
+pub type Sender = broadcast::Sender<Arc<Change>>;
+
+pub fn channel() -> Sender {
+    broadcast::channel(BUFFER).0
+}
+
+/// Listens for changes for the life of the process, reconnecting after errors.
+pub fn spawn_listener(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = listen(&state).await {
+                println!("Event listener failed: {:?}", e);
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    });
+}
+
+async fn listen(state: &AppState) -> sqlx::Result<()> {
+    let mut listener = PgListener::connect_with(&state.db).await?;
+    listener.listen(CHANNEL).await?;
+    loop {
+        let notification = listener.recv().await?;
+        let Ok(notification) = serde_json::from_str::<Notification>(notification.payload()) else {
+            println!("Ignoring malformed event: {}", notification.payload());
+            continue;
+        };
+        match load(&state.db, &notification).await {
+            // Sending only fails when no stream is open.
+            Ok(Some(change)) => drop(state.events.send(Arc::new(change))),
+            Ok(None) => {}
+            Err(e) => println!("Failed to load {} {}: {:?}", notification.kind, notification.id, e),
+        }
+    }
+}
+
+async fn load(db: &sqlx::PgPool, notification: &Notification) -> sqlx::Result<Option<Change>> {
+    let id = notification.id;
+    Ok(match notification.kind.as_str() {
+        "habit" => sqlx::query_as::<_, Habit>(&format!("SELECT {} FROM habits WHERE id = $1", HABIT_COLUMNS))
+            .bind(id)
+            .fetch_optional(db)
+            .await?
+            .map(Change::Habit),
+        "habit_removed" => Some(Change::HabitRemoved(id)),
+        "theme" => sqlx::query_as::<_, Theme>(&format!("SELECT {} FROM themes WHERE id = $1", THEME_COLUMNS))
+            .bind(id)
+            .fetch_optional(db)
+            .await?
+            .and_then(|theme| theme.compile().ok())
+            .map(Change::Theme),
+        "theme_removed" => Some(Change::ThemeRemoved(id)),
+        "tracker" => SharedTracker::load(db, id)
+            .await?
+            .map(|(tracker, groups)| Change::Tracker { tracker, groups }),
+        "group" => sqlx::query_scalar("SELECT invite_code FROM groups WHERE id = $1")
+            .bind(id)
+            .fetch_optional(db)
+            .await?
+            .map(|invite_code| Change::Group { invite_code }),
+        _ => None,
+    })
+}
+
+/// The trackers and groups a stream follows, as comma-separated ids and
+/// invite codes. Habits and themes are public and always streamed.
+#[derive(Deserialize)]
+pub struct Follow {
+    #[serde(default)]
+    trackers: String,
+    #[serde(default)]
+    groups: String,
+}
+
+#[derive(Serialize)]
+struct GroupChanged<'a> {
+    invite_code: &'a str,
+}
+
+#[derive(Serialize)]
+struct Removed {
+    id: uuid::Uuid,
+}
+
+struct Filter {
+    trackers: HashSet<uuid::Uuid>,
+    groups: HashSet<String>,
+}
+
+impl Filter {
+    fn new(follow: &Follow) -> Self {
+        let split = |s: &str| s.split(',').map(str::trim).filter(|s| !s.is_empty()).take(MAX_FOLLOWED).map(String::from).collect::<Vec<_>>();
+        Self {
+            trackers: split(&follow.trackers).iter().filter_map(|id| uuid::Uuid::parse_str(id).ok()).collect(),
+            groups: split(&follow.groups).into_iter().collect(),
+        }
+    }
+
+    /// The events, by name and JSON data, `change` becomes on this stream.
+    /// Group events only name the group; its page refetches it.
+    fn events(&self, change: &Change) -> Vec<(&'static str, String)> {
+        let group = |invite_code: &str| ("group", json(&GroupChanged { invite_code }));
+        match change {
+            Change::Habit(habit) => vec![("habit", json(habit))],
+            Change::HabitRemoved(id) => vec![("habit_removed", json(&Removed { id: *id }))],
+            Change::Theme(theme) => vec![("theme", json(theme))],
+            Change::ThemeRemoved(id) => vec![("theme_removed", json(&Removed { id: *id }))],
+            Change::Tracker { tracker, groups } => self
+                .trackers
+                .contains(&tracker.id())
+                .then(|| ("tracker", json(tracker)))
+                .into_iter()
+                .chain(groups.iter().filter(|code| self.groups.contains(*code)).map(|code| group(code)))
+                .collect(),
+            Change::Group { invite_code } if self.groups.contains(invite_code) => vec![group(invite_code)],
+            Change::Group { .. } => Vec::new(),
+        }
+    }
+}
+
+fn json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// Streams changes to habits, themes and the followed trackers and groups.
+/// A `resync` event means changes were missed and everything should be refetched.
+pub async fn stream(
+    State(state): State<AppState>,
+    Query(follow): Query<Follow>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let filter = Filter::new(&follow);
+    let events = BroadcastStream::new(state.events.subscribe()).flat_map(move |change| {
+        let events = match change {
+            Ok(change) => filter.events(&change),
+            Err(_) => vec![("resync", String::new())],
+        };
+        stream::iter(events.into_iter().map(|(name, data)| Ok(Event::default().event(name).data(data))))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_are_only_streamed_to_followers() {
+        let filter = Filter::new(&Follow {
+            trackers: format!("{}, not-a-uuid", uuid::Uuid::new_v4()),
+            groups: "abc,,def".to_string(),
+        });
+        assert_eq!(filter.trackers.len(), 1);
+        assert_eq!(filter.groups.len(), 2);
+
+        let followed = Change::Group { invite_code: "abc".to_string() };
+        assert_eq!(filter.events(&followed), vec![("group", r#"{"invite_code":"abc"}"#.to_string())]);
+        let other = Change::Group { invite_code: "xyz".to_string() };
+        assert!(filter.events(&other).is_empty());
+    }
+
+    #[tokio::test]
+    async fn deleting_a_theme_is_announced() {
+        let Some(state) = crate::test_state().await else { return };
+        let mut listener = PgListener::connect_with(&state.db).await.unwrap();
+        listener.listen(CHANNEL).await.unwrap();
+        let id = uuid::Uuid::new_v4();
+        sqlx::query("INSERT INTO themes (id, name, css) VALUES ($1, 'Fleeting', '')").bind(id).execute(&state.db).await.unwrap();
+        sqlx::query("DELETE FROM themes WHERE id = $1").bind(id).execute(&state.db).await.unwrap();
+
+        // Other tests may be writing too, so skip what isn't ours.
+        let removed = loop {
+            let notification = listener.recv().await.unwrap();
+            let notification: Notification = serde_json::from_str(notification.payload()).unwrap();
+            if notification.id == id && notification.kind == "theme_removed" {
+                break load(&state.db, &notification).await.unwrap().unwrap();
+            }
+        };
+        let filter = Filter::new(&Follow { trackers: String::new(), groups: String::new() });
+        assert_eq!(filter.events(&removed), vec![("theme_removed", format!(r#"{{"id":"{}"}}"#, id))]);
+    }
+}
@@ -0,0 +1,275 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use shared::hash::fnv1a;
+
+use crate::{AppState, Habit, Theme};
+
+const FEED_LIMIT: i64 = 50;
+const CSS_PREVIEW_CHARS: usize = 280;
+
+struct Entry {
+    id: uuid::Uuid,
+    title: String,
+    link: String,
+    summary: String,
+    content: String,
+    published: DateTime<Utc>,
+    updated: DateTime<Utc>,
+}
+
+struct Feed {
+    title: &'static str,
+    self_url: String,
+    alternate_url: String,
+    entries: Vec<Entry>,
+}
+
+pub async fn habits_feed(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let result = sqlx::query_as::<_, Habit>(
+        "SELECT id, slug, name, icon, unit_name, created_at, updated_at FROM habits \
+         WHERE NOT hidden AND merged_into IS NULL ORDER BY created_at DESC LIMIT $1"
+    )
+    .bind(FEED_LIMIT)
+    .fetch_all(&state.db)
+    .await;
+
+    let habits = match result {
+        Ok(habits) => habits,
+        Err(e) => {
+            println!("Error fetching habits feed: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let base = &state.public_url;
+    let feed = Feed {
+        title: "Quitter: new habits",
+        self_url: format!("{}/feeds/habits.atom", base),
+        alternate_url: format!("{}/habits", base),
+        entries: habits
+            .into_iter()
+            .map(|h| Entry {
+                id: h.id,
+                title: format!("{} {}", h.icon, h.name),
+                link: format!("{}/{}", base, h.slug),
+                summary: format!("Counted in {}", h.unit_name),
+                content: format!("Icon: {}\nUnit: {}\nSlug: /{}", h.icon, h.unit_name, h.slug),
+                published: h.created_at,
+                updated: h.updated_at,
+            })
+            .collect(),
+    };
+
+    respond(&headers, &feed)
+}
+
+pub async fn themes_feed(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let result = sqlx::query_as::<_, Theme>(
+        "SELECT id, name, css, icon_limit, created_at, updated_at, tokens FROM themes ORDER BY created_at DESC LIMIT $1"
+    )
+    .bind(FEED_LIMIT)
+    .fetch_all(&state.db)
+    .await;
+
+    let themes = match result {
+        Ok(themes) => themes,
+        Err(e) => {
+            println!("Error fetching themes feed: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let base = &state.public_url;
+    let feed = Feed {
+        title: "Quitter: new themes",
+        self_url: format!("{}/feeds/themes.atom", base),
+        alternate_url: format!("{}/themes", base),
+        entries: themes
+            .into_iter()
+            .filter_map(|t| t.compile().ok())
+            .map(|t| Entry {
+                id: t.id,
+                title: t.name,
+                link: format!("{}/themes/{}", base, t.id),
+                summary: match t.icon_limit {
+                    Some(limit) => format!("Icon limit: {}", limit),
+                    None => "No icon limit".to_string(),
+                },
+                content: css_preview(&t.css),
+                published: t.created_at,
+                updated: t.updated_at,
+            })
+            .collect(),
+    };
+
+    respond(&headers, &feed)
+}
+
+/// Renders the feed and answers `If-None-Match` with a 304 when the body is unchanged.
+fn respond(headers: &HeaderMap, feed: &Feed) -> Response {
+    let body = render(feed);
+    let etag = format!("\"{:016x}\"", fnv1a(body.as_bytes()));
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| etag_matches(v, &etag));
+
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/atom+xml; charset=utf-8".to_string()),
+            (header::ETAG, etag),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+fn render(feed: &Feed) -> String {
+    // Entries are newest first, but an older one may have been edited since.
+    let updated = feed
+        .entries
+        .iter()
+        .map(|e| e.updated)
+        .max()
+        .unwrap_or(DateTime::UNIX_EPOCH);
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", escape(feed.title)));
+    xml.push_str(&format!("  <id>{}</id>\n", escape(&feed.self_url)));
+    xml.push_str(&format!("  <link rel=\"self\" href=\"{}\"/>\n", escape(&feed.self_url)));
+    xml.push_str(&format!("  <link rel=\"alternate\" href=\"{}\"/>\n", escape(&feed.alternate_url)));
+    xml.push_str(&format!("  <updated>{}</updated>\n", updated.to_rfc3339()));
+    xml.push_str("  <author><name>Alcoholics Audacious</name></author>\n");
+
+    for entry in &feed.entries {
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <id>urn:uuid:{}</id>\n", entry.id));
+        xml.push_str(&format!("    <title>{}</title>\n", escape(&entry.title)));
+        xml.push_str(&format!("    <link href=\"{}\"/>\n", escape(&entry.link)));
+        xml.push_str(&format!("    <updated>{}</updated>\n", entry.updated.to_rfc3339()));
+        xml.push_str(&format!("    <published>{}</published>\n", entry.published.to_rfc3339()));
+        xml.push_str(&format!("    <summary>{}</summary>\n", escape(&entry.summary)));
+        xml.push_str(&format!("    <content type=\"text\">{}</content>\n", escape(&entry.content)));
+        xml.push_str("  </entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn css_preview(css: &str) -> String {
+    let trimmed = css.trim();
+    match trimmed.char_indices().nth(CSS_PREVIEW_CHARS) {
+        Some((cut, _)) => format!("{}…", &trimmed[..cut]),
+        None => trimmed.to_string(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|t| t.trim().trim_start_matches("W/"))
+        .any(|t| t == "*" || t == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup_in_css() {
+        assert_eq!(escape("a > b & \"c\""), "a &gt; b &amp; &quot;c&quot;");
+    }
+
+    #[test]
+    fn css_preview_truncates_on_char_boundary() {
+        let css = "é".repeat(CSS_PREVIEW_CHARS + 10);
+        let preview = css_preview(&css);
+        assert_eq!(preview.chars().count(), CSS_PREVIEW_CHARS + 1);
+        assert!(preview.ends_with('…'));
+    }
+
+    #[test]
+    fn etag_matching_handles_lists_and_weak_tags() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("\"x\", W/\"abc\"", "\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"abd\"", "\"abc\""));
+    }
+
+    #[test]
+    fn edits_update_entries_but_not_their_publication() {
+        let published = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let edited = DateTime::from_timestamp(1_700_086_400, 0).unwrap();
+        let entry = |updated| Entry {
+            id: uuid::Uuid::new_v4(),
+            title: "Theme".to_string(),
+            link: "http://localhost/themes/x".to_string(),
+            summary: String::new(),
+            content: String::new(),
+            published,
+            updated,
+        };
+        let feed = Feed {
+            title: "Quitter: new themes",
+            self_url: "http://localhost/feeds/themes.atom".to_string(),
+            alternate_url: "http://localhost/themes".to_string(),
+            entries: vec![entry(published), entry(edited)],
+        };
+
+        let xml = render(&feed);
+        assert!(xml.contains(&format!("  <updated>{}</updated>", edited.to_rfc3339())));
+        assert!(xml.contains(&format!("    <updated>{}</updated>", edited.to_rfc3339())));
+        assert_eq!(xml.matches(&format!("<published>{}</published>", published.to_rfc3339())).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn entries_link_to_their_own_pages() {
+        let Some(state) = crate::test_state().await else { return };
+        let (habit, theme) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let slug = format!("feed-{}", habit);
+        sqlx::query("INSERT INTO habits (id, slug, name, icon, unit_name) VALUES ($1, $2, 'Feed', '📰', 'papers')")
+            .bind(habit)
+            .bind(&slug)
+            .execute(&state.db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO themes (id, name, css) VALUES ($1, 'Feed', '')").bind(theme).execute(&state.db).await.unwrap();
+
+        let read = |response: Response| async move {
+            String::from_utf8(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap()
+        };
+        let habits = read(habits_feed(State(state.clone()), HeaderMap::new()).await).await;
+        assert!(habits.contains(&format!("<link href=\"{}/{}\"/>", state.public_url, slug)));
+        let themes = read(themes_feed(State(state.clone()), HeaderMap::new()).await).await;
+        assert!(themes.contains(&format!("<link href=\"{}/themes/{}\"/>", state.public_url, theme)));
+
+        sqlx::query("DELETE FROM themes WHERE id = $1").bind(theme).execute(&state.db).await.unwrap();
+    }
+}
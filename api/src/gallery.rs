@@ -0,0 +1,290 @@
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use shared::css::{Rule, Stylesheet};
+use shared::theme_tokens::is_color;
+use shared::tracker::{BorderStyle, Palette};
+use std::net::SocketAddr;
+
+use crate::{client, AppState, Theme};
+
+const VOTES_PER_HOUR: i64 = 20;
+const USAGES_PER_HOUR: i64 = 20;
+
+pub async fn vote_theme(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<uuid::Uuid>,
+) -> StatusCode {
+    let ip = client::client_ip(&headers, peer, state.trust_proxy_headers);
+    let voter = client::anonymous_id(ip, &state.vote_salt);
+
+    let recent: i64 = match sqlx::query_scalar(
+        "SELECT COUNT(*) FROM theme_votes WHERE voter = $1 AND created_at > NOW() - INTERVAL '1 hour'"
+    )
+    .bind(&voter)
+    .fetch_one(&state.db)
+    .await
+    {
+        Ok(count) => count,
+        Err(e) => {
+            println!("Error counting votes: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    };
+    if recent >= VOTES_PER_HOUR {
+        return StatusCode::TOO_MANY_REQUESTS;
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO theme_votes (theme_id, voter) SELECT id, $2 FROM themes WHERE id = $1 ON CONFLICT DO NOTHING"
+    )
+    .bind(id)
+    .bind(&voter)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(e) => {
+            println!("Error recording vote: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Records that a tracker uses the theme. The body is the tracker id as text.
+/// Usage counts distinct clients, keyed like votes, so made-up tracker ids
+/// from one client add nothing.
+pub async fn record_usage(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<uuid::Uuid>,
+    body: Bytes,
+) -> StatusCode {
+    let Some(tracker_id) = std::str::from_utf8(&body)
+        .ok()
+        .and_then(|s| uuid::Uuid::parse_str(s.trim()).ok())
+    else {
+        return StatusCode::BAD_REQUEST;
+    };
+    let ip = client::client_ip(&headers, peer, state.trust_proxy_headers);
+    let client = client::anonymous_id(ip, &state.vote_salt);
+
+    let recent: i64 = match sqlx::query_scalar(
+        "SELECT COUNT(*) FROM theme_usage WHERE client = $1 AND created_at > NOW() - INTERVAL '1 hour'"
+    )
+    .bind(&client)
+    .fetch_one(&state.db)
+    .await
+    {
+        Ok(count) => count,
+        Err(e) => {
+            println!("Error counting theme usage: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    };
+    if recent >= USAGES_PER_HOUR {
+        return StatusCode::TOO_MANY_REQUESTS;
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO theme_usage (theme_id, tracker_id, client) SELECT id, $2, $3 FROM themes WHERE id = $1 ON CONFLICT DO NOTHING"
+    )
+    .bind(id)
+    .bind(tracker_id)
+    .bind(&client)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(e) => {
+            println!("Error recording theme usage: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+pub async fn theme_thumbnail(State(state): State<AppState>, Path(id): Path<uuid::Uuid>) -> Response {
+    let result = sqlx::query_as::<_, Theme>(
+        "SELECT id, name, css, icon_limit, created_at, updated_at, tokens FROM themes WHERE id = $1"
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await;
+
+    let theme = match result {
+        Ok(Some(theme)) => theme,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            println!("Error fetching theme thumbnail: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        render_thumbnail(&theme),
+    )
+        .into_response()
+}
+
+struct Look {
+    palette: Palette,
+    border_style: BorderStyle,
+    border_width: i32,
+    radius: i32,
+}
+
+fn render_thumbnail(theme: &Theme) -> String {
+    let look = match &theme.tokens {
+        Some(tokens) => Look {
+            palette: tokens.palette.clone().unwrap_or_else(Palette::winamp),
+            border_style: BorderStyle::try_from(tokens.border_style).unwrap_or(BorderStyle::Solid),
+            border_width: tokens.border_width_px.clamp(0, 8),
+            radius: tokens.border_radius_px.clamp(0, 24),
+        },
+        None => Look {
+            palette: palette_from_css(&theme.css),
+            border_style: BorderStyle::Solid,
+            border_width: 2,
+            radius: 0,
+        },
+    };
+
+    let p = &look.palette;
+    let color = |c: &str, fallback: &'static str| if is_color(c) { c.to_string() } else { fallback.to_string() };
+    let (bg, surface, text, accent, muted) = (
+        color(&p.background, "#000"),
+        color(&p.surface, "#111"),
+        color(&p.text, "#0f0"),
+        color(&p.accent, "#0f0"),
+        color(&p.muted, "#333"),
+    );
+    let dash = match look.border_style {
+        BorderStyle::Dashed => " stroke-dasharray=\"6 4\"",
+        BorderStyle::Dotted => " stroke-dasharray=\"2 3\"",
+        _ => "",
+    };
+    let stroke = if look.border_style == BorderStyle::None { 0 } else { look.border_width };
+
+    // Scatter a few "icons" deterministically from the theme id.
+    let icons: String = theme
+        .id
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let x = 30 + (pair[0] as u32 * 180 / 255);
+            let y = 72 + (pair[1] as u32 * 50 / 255);
+            format!("<circle cx=\"{}\" cy=\"{}\" r=\"4\" fill=\"{}\" fill-opacity=\"0.8\"/>", x, y, accent)
+        })
+        .collect();
+
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="240" height="150" viewBox="0 0 240 150">
+<rect width="240" height="150" fill="{bg}"/>
+<rect x="15" y="15" width="210" height="120" rx="{radius}" fill="{surface}" stroke="{accent}" stroke-width="{stroke}"{dash}/>
+<rect x="27" y="26" width="110" height="7" fill="{text}"/>
+<line x1="27" y1="40" x2="213" y2="40" stroke="{muted}"/>
+<rect x="70" y="50" width="100" height="9" fill="{accent}"/>
+{icons}
+</svg>"#,
+        radius = look.radius,
+    )
+}
+
+/// Best-effort palette for themes written as raw CSS.
+fn palette_from_css(css: &str) -> Palette {
+    let mut palette = Palette::winamp();
+    let Ok(sheet) = Stylesheet::parse(css) else {
+        return palette;
+    };
+
+    for rule in &sheet.rules {
+        let Rule::Style { selector, declarations } = rule else { continue };
+        for d in declarations {
+            let Some(value) = first_color(&d.value) else { continue };
+            let prop = d.property.as_str();
+            let is_background = prop == "background" || prop == "background-color";
+            if selector.contains(".habit-card") {
+                if is_background {
+                    palette.surface = value;
+                } else if prop.starts_with("border") {
+                    palette.accent = value;
+                }
+            } else if selector.contains(".habit-stats") && prop == "color" {
+                palette.accent = value;
+            } else if selector.contains(".habit-header") && prop.starts_with("border") {
+                palette.muted = value;
+            } else if is_page_selector(selector) {
+                if is_background {
+                    palette.background = value;
+                } else if prop == "color" {
+                    palette.text = value;
+                }
+            }
+        }
+    }
+    palette
+}
+
+fn is_page_selector(selector: &str) -> bool {
+    selector
+        .split(',')
+        .map(str::trim)
+        .any(|s| s == "body" || s == ".app-container" || s.starts_with("[data-theme=") && !s.contains(' '))
+}
+
+fn first_color(value: &str) -> Option<String> {
+    value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|word| word.trim_end_matches(';'))
+        .find(|word| word.starts_with('#') && is_color(word))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_colors_out_of_raw_css() {
+        let palette = palette_from_css(
+            "body { background: #123456; color: #abcdef; }\n.habit-card { border: 2px solid #ff0000; background: #222; }",
+        );
+        assert_eq!(palette.background, "#123456");
+        assert_eq!(palette.text, "#abcdef");
+        assert_eq!(palette.accent, "#ff0000");
+        assert_eq!(palette.surface, "#222");
+    }
+
+    #[tokio::test]
+    async fn usage_counts_each_client_once() {
+        let Some(state) = crate::test_state().await else { return };
+        let id = uuid::Uuid::new_v4();
+        sqlx::query("INSERT INTO themes (id, name, css) VALUES ($1, 'Popular', '')").bind(id).execute(&state.db).await.unwrap();
+        let use_theme = |ip: [u8; 4]| {
+            let peer = SocketAddr::from((ip, 4000));
+            let tracker = Bytes::from(uuid::Uuid::new_v4().to_string());
+            record_usage(State(state.clone()), ConnectInfo(peer), HeaderMap::new(), Path(id), tracker)
+        };
+        for _ in 0..3 {
+            assert_eq!(use_theme([192, 0, 2, 1]).await, StatusCode::NO_CONTENT);
+        }
+        assert_eq!(use_theme([192, 0, 2, 2]).await, StatusCode::NO_CONTENT);
+
+        let sql = format!("SELECT {} FROM themes WHERE id = $1", crate::THEME_COLUMNS);
+        let theme = sqlx::query_as::<_, Theme>(&sql).bind(id).fetch_one(&state.db).await.unwrap();
+        assert_eq!(theme.usage_count, 2);
+        sqlx::query("DELETE FROM themes WHERE id = $1").bind(id).execute(&state.db).await.unwrap();
+    }
+}
@@ -0,0 +1,410 @@
+//! Groups of people quitting the same habit together. A group is reached
+//! through its invite code; members are server-side copies of their trackers.
+
+use axum::{
+    body::Bytes,
+    extract::{Json, Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, TimeZone, Utc};
+use prost::Message;
+use serde::Serialize;
+use shared::tracker::{CreateGroup, GroupTracker};
+use sqlx::{FromRow, Postgres, Transaction};
+
+use crate::secrets::{hash_secret, random_token};
+use crate::{api_error, internal_error, ApiErrorResponse, AppState};
+
+/// Proves a browser created a server-side tracker.
+pub const SECRET_HEADER: &str = "x-tracker-secret";
+const MAX_GROUP_NAME_LEN: usize = 60;
+const MAX_USER_NAME_LEN: usize = 50;
+pub const MAX_MEMBERS: i64 = 50;
+
+#[derive(Serialize, FromRow)]
+pub struct Member {
+    tracker_id: uuid::Uuid,
+    user_name: String,
+    start_date: DateTime<Utc>,
+    units_per_day: f64,
+    last_relapse_at: Option<DateTime<Utc>>,
+    joined_at: DateTime<Utc>,
+}
+
+/// A server-side tracker as `/api/events` streams it to the browsers holding it.
+#[derive(Serialize, FromRow)]
+pub struct SharedTracker {
+    id: uuid::Uuid,
+    user_name: String,
+    start_date: DateTime<Utc>,
+    units_per_day: f64,
+    last_relapse_at: Option<DateTime<Utc>>,
+}
+
+impl SharedTracker {
+    pub async fn load(db: &sqlx::PgPool, id: uuid::Uuid) -> sqlx::Result<Option<(Self, Vec<String>)>> {
+        let Some(tracker) = sqlx::query_as::<_, Self>(
+            "SELECT id, user_name, start_date, units_per_day, last_relapse_at FROM trackers WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(db)
+        .await?
+        else {
+            return Ok(None);
+        };
+        let groups = sqlx::query_scalar(
+            "SELECT g.invite_code FROM group_members m JOIN groups g ON g.id = m.group_id WHERE m.tracker_id = $1"
+        )
+        .bind(id)
+        .fetch_all(db)
+        .await?;
+        Ok(Some((tracker, groups)))
+    }
+
+    pub fn id(&self) -> uuid::Uuid {
+        self.id
+    }
+}
+
+#[derive(Serialize, FromRow)]
+pub struct Group {
+    #[serde(skip)]
+    id: uuid::Uuid,
+    invite_code: String,
+    name: String,
+    habit_id: uuid::Uuid,
+    created_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    members: Vec<Member>,
+}
+
+#[derive(Serialize)]
+pub struct Membership {
+    group: Group,
+    /// Only sent when the tracker was first stored; the browser keeps it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tracker_secret: Option<String>,
+}
+
+fn bad_request(code: &'static str, field: Option<&'static str>, message: &str) -> ApiErrorResponse {
+    api_error(StatusCode::BAD_REQUEST, code, field, message)
+}
+
+fn not_found() -> ApiErrorResponse {
+    api_error(StatusCode::NOT_FOUND, "not_found", None, "No group with that invite link")
+}
+
+/// The fields of a tracker the server keeps.
+pub struct TrackerFields {
+    pub id: uuid::Uuid,
+    habit_id: uuid::Uuid,
+    user_name: String,
+    start_date: DateTime<Utc>,
+    units_per_day: f64,
+    last_relapse_at: Option<DateTime<Utc>>,
+}
+
+pub fn validate_tracker(proto: GroupTracker) -> Result<TrackerFields, ApiErrorResponse> {
+    let id = uuid::Uuid::parse_str(&proto.id).map_err(|_| bad_request("invalid_id", Some("id"), "Invalid tracker id"))?;
+    let habit_id = uuid::Uuid::parse_str(&proto.habit_id)
+        .map_err(|_| bad_request("invalid_id", Some("habit_id"), "Invalid habit id"))?;
+    let state = proto.state.unwrap_or_default();
+
+    let user_name = state.user_name.trim().to_string();
+    if user_name.is_empty() || user_name.chars().count() > MAX_USER_NAME_LEN {
+        return Err(bad_request("invalid", Some("user_name"), "Name must be 1 to 50 characters"));
+    }
+    if !state.units_per_day.is_finite() || !(0.0..=10_000.0).contains(&state.units_per_day) {
+        return Err(bad_request("invalid", Some("units_per_day"), "Units per day must be between 0 and 10000"));
+    }
+    let start_date = Utc
+        .timestamp_opt(state.start_timestamp, 0)
+        .single()
+        .filter(|d| *d <= Utc::now())
+        .ok_or_else(|| bad_request("invalid", Some("start_timestamp"), "Start date must not be in the future"))?;
+
+    let last_relapse_at = match proto.last_relapse_timestamp {
+        Some(ts) => Some(
+            Utc.timestamp_opt(ts, 0)
+                .single()
+                .filter(|d| *d <= Utc::now())
+                .ok_or_else(|| bad_request("invalid", Some("last_relapse_timestamp"), "Relapse must not be in the future"))?,
+        ),
+        None => None,
+    };
+
+    Ok(TrackerFields { id, habit_id, user_name, start_date, units_per_day: state.units_per_day, last_relapse_at })
+}
+
+/// Stores or updates the server copy of a tracker. Updating needs the secret
+/// handed out when it was stored; a new secret is returned for new trackers.
+pub async fn upsert_tracker(
+    tx: &mut Transaction<'_, Postgres>,
+    tracker: &TrackerFields,
+    secret: Option<&str>,
+) -> Result<Option<String>, ApiErrorResponse> {
+    let existing: Option<String> = sqlx::query_scalar("SELECT secret_hash FROM trackers WHERE id = $1 FOR UPDATE")
+        .bind(tracker.id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(internal_error)?;
+
+    match existing {
+        Some(hash) => {
+            if secret.map(hash_secret) != Some(hash) {
+                return Err(api_error(StatusCode::FORBIDDEN, "forbidden", None, "This tracker belongs to another browser"));
+            }
+            sqlx::query(
+                "UPDATE trackers SET user_name = $2, start_date = $3, units_per_day = $4, last_relapse_at = $5 WHERE id = $1"
+            )
+            .bind(tracker.id)
+            .bind(&tracker.user_name)
+            .bind(tracker.start_date)
+            .bind(tracker.units_per_day)
+            .bind(tracker.last_relapse_at)
+            .execute(&mut **tx)
+            .await
+            .map_err(internal_error)?;
+            Ok(None)
+        }
+        None => {
+            let secret = random_token(32);
+            let stored = sqlx::query(
+                "INSERT INTO trackers (id, habit_id, user_name, start_date, units_per_day, secret_hash, last_relapse_at) \
+                 SELECT $1, id, $3, $4, $5, $6, $7 FROM habits WHERE id = $2"
+            )
+            .bind(tracker.id)
+            .bind(tracker.habit_id)
+            .bind(&tracker.user_name)
+            .bind(tracker.start_date)
+            .bind(tracker.units_per_day)
+            .bind(hash_secret(&secret))
+            .bind(tracker.last_relapse_at)
+            .execute(&mut **tx)
+            .await
+            .map_err(internal_error)?
+            .rows_affected();
+            if stored == 0 {
+                return Err(bad_request("unknown_habit", Some("habit_id"), "Unknown habit"));
+            }
+            Ok(Some(secret))
+        }
+    }
+}
+
+pub fn secret_header(headers: &HeaderMap) -> Option<&str> {
+    headers.get(SECRET_HEADER).and_then(|v| v.to_str().ok())
+}
+
+async fn load_group(db: &sqlx::PgPool, code: &str) -> Result<Group, ApiErrorResponse> {
+    let mut group = sqlx::query_as::<_, Group>("SELECT id, invite_code, name, habit_id, created_at FROM groups WHERE invite_code = $1")
+        .bind(code)
+        .fetch_optional(db)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+
+    group.members = sqlx::query_as::<_, Member>(
+        "SELECT t.id AS tracker_id, t.user_name, t.start_date, t.units_per_day, t.last_relapse_at, m.joined_at \
+         FROM group_members m JOIN trackers t ON t.id = m.tracker_id \
+         WHERE m.group_id = $1 ORDER BY m.joined_at"
+    )
+    .bind(group.id)
+    .fetch_all(db)
+    .await
+    .map_err(internal_error)?;
+    Ok(group)
+}
+
+/// Creates a group for the creator's tracker's habit, with them as its first member.
+pub async fn create_group(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<Membership>), ApiErrorResponse> {
+    let proto = CreateGroup::decode(body).map_err(|_| bad_request("malformed", None, "Malformed group"))?;
+    let name = proto.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_GROUP_NAME_LEN {
+        return Err(bad_request("invalid", Some("name"), "Group name must be 1 to 60 characters"));
+    }
+    let tracker = validate_tracker(proto.tracker.unwrap_or_default())?;
+
+    let mut tx = state.db.begin().await.map_err(internal_error)?;
+    let tracker_secret = upsert_tracker(&mut tx, &tracker, secret_header(&headers)).await?;
+
+    let group_id = uuid::Uuid::new_v4();
+    let invite_code = random_token(12);
+    sqlx::query("INSERT INTO groups (id, invite_code, name, habit_id) VALUES ($1, $2, $3, $4)")
+        .bind(group_id)
+        .bind(&invite_code)
+        .bind(&name)
+        .bind(tracker.habit_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    sqlx::query("INSERT INTO group_members (group_id, tracker_id) VALUES ($1, $2)")
+        .bind(group_id)
+        .bind(tracker.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    let group = load_group(&state.db, &invite_code).await?;
+    Ok((StatusCode::CREATED, Json(Membership { group, tracker_secret })))
+}
+
+pub async fn get_group(State(state): State<AppState>, Path(code): Path<String>) -> Result<Json<Group>, ApiErrorResponse> {
+    Ok(Json(load_group(&state.db, &code).await?))
+}
+
+/// Joins a tracker to the group, or refreshes a member's tracker.
+pub async fn join_group(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Membership>, ApiErrorResponse> {
+    let proto = GroupTracker::decode(body).map_err(|_| bad_request("malformed", None, "Malformed tracker"))?;
+    let tracker = validate_tracker(proto)?;
+
+    let mut tx = state.db.begin().await.map_err(internal_error)?;
+    let (group_id, habit_id, members): (uuid::Uuid, uuid::Uuid, i64) = sqlx::query_as(
+        "SELECT id, habit_id, (SELECT COUNT(*) FROM group_members m WHERE m.group_id = groups.id) \
+         FROM groups WHERE invite_code = $1 FOR UPDATE"
+    )
+    .bind(&code)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?
+    .ok_or_else(not_found)?;
+
+    if tracker.habit_id != habit_id {
+        return Err(bad_request("habit_mismatch", Some("habit_id"), "This group tracks a different habit"));
+    }
+    let tracker_secret = upsert_tracker(&mut tx, &tracker, secret_header(&headers)).await?;
+
+    let joined = sqlx::query("INSERT INTO group_members (group_id, tracker_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(group_id)
+        .bind(tracker.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?
+        .rows_affected();
+    if joined == 1 && members >= MAX_MEMBERS {
+        return Err(api_error(StatusCode::CONFLICT, "group_full", None, format!("Groups have at most {} members", MAX_MEMBERS)));
+    }
+    tx.commit().await.map_err(internal_error)?;
+
+    let group = load_group(&state.db, &code).await?;
+    Ok(Json(Membership { group, tracker_secret }))
+}
+
+/// Refreshes the server copy of a tracker after it changed in the browser,
+/// e.g. a relapse, so its groups see it.
+pub async fn update_tracker(
+    State(state): State<AppState>,
+    Path(id): Path<uuid::Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, ApiErrorResponse> {
+    let proto = GroupTracker::decode(body).map_err(|_| bad_request("malformed", None, "Malformed tracker"))?;
+    let tracker = validate_tracker(proto)?;
+    if tracker.id != id {
+        return Err(bad_request("invalid_id", Some("id"), "Tracker id does not match the URL"));
+    }
+
+    let mut tx = state.db.begin().await.map_err(internal_error)?;
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM trackers WHERE id = $1)")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    if !exists {
+        return Err(api_error(StatusCode::NOT_FOUND, "not_found", None, "This tracker is not shared"));
+    }
+    upsert_tracker(&mut tx, &tracker, secret_header(&headers)).await?;
+    tx.commit().await.map_err(internal_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes a tracker's server copy once it is in no group and not on a leaderboard.
+pub async fn delete_if_unused(tx: &mut Transaction<'_, Postgres>, tracker_id: uuid::Uuid) -> Result<(), ApiErrorResponse> {
+    sqlx::query(
+        "DELETE FROM trackers t WHERE id = $1 AND leaderboard_nickname IS NULL \
+           AND NOT EXISTS (SELECT 1 FROM group_members m WHERE m.tracker_id = t.id)"
+    )
+    .bind(tracker_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(internal_error)?;
+    Ok(())
+}
+
+/// Removes a tracker from the group. Its server copy goes once nothing uses it.
+pub async fn leave_group(
+    State(state): State<AppState>,
+    Path((code, tracker_id)): Path<(String, uuid::Uuid)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiErrorResponse> {
+    let hash = secret_header(&headers).map(hash_secret).unwrap_or_default();
+    let mut tx = state.db.begin().await.map_err(internal_error)?;
+
+    let left = sqlx::query(
+        "DELETE FROM group_members m USING groups g, trackers t \
+         WHERE m.group_id = g.id AND m.tracker_id = t.id \
+           AND g.invite_code = $1 AND t.id = $2 AND t.secret_hash = $3"
+    )
+    .bind(&code)
+    .bind(tracker_id)
+    .bind(&hash)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?
+    .rows_affected();
+    if left == 0 {
+        return Err(not_found());
+    }
+
+    delete_if_unused(&mut tx, tracker_id).await?;
+    tx.commit().await.map_err(internal_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::tracker::TrackerState;
+
+    fn proto(start_timestamp: i64, units_per_day: f64, user_name: &str) -> GroupTracker {
+        GroupTracker {
+            id: uuid::Uuid::new_v4().to_string(),
+            habit_id: uuid::Uuid::new_v4().to_string(),
+            state: Some(TrackerState {
+                start_timestamp,
+                units_per_day,
+                theme_id: None,
+                user_name: user_name.to_string(),
+                visualizer_mode: None,
+                achievements: Vec::new(),
+                leaderboard_entry: None,
+                last_relapse_timestamp: None,
+            }),
+            last_relapse_timestamp: None,
+        }
+    }
+
+    #[test]
+    fn trackers_are_validated_before_storing() {
+        let now = Utc::now().timestamp();
+        let Ok(tracker) = validate_tracker(proto(now - 86_400, 2.0, "  Sam ")) else { panic!("valid tracker rejected") };
+        assert_eq!(tracker.user_name, "Sam");
+
+        assert!(validate_tracker(proto(now + 86_400, 2.0, "Sam")).is_err());
+        assert!(validate_tracker(proto(now, f64::NAN, "Sam")).is_err());
+        assert!(validate_tracker(proto(now, 2.0, " ")).is_err());
+        assert!(validate_tracker(GroupTracker { id: "nope".to_string(), ..proto(now, 2.0, "Sam") }).is_err());
+        let relapsed = GroupTracker { last_relapse_timestamp: Some(now + 86_400), ..proto(now - 86_400, 2.0, "Sam") };
+        assert!(validate_tracker(relapsed).is_err());
+    }
+}
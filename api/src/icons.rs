@@ -0,0 +1,250 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use shared::habit::MAX_ICON_IMAGE_BYTES;
+
+use crate::AppState;
+
+const MAX_PNG_DIMENSION: u32 = 512;
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+const SVG_ELEMENTS: &[&str] = &[
+    "svg", "g", "defs", "path", "circle", "ellipse", "rect", "line", "polyline", "polygon",
+    "linearGradient", "radialGradient", "stop",
+];
+
+const SVG_ATTRIBUTES: &[&str] = &[
+    "id", "viewBox", "preserveAspectRatio", "width", "height", "x", "y", "x1", "y1", "x2", "y2",
+    "cx", "cy", "r", "rx", "ry", "fx", "fy", "d", "points", "transform", "offset",
+    "fill", "fill-opacity", "fill-rule", "clip-rule", "stroke", "stroke-width", "stroke-opacity",
+    "stroke-linecap", "stroke-linejoin", "stroke-miterlimit", "stroke-dasharray", "opacity",
+    "stop-color", "stop-opacity", "gradientUnits", "gradientTransform", "spreadMethod",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum IconError {
+    TooLarge,
+    UnsupportedType,
+    Invalid(&'static str),
+}
+
+/// Stores an uploaded PNG or SVG icon for a habit. Icons are write-once, so a
+/// published habit can't have its icon swapped out from under its trackers.
+/// Only the habit's creator or an admin gets here; see `access`.
+pub async fn upload_icon(
+    State(state): State<AppState>,
+    Path(id): Path<uuid::Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    let (content_type, data) = match sanitize_icon(content_type, &body) {
+        Ok(icon) => icon,
+        Err(IconError::TooLarge) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Icons must be at most {} KB", MAX_ICON_IMAGE_BYTES / 1024),
+            )
+                .into_response();
+        }
+        Err(IconError::UnsupportedType) => {
+            return (StatusCode::UNSUPPORTED_MEDIA_TYPE, "Icons must be PNG or SVG").into_response();
+        }
+        Err(IconError::Invalid(reason)) => return (StatusCode::BAD_REQUEST, reason).into_response(),
+    };
+
+    let result = sqlx::query(
+        "INSERT INTO habit_icons (habit_id, content_type, data) SELECT id, $2, $3 FROM habits WHERE id = $1 \
+         ON CONFLICT DO NOTHING"
+    )
+    .bind(id)
+    .bind(content_type)
+    .bind(data)
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(done) if done.rows_affected() == 1 => StatusCode::CREATED.into_response(),
+        Ok(_) => (StatusCode::CONFLICT, "Habit is missing or already has an icon").into_response(),
+        Err(e) => {
+            println!("Error saving habit icon: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub async fn get_icon(State(state): State<AppState>, Path(id): Path<uuid::Uuid>) -> Response {
+    let result = sqlx::query_as::<_, (String, Vec<u8>)>(
+        "SELECT content_type, data FROM habit_icons WHERE habit_id = $1"
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await;
+
+    match result {
+        Ok(Some((content_type, data))) => (
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::CACHE_CONTROL, "public, max-age=604800, immutable".to_string()),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+                // Belt and braces in case the SVG is opened directly.
+                (header::CONTENT_SECURITY_POLICY, "default-src 'none'; style-src 'unsafe-inline'".to_string()),
+            ],
+            data,
+        )
+            .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            println!("Error fetching habit icon: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Checks an upload and returns the content type and bytes to store.
+fn sanitize_icon(content_type: &str, data: &[u8]) -> Result<(&'static str, Vec<u8>), IconError> {
+    if data.len() > MAX_ICON_IMAGE_BYTES {
+        return Err(IconError::TooLarge);
+    }
+    match content_type.split(';').next().unwrap_or_default().trim() {
+        "image/png" => {
+            validate_png(data)?;
+            Ok(("image/png", data.to_vec()))
+        }
+        "image/svg+xml" => {
+            let text = std::str::from_utf8(data).map_err(|_| IconError::Invalid("SVG must be UTF-8"))?;
+            Ok(("image/svg+xml", sanitize_svg(text)?.into_bytes()))
+        }
+        _ => Err(IconError::UnsupportedType),
+    }
+}
+
+fn validate_png(data: &[u8]) -> Result<(), IconError> {
+    let Some(rest) = data.strip_prefix(PNG_SIGNATURE) else {
+        return Err(IconError::Invalid("Not a PNG file"));
+    };
+    // The first chunk must be IHDR: length(4) type(4) width(4) height(4).
+    if rest.len() < 16 || &rest[4..8] != b"IHDR" {
+        return Err(IconError::Invalid("PNG header is missing"));
+    }
+    let width = u32::from_be_bytes([rest[8], rest[9], rest[10], rest[11]]);
+    let height = u32::from_be_bytes([rest[12], rest[13], rest[14], rest[15]]);
+    if width == 0 || height == 0 || width > MAX_PNG_DIMENSION || height > MAX_PNG_DIMENSION {
+        return Err(IconError::Invalid("PNG icons must be at most 512x512 pixels"));
+    }
+    Ok(())
+}
+
+/// Rebuilds the SVG from an allow-list of shape elements and presentation
+/// attributes. Scripts, styles, links, foreign objects and external
+/// references are dropped rather than escaped.
+fn sanitize_svg(text: &str) -> Result<String, IconError> {
+    // DTDs (and with them entity expansion) are rejected by the parser's defaults.
+    let doc = roxmltree::Document::parse(text).map_err(|_| IconError::Invalid("SVG is not well-formed XML"))?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "svg" {
+        return Err(IconError::Invalid("Root element must be <svg>"));
+    }
+
+    let mut out = String::new();
+    write_element(root, &mut out);
+    Ok(out)
+}
+
+fn write_element(node: roxmltree::Node, out: &mut String) {
+    let name = node.tag_name().name();
+    let in_svg_namespace = node
+        .tag_name()
+        .namespace()
+        .is_none_or(|ns| ns == "http://www.w3.org/2000/svg");
+    if !in_svg_namespace || !SVG_ELEMENTS.contains(&name) {
+        return;
+    }
+
+    out.push('<');
+    out.push_str(name);
+    if name == "svg" && node.parent_element().is_none() {
+        out.push_str(" xmlns=\"http://www.w3.org/2000/svg\"");
+    }
+    for attr in node.attributes() {
+        if attr.namespace().is_some() || !SVG_ATTRIBUTES.contains(&attr.name()) || !is_safe_value(attr.value()) {
+            continue;
+        }
+        out.push_str(&format!(" {}=\"{}\"", attr.name(), escape(attr.value())));
+    }
+    out.push('>');
+    for child in node.children().filter(|c| c.is_element()) {
+        write_element(child, out);
+    }
+    out.push_str(&format!("</{}>", name));
+}
+
+/// Only same-document references such as `url(#gradient)` may appear in values.
+fn is_safe_value(value: &str) -> bool {
+    let lower = value.to_ascii_lowercase();
+    if lower.contains("javascript:") || lower.contains("data:") {
+        return false;
+    }
+    let mut rest = lower.as_str();
+    while let Some(i) = rest.find("url(") {
+        rest = &rest[i + 4..];
+        if !rest.trim_start().trim_start_matches(['"', '\'']).starts_with('#') {
+            return false;
+        }
+    }
+    true
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svg_keeps_shapes_and_drops_active_content() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" viewBox="0 0 10 10" onload="alert(1)">
+            <script>alert(1)</script>
+            <style>circle { fill: url(https://evil.example/x) }</style>
+            <circle cx="5" cy="5" r="4" fill="url(#g)" stroke="url(https://evil.example/y)"/>
+            <a xlink:href="javascript:alert(1)"><rect width="1" height="1"/></a>
+            <image href="https://evil.example/z.png"/>
+            <foreignObject><div xmlns="http://www.w3.org/1999/xhtml">x</div></foreignObject>
+        </svg>"##;
+        let clean = sanitize_svg(svg).unwrap();
+        assert_eq!(
+            clean,
+            r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><circle cx="5" cy="5" r="4" fill="url(#g)"></circle></svg>"##
+        );
+    }
+
+    #[test]
+    fn svg_with_entities_is_rejected() {
+        let svg = r#"<!DOCTYPE svg [<!ENTITY a "aaaa">]><svg xmlns="http://www.w3.org/2000/svg">&a;</svg>"#;
+        assert!(sanitize_svg(svg).is_err());
+    }
+
+    #[test]
+    fn png_must_have_a_small_header() {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(&[0, 0, 0, 13]);
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&64u32.to_be_bytes());
+        png.extend_from_slice(&64u32.to_be_bytes());
+        assert_eq!(validate_png(&png), Ok(()));
+
+        png[16..20].copy_from_slice(&4096u32.to_be_bytes());
+        assert!(validate_png(&png).is_err());
+        assert!(validate_png(b"GIF89a").is_err());
+        assert_eq!(sanitize_icon("image/gif", b"GIF89a"), Err(IconError::UnsupportedType));
+    }
+}
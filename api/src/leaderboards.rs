@@ -0,0 +1,228 @@
+//! Opt-in public leaderboards, one per habit. A tracker is listed under a
+//! nickname only; its name, id and group memberships stay private.
+
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use shared::tracker::PublishTracker;
+use sqlx::FromRow;
+use std::net::SocketAddr;
+
+use crate::groups::{delete_if_unused, secret_header, upsert_tracker, validate_tracker};
+use crate::{api_error, client, internal_error, ApiErrorResponse, AppState};
+
+const MAX_NICKNAME_LEN: usize = 24;
+const MAX_ENTRIES: i64 = 100;
+/// Reports from different clients that take an entry off the leaderboard.
+const FLAGS_TO_HIDE: i64 = 3;
+const FLAGS_PER_HOUR: i64 = 10;
+
+#[derive(Serialize, FromRow)]
+pub struct Entry {
+    entry_id: uuid::Uuid,
+    nickname: String,
+    start_date: DateTime<Utc>,
+    units_per_day: f64,
+    /// The start date, or the last relapse when there was one since.
+    clean_since: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct Publication {
+    entry_id: uuid::Uuid,
+    /// Only sent when the tracker was first stored; the browser keeps it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tracker_secret: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    sort: Option<String>,
+}
+
+/// Nicknames are the only identity on a leaderboard, so they stay short and
+/// plain: letters, digits, spaces and `-_.`, and no links.
+fn validate_nickname(nickname: &str) -> Result<String, ApiErrorResponse> {
+    let nickname = nickname.trim();
+    let invalid = |message: &str| api_error(StatusCode::BAD_REQUEST, "invalid", Some("nickname"), message);
+    let len = nickname.chars().count();
+    if !(2..=MAX_NICKNAME_LEN).contains(&len) {
+        return Err(invalid("Nickname must be 2 to 24 characters"));
+    }
+    if !nickname.chars().all(|c| c.is_alphanumeric() || c == ' ' || "-_.".contains(c)) {
+        return Err(invalid("Nickname may only use letters, digits, spaces and - _ ."));
+    }
+    let lower = nickname.to_lowercase();
+    if lower.contains("http") || lower.contains("www.") {
+        return Err(invalid("Nickname must not be a link"));
+    }
+    Ok(nickname.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// The habit's leaderboard, by current streak (the default) or units avoided.
+pub async fn get_leaderboard(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<Json<Vec<Entry>>, ApiErrorResponse> {
+    let habit_id: uuid::Uuid = sqlx::query_scalar("SELECT id FROM habits WHERE slug = $1")
+        .bind(&slug)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "not_found", None, "No habit with that slug"))?;
+
+    let order = match query.sort.as_deref() {
+        Some("units") => "EXTRACT(EPOCH FROM NOW() - start_date) * units_per_day DESC",
+        _ => "clean_since ASC",
+    };
+    let sql = format!(
+        "SELECT leaderboard_id AS entry_id, leaderboard_nickname AS nickname, start_date, units_per_day, \
+            GREATEST(start_date, COALESCE(last_relapse_at, start_date)) AS clean_since \
+         FROM trackers t \
+         WHERE habit_id = $1 AND leaderboard_nickname IS NOT NULL \
+           AND (SELECT COUNT(*) FROM leaderboard_flags f WHERE f.tracker_id = t.id) < $2 \
+         ORDER BY {} LIMIT $3",
+        order
+    );
+    let entries = sqlx::query_as::<_, Entry>(&sql)
+        .bind(habit_id)
+        .bind(FLAGS_TO_HIDE)
+        .bind(MAX_ENTRIES)
+        .fetch_all(&state.db)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(entries))
+}
+
+/// Lists the tracker on its habit's leaderboard, or renames its entry.
+pub async fn publish(
+    State(state): State<AppState>,
+    Path(id): Path<uuid::Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Publication>, ApiErrorResponse> {
+    let proto = PublishTracker::decode(body)
+        .map_err(|_| api_error(StatusCode::BAD_REQUEST, "malformed", None, "Malformed tracker"))?;
+    let nickname = validate_nickname(&proto.nickname)?;
+    let tracker = validate_tracker(proto.tracker.unwrap_or_default())?;
+    if tracker.id != id {
+        return Err(api_error(StatusCode::BAD_REQUEST, "invalid_id", Some("id"), "Tracker id does not match the URL"));
+    }
+
+    let mut tx = state.db.begin().await.map_err(internal_error)?;
+    let tracker_secret = upsert_tracker(&mut tx, &tracker, secret_header(&headers)).await?;
+    let entry_id = sqlx::query_scalar(
+        "UPDATE trackers SET leaderboard_nickname = $2, leaderboard_id = COALESCE(leaderboard_id, $3) \
+         WHERE id = $1 RETURNING leaderboard_id"
+    )
+    .bind(id)
+    .bind(&nickname)
+    .bind(uuid::Uuid::new_v4())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(Publication { entry_id, tracker_secret }))
+}
+
+/// Takes the tracker off the leaderboard. Its reports go with it.
+pub async fn unpublish(
+    State(state): State<AppState>,
+    Path(id): Path<uuid::Uuid>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiErrorResponse> {
+    let hash = secret_header(&headers).map(crate::secrets::hash_secret).unwrap_or_default();
+    let mut tx = state.db.begin().await.map_err(internal_error)?;
+
+    let removed = sqlx::query(
+        "UPDATE trackers SET leaderboard_nickname = NULL, leaderboard_id = NULL \
+         WHERE id = $1 AND secret_hash = $2 AND leaderboard_nickname IS NOT NULL"
+    )
+    .bind(id)
+    .bind(&hash)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?
+    .rows_affected();
+    if removed == 0 {
+        return Err(api_error(StatusCode::NOT_FOUND, "not_found", None, "This tracker is not on a leaderboard"));
+    }
+
+    sqlx::query("DELETE FROM leaderboard_flags WHERE tracker_id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    delete_if_unused(&mut tx, id).await?;
+    tx.commit().await.map_err(internal_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Reports an entry, e.g. for an offensive nickname or made-up numbers.
+pub async fn flag_entry(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(entry_id): Path<uuid::Uuid>,
+) -> Result<StatusCode, ApiErrorResponse> {
+    let ip = client::client_ip(&headers, peer, state.trust_proxy_headers);
+    let reporter = client::anonymous_id(ip, &state.vote_salt);
+
+    let recent: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM leaderboard_flags WHERE reporter = $1 AND created_at > NOW() - INTERVAL '1 hour'"
+    )
+    .bind(&reporter)
+    .fetch_one(&state.db)
+    .await
+    .map_err(internal_error)?;
+    if recent >= FLAGS_PER_HOUR {
+        return Err(api_error(StatusCode::TOO_MANY_REQUESTS, "rate_limited", None, "Too many reports, try again later"));
+    }
+
+    let found = sqlx::query(
+        "INSERT INTO leaderboard_flags (tracker_id, reporter) \
+         SELECT id, $2 FROM trackers WHERE leaderboard_id = $1 ON CONFLICT DO NOTHING"
+    )
+    .bind(entry_id)
+    .bind(&reporter)
+    .execute(&state.db)
+    .await
+    .map_err(internal_error)?
+    .rows_affected();
+    if found == 0 {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM trackers WHERE leaderboard_id = $1)")
+            .bind(entry_id)
+            .fetch_one(&state.db)
+            .await
+            .map_err(internal_error)?;
+        if !exists {
+            return Err(api_error(StatusCode::NOT_FOUND, "not_found", None, "No such leaderboard entry"));
+        }
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nicknames_are_short_plain_and_linkless() {
+        let valid = |n: &str| validate_nickname(n).ok();
+        assert_eq!(valid("  Sober   Sam "), Some("Sober Sam".to_string()));
+        assert_eq!(valid("ana_b.2"), Some("ana_b.2".to_string()));
+        assert_eq!(valid("Zoë"), Some("Zoë".to_string()));
+        assert_eq!(valid("x"), None);
+        assert_eq!(valid(&"a".repeat(25)), None);
+        assert_eq!(valid("<b>hi</b>"), None);
+        assert_eq!(valid("www.spam.com"), None);
+        assert_eq!(valid("HTTPbin"), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Proof of work on anonymous writes: login links, groups, leaderboard
+//! entries and encouragement. See `shared::pow` for the puzzle itself.
+//!
+//! Challenges are `<issued>.<mac>`, signed so the server needn't remember
+//! handing them out. It does remember the ones spent, until they expire, so
+//! each buys one request; that memory is per instance. `POW_DIFFICULTY` sets
+//! the zero bits asked for (0 turns the check off); instances behind one load
+//! balancer must share `POW_SECRET`.
+
+use axum::{
+    body::Body,
+    extract::{Json, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+use shared::pow::{Challenge, HEADER};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::{api_error, ApiErrorResponse, AppState};
+
+const DEFAULT_DIFFICULTY: u32 = 18;
+/// How long a challenge may be worked on and used.
+const CHALLENGE_SECS: i64 = 10 * 60;
+
+pub struct ProofOfWork {
+    key: Vec<u8>,
+    difficulty: u32,
+    /// Spent challenges and when they were issued.
+    spent: Mutex<HashMap<String, i64>>,
+}
+
+impl ProofOfWork {
+    pub fn from_env() -> Self {
+        let difficulty = std::env::var("POW_DIFFICULTY")
+            .ok()
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(DEFAULT_DIFFICULTY);
+        let key = match std::env::var("POW_SECRET") {
+            Ok(secret) => secret.into_bytes(),
+            Err(_) => {
+                let mut key = vec![0u8; 32];
+                OsRng.fill_bytes(&mut key);
+                key
+            }
+        };
+        Self::new(key, difficulty)
+    }
+
+    fn new(key: Vec<u8>, difficulty: u32) -> Self {
+        Self { key, difficulty, spent: Mutex::new(HashMap::new()) }
+    }
+
+    fn mac(&self, issued: i64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key length");
+        mac.update(issued.to_string().as_bytes());
+        mac
+    }
+
+    fn issue(&self, now: i64) -> Challenge {
+        let mac = URL_SAFE_NO_PAD.encode(self.mac(now).finalize().into_bytes());
+        Challenge { challenge: format!("{}.{}", now, mac), difficulty: self.difficulty }
+    }
+
+    /// Checks the proof sent for `body` to `method` `path`, as of `now`, and
+    /// spends its challenge.
+    fn check(&self, header: Option<&str>, method: &str, path: &str, body: &[u8], now: i64) -> Result<(), &'static str> {
+        let (challenge, nonce) = header.and_then(shared::pow::parse_header).ok_or("Proof of work required")?;
+        let (issued, mac) = challenge.split_once('.').ok_or("Invalid challenge")?;
+        let issued: i64 = issued.parse().map_err(|_| "Invalid challenge")?;
+        let mac = URL_SAFE_NO_PAD.decode(mac).map_err(|_| "Invalid challenge")?;
+        self.mac(issued).verify_slice(&mac).map_err(|_| "Invalid challenge")?;
+        if !(0..=CHALLENGE_SECS).contains(&(now - issued)) {
+            return Err("Challenge expired, try again");
+        }
+        if !shared::pow::verify(challenge, nonce, self.difficulty, method, path, body) {
+            return Err("Proof of work doesn't match the request");
+        }
+
+        let mut spent = self.spent.lock().unwrap();
+        spent.retain(|_, issued| now - *issued <= CHALLENGE_SECS);
+        if spent.insert(challenge.to_string(), issued).is_some() {
+            return Err("Challenge already used, try again");
+        }
+        Ok(())
+    }
+}
+
+pub async fn challenge(State(state): State<AppState>) -> Json<Challenge> {
+    Json(state.pow.issue(chrono::Utc::now().timestamp()))
+}
+
+/// Turns away requests without a solved challenge for their exact body.
+pub async fn require(State(state): State<AppState>, request: Request, next: Next) -> Result<Response, ApiErrorResponse> {
+    if state.pow.difficulty == 0 {
+        return Ok(next.run(request).await);
+    }
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, state.max_body_bytes)
+        .await
+        .map_err(|_| api_error(StatusCode::PAYLOAD_TOO_LARGE, "too_large", None, "Request body is too large"))?;
+    let header = parts.headers.get(HEADER).and_then(|v| v.to_str().ok());
+    state
+        .pow
+        .check(header, parts.method.as_str(), parts.uri.path(), &body, chrono::Utc::now().timestamp())
+        .map_err(|message| api_error(StatusCode::FORBIDDEN, "proof_of_work", None, message))?;
+    Ok(next.run(Request::from_parts(parts, Body::from(body))).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenges_are_signed_and_expire() {
+        let pow = ProofOfWork::new(b"secret".to_vec(), 4);
+        let issued = pow.issue(1_000);
+        let nonce = shared::pow::solve(&issued.challenge, issued.difficulty, "POST", "/api/groups", b"body");
+        let header = shared::pow::header_value(&issued.challenge, nonce);
+        let check = |pow: &ProofOfWork, header: Option<&str>, now| pow.check(header, "POST", "/api/groups", b"body", now);
+
+        assert_eq!(check(&pow, None, 1_060), Err("Proof of work required"));
+        assert_eq!(check(&pow, Some(&header), 1_000 + CHALLENGE_SECS + 1), Err("Challenge expired, try again"));
+        let forged = header.replacen("1000.", "2000.", 1);
+        assert_eq!(check(&pow, Some(&forged), 2_000), Err("Invalid challenge"));
+        let other_key = ProofOfWork::new(b"other".to_vec(), 4);
+        assert_eq!(check(&other_key, Some(&header), 1_060), Err("Invalid challenge"));
+
+        assert_eq!(check(&pow, Some(&header), 1_060), Ok(()));
+        assert_eq!(check(&pow, Some(&header), 1_061), Err("Challenge already used, try again"));
+    }
+
+    #[test]
+    fn spent_challenges_are_forgotten_once_expired() {
+        let pow = ProofOfWork::new(b"secret".to_vec(), 0);
+        for issued in [1_000, 1_100] {
+            let challenge = pow.issue(issued).challenge;
+            let header = shared::pow::header_value(&challenge, 0);
+            assert_eq!(pow.check(Some(&header), "POST", "/api/groups", b"", issued), Ok(()));
+        }
+        assert_eq!(pow.spent.lock().unwrap().len(), 2);
+        let header = shared::pow::header_value(&pow.issue(1_700).challenge, 0);
+        assert_eq!(pow.check(Some(&header), "POST", "/api/groups", b"", 1_700), Ok(()));
+        assert_eq!(pow.spent.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,435 @@
+//! Web Push: milestone notifications that arrive while the app is closed.
+//!
+//! Browsers subscribe per tracker with the schedule the milestones follow; a
+//! background task sends whatever is due, signed with the server's VAPID key
+//! (RFC 8292) and encrypted for the browser (RFC 8291).
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes128Gcm, Nonce};
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use hkdf::Hkdf;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::{PublicKey, SecretKey};
+use rand_core::{OsRng, RngCore};
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::Sha256;
+use shared::milestones::{QuietHours, Schedule};
+use sqlx::{FromRow, Pool, Postgres};
+
+use crate::{api_error, internal_error, ApiErrorResponse, AppState};
+
+/// Push services of the major browsers. `PUSH_ENDPOINTS` replaces the list,
+/// e.g. with a local mock push service for testing.
+const DEFAULT_ENDPOINTS: &[&str] = &[
+    "https://fcm.googleapis.com",
+    "https://updates.push.services.mozilla.com",
+    "https://web.push.apple.com",
+    "https://*.notify.windows.com",
+];
+const SEND_INTERVAL_SECS: u64 = 60;
+/// How long the push service keeps a message for an offline browser.
+const TTL_SECS: u32 = 24 * 60 * 60;
+const RECORD_SIZE: u32 = 4096;
+const MAX_ENDPOINT_LEN: usize = 1024;
+const MAX_NAME_LEN: usize = 100;
+
+pub struct PushService {
+    key: SecretKey,
+    /// The uncompressed public key, base64url; browsers subscribe with it.
+    public_key: String,
+    subject: String,
+    endpoints: Vec<String>,
+    client: reqwest::Client,
+}
+
+impl PushService {
+    /// Loads the VAPID key from `VAPID_PRIVATE_KEY` or the database, creating
+    /// one on first start so subscriptions survive restarts.
+    pub async fn load(db: &Pool<Postgres>, public_url: &str) -> Result<Self, String> {
+        let encoded = match std::env::var("VAPID_PRIVATE_KEY") {
+            Ok(key) => key,
+            Err(_) => {
+                let generated = URL_SAFE_NO_PAD.encode(SecretKey::random(&mut OsRng).to_bytes());
+                // Several instances may start at once; the first key stored wins.
+                sqlx::query("INSERT INTO vapid_keys (private_key) VALUES ($1) ON CONFLICT DO NOTHING")
+                    .bind(&generated)
+                    .execute(db)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                sqlx::query_scalar("SELECT private_key FROM vapid_keys")
+                    .fetch_one(db)
+                    .await
+                    .map_err(|e| e.to_string())?
+            }
+        };
+        let key = decode_base64(&encoded)
+            .and_then(|bytes| SecretKey::from_slice(&bytes).ok())
+            .ok_or("VAPID private key must be a base64url P-256 scalar")?;
+
+        let endpoints = match std::env::var("PUSH_ENDPOINTS") {
+            Ok(list) => list.split(',').map(|e| e.trim().trim_end_matches('/').to_string()).filter(|e| !e.is_empty()).collect(),
+            Err(_) => DEFAULT_ENDPOINTS.iter().map(|e| e.to_string()).collect(),
+        };
+
+        Ok(Self {
+            public_key: URL_SAFE_NO_PAD.encode(key.public_key().to_encoded_point(false).as_bytes()),
+            key,
+            subject: std::env::var("VAPID_SUBJECT").unwrap_or_else(|_| public_url.to_string()),
+            endpoints,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// The `Authorization` header for a push to `endpoint`, valid for 12 hours.
+    fn authorization(&self, endpoint: &Url, now: DateTime<Utc>) -> String {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"ES256"}"#);
+        let claims = serde_json::json!({
+            "aud": endpoint.origin().ascii_serialization(),
+            "exp": (now + Duration::hours(12)).timestamp(),
+            "sub": self.subject,
+        });
+        let unsigned = format!("{}.{}", header, URL_SAFE_NO_PAD.encode(claims.to_string()));
+        let signature: Signature = SigningKey::from(&self.key).sign(unsigned.as_bytes());
+        format!("vapid t={}.{}, k={}", unsigned, URL_SAFE_NO_PAD.encode(signature.to_bytes()), self.public_key)
+    }
+}
+
+/// Whether `endpoint` belongs to one of the allowed push services. Entries
+/// are origins; `https://*.example.com` allows any subdomain.
+fn endpoint_allowed(endpoint: &Url, allowed: &[String]) -> bool {
+    let Some(host) = endpoint.host_str() else { return false };
+    let authority = match endpoint.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+    allowed.iter().any(|entry| {
+        let Some((scheme, rest)) = entry.split_once("://") else { return false };
+        if scheme != endpoint.scheme() {
+            return false;
+        }
+        match rest.strip_prefix("*.") {
+            Some(suffix) => authority.ends_with(&format!(".{}", suffix)),
+            None => authority == rest,
+        }
+    })
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    // Browsers differ on padding.
+    URL_SAFE_NO_PAD.decode(s.trim_end_matches('=')).ok()
+}
+
+/// Encrypts `plaintext` for a browser with the `aes128gcm` content coding,
+/// as a single record, using the ephemeral key `local` and `salt`.
+fn encrypt(ua_public: &[u8], auth: &[u8], plaintext: &[u8], local: &SecretKey, salt: [u8; 16]) -> Option<Vec<u8>> {
+    let ua_key = PublicKey::from_sec1_bytes(ua_public).ok()?;
+    let local_public = local.public_key().to_encoded_point(false);
+    let shared = p256::ecdh::diffie_hellman(local.to_nonzero_scalar(), ua_key.as_affine());
+
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(ua_public);
+    key_info.extend_from_slice(local_public.as_bytes());
+    let mut ikm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(auth), shared.raw_secret_bytes()).expand(&key_info, &mut ikm).ok()?;
+
+    let prk = Hkdf::<Sha256>::new(Some(&salt), &ikm);
+    let (mut cek, mut nonce) = ([0u8; 16], [0u8; 12]);
+    prk.expand(b"Content-Encoding: aes128gcm\0", &mut cek).ok()?;
+    prk.expand(b"Content-Encoding: nonce\0", &mut nonce).ok()?;
+
+    // A 0x02 delimiter marks the last (and only) record.
+    let mut record = plaintext.to_vec();
+    record.push(2);
+    let ciphertext = Aes128Gcm::new_from_slice(&cek).ok()?.encrypt(Nonce::from_slice(&nonce), record.as_ref()).ok()?;
+
+    let mut body = salt.to_vec();
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(local_public.as_bytes().len() as u8);
+    body.extend_from_slice(local_public.as_bytes());
+    body.extend_from_slice(&ciphertext);
+    Some(body)
+}
+
+#[derive(Deserialize)]
+pub struct SubscriptionKeys {
+    p256dh: String,
+    auth: String,
+}
+
+/// A browser `PushSubscription` as serialized by `JSON.stringify`.
+#[derive(Deserialize)]
+pub struct Subscription {
+    endpoint: String,
+    keys: SubscriptionKeys,
+}
+
+#[derive(Deserialize)]
+pub struct SubscribeRequest {
+    subscription: Subscription,
+    tracker_id: uuid::Uuid,
+    habit_name: String,
+    habit_slug: String,
+    unit_name: String,
+    start_date: DateTime<Utc>,
+    units_per_day: f64,
+    quiet_start: u8,
+    quiet_end: u8,
+    utc_offset_minutes: i32,
+}
+
+/// Subscribes a browser to a tracker's milestones, or refreshes the schedule
+/// of an existing subscription.
+pub async fn subscribe(State(state): State<AppState>, Json(req): Json<SubscribeRequest>) -> Result<StatusCode, ApiErrorResponse> {
+    let invalid = |field, message: &str| api_error(StatusCode::BAD_REQUEST, "invalid", Some(field), message);
+
+    let endpoint = Url::parse(&req.subscription.endpoint).map_err(|_| invalid("endpoint", "Invalid push endpoint"))?;
+    if req.subscription.endpoint.len() > MAX_ENDPOINT_LEN || !endpoint_allowed(&endpoint, &state.push.endpoints) {
+        return Err(invalid("endpoint", "Unsupported push service"));
+    }
+    if decode_base64(&req.subscription.keys.p256dh).and_then(|k| PublicKey::from_sec1_bytes(&k).ok()).is_none() {
+        return Err(invalid("p256dh", "Invalid subscription key"));
+    }
+    if decode_base64(&req.subscription.keys.auth).is_none_or(|a| a.len() != 16) {
+        return Err(invalid("auth", "Invalid subscription secret"));
+    }
+    for (field, value) in [("habit_name", &req.habit_name), ("habit_slug", &req.habit_slug), ("unit_name", &req.unit_name)] {
+        if value.trim().is_empty() || value.chars().count() > MAX_NAME_LEN {
+            return Err(invalid(field, "Must be 1 to 100 characters"));
+        }
+    }
+    if !req.units_per_day.is_finite() || !(0.0..=10_000.0).contains(&req.units_per_day) {
+        return Err(invalid("units_per_day", "Units per day must be between 0 and 10000"));
+    }
+    if req.quiet_start > 23 || req.quiet_end > 23 {
+        return Err(invalid("quiet_start", "Quiet hours must be between 0 and 23"));
+    }
+    if FixedOffset::east_opt(req.utc_offset_minutes.saturating_mul(60)).is_none() {
+        return Err(invalid("utc_offset_minutes", "Invalid UTC offset"));
+    }
+
+    // A new subscription starts counting from now so past milestones aren't replayed.
+    sqlx::query(
+        "INSERT INTO push_subscriptions \
+            (endpoint, tracker_id, p256dh, auth, habit_name, habit_slug, unit_name, start_date, units_per_day, \
+             quiet_start, quiet_end, utc_offset_minutes, last_notified) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, NOW()) \
+         ON CONFLICT (endpoint, tracker_id) DO UPDATE SET \
+            p256dh = $3, auth = $4, habit_name = $5, habit_slug = $6, unit_name = $7, start_date = $8, \
+            units_per_day = $9, quiet_start = $10, quiet_end = $11, utc_offset_minutes = $12"
+    )
+    .bind(&req.subscription.endpoint)
+    .bind(req.tracker_id)
+    .bind(&req.subscription.keys.p256dh)
+    .bind(&req.subscription.keys.auth)
+    .bind(req.habit_name.trim())
+    .bind(req.habit_slug.trim())
+    .bind(req.unit_name.trim())
+    .bind(req.start_date)
+    .bind(req.units_per_day)
+    .bind(req.quiet_start as i16)
+    .bind(req.quiet_end as i16)
+    .bind(req.utc_offset_minutes)
+    .execute(&state.db)
+    .await
+    .map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct UnsubscribeRequest {
+    endpoint: String,
+    tracker_id: uuid::Uuid,
+}
+
+pub async fn unsubscribe(State(state): State<AppState>, Json(req): Json<UnsubscribeRequest>) -> StatusCode {
+    let result = sqlx::query("DELETE FROM push_subscriptions WHERE endpoint = $1 AND tracker_id = $2")
+        .bind(&req.endpoint)
+        .bind(req.tracker_id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(e) => {
+            println!("Error removing push subscription: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+pub async fn vapid_public_key(State(state): State<AppState>) -> String {
+    state.push.public_key.clone()
+}
+
+#[derive(FromRow)]
+struct SubscriptionRow {
+    endpoint: String,
+    tracker_id: uuid::Uuid,
+    p256dh: String,
+    auth: String,
+    habit_name: String,
+    habit_slug: String,
+    unit_name: String,
+    start_date: DateTime<Utc>,
+    units_per_day: f64,
+    quiet_start: i16,
+    quiet_end: i16,
+    utc_offset_minutes: i32,
+    last_notified: DateTime<Utc>,
+}
+
+enum SendError {
+    /// The browser unsubscribed or the subscription expired.
+    Gone,
+    Failed(String),
+}
+
+/// Checks for due milestones once a minute for as long as the server runs.
+pub fn spawn_sender(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(SEND_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if let Err(e) = send_due(&state, Utc::now()).await {
+                println!("Error sending milestone pushes: {:?}", e);
+            }
+        }
+    });
+}
+
+async fn send_due(state: &AppState, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+    let rows = sqlx::query_as::<_, SubscriptionRow>("SELECT * FROM push_subscriptions").fetch_all(&state.db).await?;
+
+    for row in rows {
+        let schedule = Schedule { start: row.start_date, units_per_day: row.units_per_day };
+        let quiet = QuietHours { start: row.quiet_start as u8, end: row.quiet_end as u8 };
+        let offset = FixedOffset::east_opt(row.utc_offset_minutes * 60).unwrap_or(FixedOffset::east_opt(0).unwrap());
+        let Some(milestone) = schedule.due(quiet, row.last_notified, now, offset) else { continue };
+
+        let payload = serde_json::json!({
+            "title": milestone.title(&row.habit_name, &row.unit_name),
+            "tag": row.tracker_id,
+            "url": format!("/{}", row.habit_slug),
+        });
+        match send(&state.push, &row, payload.to_string().as_bytes(), now).await {
+            Ok(()) => {
+                sqlx::query("UPDATE push_subscriptions SET last_notified = $3 WHERE endpoint = $1 AND tracker_id = $2")
+                    .bind(&row.endpoint)
+                    .bind(row.tracker_id)
+                    .bind(milestone.at)
+                    .execute(&state.db)
+                    .await?;
+            }
+            Err(SendError::Gone) => {
+                sqlx::query("DELETE FROM push_subscriptions WHERE endpoint = $1")
+                    .bind(&row.endpoint)
+                    .execute(&state.db)
+                    .await?;
+            }
+            // Left as is, so the next run tries again.
+            Err(SendError::Failed(e)) => println!("Push to {} failed: {}", row.endpoint, e),
+        }
+    }
+    Ok(())
+}
+
+async fn send(push: &PushService, row: &SubscriptionRow, payload: &[u8], now: DateTime<Utc>) -> Result<(), SendError> {
+    let endpoint = Url::parse(&row.endpoint).map_err(|_| SendError::Gone)?;
+    // The allow-list may have changed since the browser subscribed.
+    if !endpoint_allowed(&endpoint, &push.endpoints) {
+        return Err(SendError::Gone);
+    }
+    let (ua_public, auth) = decode_base64(&row.p256dh).zip(decode_base64(&row.auth)).ok_or(SendError::Gone)?;
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let body = encrypt(&ua_public, &auth, payload, &SecretKey::random(&mut OsRng), salt).ok_or(SendError::Gone)?;
+
+    let response = push
+        .client
+        .post(endpoint.clone())
+        .header("Authorization", push.authorization(&endpoint, now))
+        .header("Content-Encoding", "aes128gcm")
+        .header("Content-Type", "application/octet-stream")
+        .header("TTL", TTL_SECS.to_string())
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| SendError::Failed(e.to_string()))?;
+
+    match response.status() {
+        s if s.is_success() => Ok(()),
+        reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE => Err(SendError::Gone),
+        s => Err(SendError::Failed(s.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn b64(s: &str) -> Vec<u8> {
+        decode_base64(s).unwrap()
+    }
+
+    #[test]
+    fn encrypts_the_rfc_8291_example() {
+        let local = SecretKey::from_slice(&b64("yfWPiYE-n46HLnH0KqZOF1fJJU3MYrct3AELtAQ-oRw")).unwrap();
+        let ua_public = b64("BCVxsr7N_eNgVRqvHtD0zTZsEc6-VV-JvLexhqUzORcxaOzi6-AYWXvTBHm4bjyPjs7Vd8pZGH6SRpkNtoIAiw4");
+        let auth = b64("BTBZMqHH6r4Tts7J_aSIgg");
+        let salt = b64("DGv6ra1nlYgDCS1FRnbzlw").try_into().unwrap();
+
+        let body = encrypt(&ua_public, &auth, b"When I grow up, I want to be a watermelon", &local, salt).unwrap();
+        assert_eq!(
+            URL_SAFE_NO_PAD.encode(body),
+            "DGv6ra1nlYgDCS1FRnbzlwAAEABBBP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A_yl95bQpu6cVPTpK4Mqgkf1CXztLVBSt2Ks3oZwbuwXPXLWyouBWLVWGNWQexSgSxsj_Qulcy4a-fN"
+        );
+    }
+
+    #[test]
+    fn only_allowed_push_services_are_accepted() {
+        let allowed: Vec<String> = DEFAULT_ENDPOINTS.iter().map(|e| e.to_string()).collect();
+        let ok = |url: &str| endpoint_allowed(&Url::parse(url).unwrap(), &allowed);
+        assert!(ok("https://fcm.googleapis.com/fcm/send/abc"));
+        assert!(ok("https://wns2-par02p.notify.windows.com/w/?token=abc"));
+        assert!(!ok("http://fcm.googleapis.com/fcm/send/abc"));
+        assert!(!ok("https://fcm.googleapis.com.evil.example/x"));
+        assert!(!ok("https://notify.windows.com.evil.example/x"));
+        assert!(!ok("https://169.254.169.254/latest/meta-data"));
+
+        let mock = vec!["http://localhost:9000".to_string()];
+        assert!(endpoint_allowed(&Url::parse("http://localhost:9000/push/1").unwrap(), &mock));
+        assert!(!endpoint_allowed(&Url::parse("http://localhost:9001/push/1").unwrap(), &mock));
+    }
+
+    #[test]
+    fn vapid_tokens_verify_against_the_public_key() {
+        use p256::ecdsa::{signature::Verifier, VerifyingKey};
+
+        let key = SecretKey::random(&mut OsRng);
+        let push = PushService {
+            public_key: URL_SAFE_NO_PAD.encode(key.public_key().to_encoded_point(false).as_bytes()),
+            key,
+            subject: "mailto:admin@example.com".to_string(),
+            endpoints: Vec::new(),
+            client: reqwest::Client::new(),
+        };
+        let header = push.authorization(&Url::parse("https://fcm.googleapis.com/fcm/send/abc").unwrap(), Utc::now());
+        let (token, k) = header.strip_prefix("vapid t=").unwrap().split_once(", k=").unwrap();
+        let (unsigned, signature) = token.rsplit_once('.').unwrap();
+
+        let claims: serde_json::Value = serde_json::from_slice(&b64(unsigned.split_once('.').unwrap().1)).unwrap();
+        assert_eq!(claims["aud"], "https://fcm.googleapis.com");
+        let verifying = VerifyingKey::from_sec1_bytes(&b64(k)).unwrap();
+        let signature = Signature::from_slice(&b64(signature)).unwrap();
+        assert!(verifying.verify(unsigned.as_bytes(), &signature).is_ok());
+    }
+}
@@ -0,0 +1,150 @@
+//! Per-IP token buckets. Every write to the API draws from one bucket, and
+//! creating habits and themes also draws from a smaller one. Buckets live in
+//! memory, so each instance counts on its own.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{api_error, client, AppState};
+
+/// Past this many clients, full buckets are forgotten; they'd start full anyway.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct RateLimiter {
+    capacity: f64,
+    per_second: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Allows bursts of `capacity` requests, refilled evenly over `period`.
+    /// A capacity of zero turns the limit off.
+    pub fn new(capacity: u32, period: Duration) -> Self {
+        Self {
+            capacity: capacity as f64,
+            per_second: capacity as f64 / period.as_secs_f64(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `new`, with the capacity read from `var` when it is set.
+    pub fn from_env(var: &str, default: u32, period: Duration) -> Self {
+        let capacity = std::env::var(var).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Self::new(capacity, period)
+    }
+
+    /// Takes a token for `ip`, or says how long until the next one.
+    fn take(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        if self.capacity == 0.0 {
+            return Ok(());
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            let (capacity, per_second) = (self.capacity, self.per_second);
+            buckets.retain(|_, b| b.tokens + now.duration_since(b.updated).as_secs_f64() * per_second < capacity);
+        }
+        let bucket = buckets.entry(ip).or_insert(Bucket { tokens: self.capacity, updated: now });
+        let refilled = now.duration_since(bucket.updated).as_secs_f64() * self.per_second;
+        bucket.tokens = (bucket.tokens + refilled).min(self.capacity);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            return Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second));
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    fn check(&self, state: &AppState, request: &Request, peer: SocketAddr) -> Result<(), Duration> {
+        let ip = client::client_ip(request.headers(), peer, state.trust_proxy_headers);
+        self.take(ip, Instant::now())
+    }
+}
+
+fn too_many_requests(wait: Duration) -> Response {
+    let secs = wait.as_secs() + 1;
+    (
+        [(header::RETRY_AFTER, secs.to_string())],
+        api_error(StatusCode::TOO_MANY_REQUESTS, "rate_limited", None, format!("Too many requests, try again in {} seconds", secs)),
+    )
+        .into_response()
+}
+
+pub struct RateLimits {
+    /// `WRITE_LIMIT_PER_MINUTE`, for every request that changes something.
+    pub writes: RateLimiter,
+    /// `CREATE_LIMIT_PER_HOUR`, for new habits and themes.
+    pub creations: RateLimiter,
+}
+
+impl RateLimits {
+    pub fn from_env() -> Self {
+        Self {
+            writes: RateLimiter::from_env("WRITE_LIMIT_PER_MINUTE", 60, Duration::from_secs(60)),
+            creations: RateLimiter::from_env("CREATE_LIMIT_PER_HOUR", 20, Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+/// Limits requests other than reads; reads pass untouched.
+pub async fn limit_writes(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if [Method::GET, Method::HEAD, Method::OPTIONS].contains(request.method()) {
+        return next.run(request).await;
+    }
+    if let Err(wait) = state.limits.writes.check(&state, &request, peer) {
+        return too_many_requests(wait);
+    }
+    next.run(request).await
+}
+
+pub async fn limit_creations(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Err(wait) = state.limits.creations.check(&state, &request, peer) {
+        return too_many_requests(wait);
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_refill_over_time() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let other: IpAddr = "203.0.113.8".parse().unwrap();
+        let start = Instant::now();
+
+        assert_eq!(limiter.take(ip, start), Ok(()));
+        assert_eq!(limiter.take(ip, start), Ok(()));
+        assert_eq!(limiter.take(ip, start).map_err(|wait| wait.as_secs_f64().round()), Err(30.0));
+        assert_eq!(limiter.take(other, start), Ok(()));
+        assert_eq!(limiter.take(ip, start + Duration::from_secs(31)), Ok(()));
+        assert!(limiter.take(ip, start + Duration::from_secs(32)).is_err());
+
+        let unlimited = RateLimiter::new(0, Duration::from_secs(60));
+        assert!((0..100).all(|_| unlimited.take(ip, start).is_ok()));
+    }
+}
@@ -0,0 +1,30 @@
+//! Random tokens handed out as secrets, and the hashes they are stored as.
+//! Invite codes, tracker secrets, login links and sessions all use these.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+pub fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+pub fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_random_and_hashes_fixed_length() {
+        let (a, b) = (random_token(12), random_token(12));
+        assert_eq!(a.len(), 16);
+        assert_ne!(a, b);
+        assert_eq!(hash_secret("s").len(), 64);
+        assert_ne!(hash_secret(&a), hash_secret(&b));
+    }
+}
@@ -0,0 +1,174 @@
+use shared::css::{theme_scope, AtBody, CssError, Declaration, Rule, Stylesheet};
+
+pub const MAX_THEME_CSS_BYTES: usize = 64 * 1024;
+
+const ALLOWED_AT_RULES: &[&str] = &[
+    "media",
+    "supports",
+    "container",
+    "layer",
+    "font-face",
+    "keyframes",
+    "-webkit-keyframes",
+];
+
+const INLINE_IMAGE_PREFIXES: &[&str] = &[
+    "data:image/png",
+    "data:image/gif",
+    "data:image/jpeg",
+    "data:image/webp",
+];
+
+#[derive(Debug)]
+pub enum ThemeCssError {
+    TooLarge,
+    Syntax(CssError),
+}
+
+/// Parses a tracker theme, drops anything that can load remote resources and
+/// confines the remaining rules to the theme's `data-theme` scope. Its keyframes
+/// get the theme id appended so they can't replace the page's own animations.
+pub fn sanitize(theme_id: &uuid::Uuid, css: &str) -> Result<String, ThemeCssError> {
+    if css.len() > MAX_THEME_CSS_BYTES {
+        return Err(ThemeCssError::TooLarge);
+    }
+
+    let mut sheet = Stylesheet::parse(css).map_err(ThemeCssError::Syntax)?;
+    sheet.rules = filter_rules(sheet.rules);
+    sheet.scope(&theme_scope(&theme_id.to_string()));
+    sheet.suffix_keyframes(&theme_id.to_string());
+    Ok(sheet.to_css())
+}
+
+fn filter_rules(rules: Vec<Rule>) -> Vec<Rule> {
+    rules
+        .into_iter()
+        .filter_map(|rule| match rule {
+            Rule::Style { selector, declarations } => Some(Rule::Style {
+                selector,
+                declarations: filter_declarations(declarations),
+            }),
+            Rule::At { name, prelude, body } => {
+                if !ALLOWED_AT_RULES.contains(&name.to_ascii_lowercase().as_str()) {
+                    return None;
+                }
+                let body = match body {
+                    AtBody::Rules(inner) => AtBody::Rules(filter_rules(inner)),
+                    AtBody::Declarations(decls) => AtBody::Declarations(filter_declarations(decls)),
+                    // Statement forms like `@layer a, b;` carry nothing worth keeping.
+                    AtBody::None => return None,
+                };
+                Some(Rule::At { name, prelude, body })
+            }
+        })
+        .collect()
+}
+
+fn filter_declarations(declarations: Vec<Declaration>) -> Vec<Declaration> {
+    declarations.into_iter().filter(is_safe).collect()
+}
+
+fn is_safe(declaration: &Declaration) -> bool {
+    // Escapes let `u\72l(` spell `url(`, so look at the decoded text.
+    let property = decode_escapes(&declaration.property).to_ascii_lowercase();
+    let value = decode_escapes(&declaration.value).to_ascii_lowercase();
+
+    if matches!(property.as_str(), "behavior" | "-moz-binding") {
+        return false;
+    }
+
+    if ["expression(", "javascript:", "image-set(", "src("]
+        .iter()
+        .any(|needle| value.contains(needle))
+    {
+        return false;
+    }
+
+    let mut rest = value.as_str();
+    while let Some(i) = rest.find("url(") {
+        rest = &rest[i + 4..];
+        let target = rest.trim_start().trim_start_matches(['"', '\'']);
+        if !INLINE_IMAGE_PREFIXES.iter().any(|p| target.starts_with(p)) {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn decode_escapes(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        let mut hex = String::new();
+        while hex.len() < 6 && chars.peek().is_some_and(|h| h.is_ascii_hexdigit()) {
+            hex.push(chars.next().unwrap());
+        }
+
+        if hex.is_empty() {
+            if let Some(escaped) = chars.next() {
+                out.push(escaped);
+            }
+        } else {
+            if chars.peek().is_some_and(|w| w.is_whitespace()) {
+                chars.next();
+            }
+            let code = u32::from_str_radix(&hex, 16).unwrap_or(0xFFFD);
+            out.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id() -> uuid::Uuid {
+        uuid::Uuid::nil()
+    }
+
+    #[test]
+    fn strips_imports_and_remote_urls() {
+        let css = "@import url(https://evil.example/x.css);\n\
+                   .a { background: url(https://evil.example/pixel.gif); color: red; }\n\
+                   .b { background: u\\72l('//evil.example/p.png'); }\n\
+                   .c { background: url(data:image/png;base64,AAAA); }";
+        let out = sanitize(&id(), css).unwrap();
+        assert!(!out.contains("@import"));
+        assert!(!out.contains("evil.example"));
+        assert!(out.contains("color: red;"));
+        assert!(out.contains("data:image/png"));
+    }
+
+    #[test]
+    fn scopes_page_level_selectors_to_the_theme() {
+        let out = sanitize(&id(), "body { display: none; }\nheader, footer { display: none; }").unwrap();
+        let scope = theme_scope(&id().to_string());
+        assert!(out.starts_with(&format!("{} {{", scope)));
+        assert!(out.contains(&format!("{} header, {} footer {{", scope, scope)));
+    }
+
+    #[test]
+    fn keyframes_cannot_replace_the_page_animations() {
+        let theme = uuid::Uuid::new_v4();
+        let css = "@keyframes achievement-unlock { to { opacity: 0; } }\n.viz-item { animation: achievement-unlock 1s; }";
+        let out = sanitize(&theme, css).unwrap();
+        assert!(out.contains(&format!("@keyframes achievement-unlock-{} {{", theme)));
+        assert!(out.contains(&format!("animation: achievement-unlock-{} 1s;", theme)));
+        // Saved themes are sanitized again when served.
+        assert_eq!(sanitize(&theme, &out).unwrap(), out);
+    }
+
+    #[test]
+    fn rejects_oversized_and_malformed_css() {
+        let huge = format!(".a {{ content: \"{}\"; }}", "x".repeat(MAX_THEME_CSS_BYTES));
+        assert!(matches!(sanitize(&id(), &huge), Err(ThemeCssError::TooLarge)));
+        assert!(matches!(sanitize(&id(), ".a { color red }"), Err(ThemeCssError::Syntax(_))));
+    }
+}
@@ -0,0 +1,114 @@
+//! Logging in with a link mailed to you. The link opens `/login#<token>`,
+//! which `LoginView` redeems for a session cookie.
+
+use crate::store::AuthStore;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use leptos::web_sys;
+
+/// The header's account corner: who is logged in, or a way to log in.
+#[component]
+pub fn AccountMenu() -> impl IntoView {
+    let auth = use_context::<AuthStore>().expect("AuthStore not found");
+    let open = RwSignal::new(false);
+
+    move || match auth.user.get() {
+        Some(user) => view! {
+            <div class="account-menu">
+                <span class="account-email">{user.email}</span>
+                <button class="winamp-btn-small" on:click=move |_| spawn_local(async move { auth.logout().await })>"LOG OUT"</button>
+            </div>
+        }.into_any(),
+        None => view! {
+            <div class="account-menu">
+                <button class="winamp-btn-small" on:click=move |_| open.update(|o| *o = !*o)>"LOG IN"</button>
+                {move || open.get().then(|| view! { <LoginForm /> })}
+            </div>
+        }.into_any(),
+    }
+}
+
+/// Asks for an email address and mails it a login link.
+#[component]
+pub fn LoginForm() -> impl IntoView {
+    let auth = use_context::<AuthStore>().expect("AuthStore not found");
+    let email = RwSignal::new(String::new());
+    let sent_to = RwSignal::new(None::<String>);
+    let error = RwSignal::new(None::<String>);
+    let busy = RwSignal::new(false);
+
+    let submit = move |ev: leptos::ev::SubmitEvent| {
+        ev.prevent_default();
+        let address = email.get_untracked();
+        busy.set(true);
+        error.set(None);
+        spawn_local(async move {
+            match auth.request_login(address.clone()).await {
+                Ok(()) => sent_to.set(Some(address)),
+                Err(e) => error.set(Some(e)),
+            }
+            busy.set(false);
+        });
+    };
+
+    move || match sent_to.get() {
+        Some(address) => view! {
+            <p class="login-sent">{format!("Check {} for a login link. It works for 15 minutes.", address)}</p>
+        }.into_any(),
+        None => view! {
+            <form class="login-form" on:submit=submit>
+                <input type="email" placeholder="you@example.com" required=true
+                    prop:value=email on:input=move |ev| email.set(event_target_value(&ev)) />
+                <button type="submit" class="winamp-btn" disabled=move || busy.get()>"SEND LINK"</button>
+                {move || error.get().map(|e| view! { <p class="error">{e}</p> })}
+            </form>
+        }.into_any(),
+    }
+}
+
+/// Shown in place of a create form while logged out.
+#[component]
+pub fn LoginPrompt(action: &'static str) -> impl IntoView {
+    view! {
+        <div class="login-prompt">
+            <p>{format!("Log in to {}. We'll email you a link, no password needed.", action)}</p>
+            <LoginForm />
+        </div>
+    }
+}
+
+/// Where login links land. Redeems the token, then goes to the trackers.
+#[component]
+pub fn LoginView() -> impl IntoView {
+    let auth = use_context::<AuthStore>().expect("AuthStore not found");
+    let set_view = use_context::<WriteSignal<crate::AppView>>().expect("AppView setter not found");
+    let error = RwSignal::new(None::<String>);
+
+    let window = web_sys::window().unwrap();
+    let token = window.location().hash().unwrap_or_default().trim_start_matches('#').to_string();
+    spawn_local(async move {
+        match auth.redeem(token).await {
+            Ok(()) => {
+                // The spent token should not linger in the address bar or history.
+                if let Ok(history) = window.history() {
+                    let _ = history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some("/"));
+                }
+                set_view.set(crate::AppView::Trackers);
+            }
+            Err(e) => error.set(Some(e)),
+        }
+    });
+
+    view! {
+        <div class="login-view">
+            {move || match error.get() {
+                None => view! { <p class="loading">"Logging in..."</p> }.into_any(),
+                Some(e) => view! {
+                    <div class="error">{e}</div>
+                    <p>"Ask for a new link:"</p>
+                    <LoginForm />
+                }.into_any(),
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,47 @@
+//! Unlocked achievements on a tracker card. Badges unlocked since the card was
+//! last shown play a short celebration (skipped under reduced motion).
+
+use crate::model::Tracker;
+use crate::store::{self, TrackerStore};
+use chrono::{Local, Utc};
+use leptos::prelude::*;
+use shared::achievements::{self, Achievement};
+
+/// Achievements unlocked locally plus any that came with a share link.
+pub fn tracker_achievements(tracker: &Tracker) -> Vec<&'static Achievement> {
+    let offset = *Local::now().offset();
+    let mut unlocked = achievements::unlocked(&tracker.history(), Utc::now(), offset);
+    for id in &tracker.shared_achievements {
+        if let Some(a) = achievements::find(id)
+            && !unlocked.contains(&a)
+        {
+            unlocked.push(a);
+        }
+    }
+    unlocked
+}
+
+#[component]
+pub fn AchievementBadges(tracker: Tracker) -> impl IntoView {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let unlocked = tracker_achievements(&tracker);
+    // Previews render trackers that aren't saved; only celebrate real ones.
+    let saved = t_store.trackers.with_untracked(|all| all.iter().any(|t| t.id == tracker.id));
+    let celebrated = if saved { store::celebrated_achievements(tracker.id) } else { Vec::new() };
+    let is_new = |a: &Achievement| saved && !celebrated.iter().any(|id| id == a.id);
+
+    if saved && unlocked.iter().any(|a| is_new(a)) {
+        store::mark_celebrated(tracker.id, unlocked.iter().map(|a| a.id.to_string()).collect());
+    }
+
+    (!unlocked.is_empty()).then(|| view! {
+        <ul class="achievements" aria-label="Achievements">
+            {unlocked.iter().map(|a| view! {
+                <li class="achievement" class:new=is_new(a) title=a.description>
+                    <span class="achievement-icon" aria-hidden="true">{a.icon}</span>
+                    <span class="achievement-title">{a.title}</span>
+                </li>
+            }).collect_view()}
+        </ul>
+    })
+}
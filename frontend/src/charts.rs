@@ -0,0 +1,219 @@
+//! Small SVG charts drawn straight from Rust. Geometry lives in plain
+//! functions so it can be tested natively; the components only emit markup.
+
+use chrono::{DateTime, TimeZone, Utc};
+use leptos::prelude::*;
+
+const WIDTH: f64 = 600.0;
+const HEIGHT: f64 = 240.0;
+/// Room for the y-axis labels on the left and the x-axis labels below.
+const MARGIN_LEFT: f64 = 56.0;
+const MARGIN_BOTTOM: f64 = 24.0;
+const MARGIN_TOP: f64 = 10.0;
+const MARGIN_RIGHT: f64 = 10.0;
+
+/// Series colours, cycled when there are more series than colours.
+const COLORS: [&str; 6] = ["#00ff00", "#00ccff", "#ffcc00", "#ff6699", "#cc99ff", "#ff9933"];
+
+pub fn color(index: usize) -> &'static str {
+    COLORS[index % COLORS.len()]
+}
+
+/// One line on a [`LineChart`]: a label and `(time, value)` points in time order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(DateTime<Utc>, f64)>,
+    /// Drawn dashed, e.g. for projections.
+    pub dashed: bool,
+}
+
+/// Round axis ticks from 0 to at least `max`: about `count` steps of 1, 2 or 5 × 10ⁿ.
+pub fn nice_ticks(max: f64, count: usize) -> Vec<f64> {
+    if max <= 0.0 || !max.is_finite() {
+        return vec![0.0, 1.0];
+    }
+    let raw = max / count.max(1) as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= raw)
+        .unwrap_or(10.0 * magnitude);
+    let steps = (max / step).ceil() as usize;
+    (0..=steps).map(|i| i as f64 * step).collect()
+}
+
+/// `1234567.0` → `"1.2M"`; keeps axis labels inside the margin.
+pub fn format_compact(value: f64) -> String {
+    let abs = value.abs();
+    let (scaled, suffix) = if abs >= 1e9 {
+        (value / 1e9, "B")
+    } else if abs >= 1e6 {
+        (value / 1e6, "M")
+    } else if abs >= 1e3 {
+        (value / 1e3, "k")
+    } else {
+        (value, "")
+    };
+    if scaled.fract().abs() < 0.05 || scaled.abs() >= 100.0 {
+        format!("{:.0}{}", scaled, suffix)
+    } else {
+        format!("{:.1}{}", scaled, suffix)
+    }
+}
+
+/// Maps a value in `domain` onto `range`.
+fn scale(value: f64, domain: (f64, f64), range: (f64, f64)) -> f64 {
+    let span = domain.1 - domain.0;
+    if span == 0.0 {
+        return range.0;
+    }
+    range.0 + (value - domain.0) / span * (range.1 - range.0)
+}
+
+/// SVG path data for `series` inside the plot area.
+fn line_path(points: &[(DateTime<Utc>, f64)], x_domain: (f64, f64), y_max: f64) -> String {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, (t, v))| {
+            let x = scale(t.timestamp() as f64, x_domain, (MARGIN_LEFT, WIDTH - MARGIN_RIGHT));
+            let y = scale(*v, (0.0, y_max), (HEIGHT - MARGIN_BOTTOM, MARGIN_TOP));
+            format!("{}{:.1},{:.1}", if i == 0 { "M" } else { " L" }, x, y)
+        })
+        .collect()
+}
+
+/// Value over time for one or more series sharing both axes.
+#[component]
+pub fn LineChart(#[prop(into)] series: Signal<Vec<Series>>) -> impl IntoView {
+    let chart = move || {
+        let series = series.get();
+        let points = series.iter().flat_map(|s| s.points.iter());
+        let (mut t_min, mut t_max, mut v_max) = (i64::MAX, i64::MIN, 0.0f64);
+        for (t, v) in points {
+            t_min = t_min.min(t.timestamp());
+            t_max = t_max.max(t.timestamp());
+            v_max = v_max.max(*v);
+        }
+        if t_min > t_max {
+            return view! { <p class="chart-empty">"Nothing to chart yet."</p> }.into_any();
+        }
+
+        let ticks = nice_ticks(v_max, 4);
+        let y_max = ticks.last().copied().unwrap_or(1.0);
+        let x_domain = (t_min as f64, t_max as f64);
+        let date_label = |t: f64| {
+            Utc.timestamp_opt(t as i64, 0).single().map(|d| d.format("%b %d").to_string()).unwrap_or_default()
+        };
+        let x_labels = [
+            (MARGIN_LEFT, "start", date_label(x_domain.0)),
+            (WIDTH - MARGIN_RIGHT, "end", date_label(x_domain.1)),
+        ];
+
+        view! {
+            <svg class="chart" viewBox=format!("0 0 {} {}", WIDTH, HEIGHT) role="img">
+                {ticks.iter().map(|tick| {
+                    let y = scale(*tick, (0.0, y_max), (HEIGHT - MARGIN_BOTTOM, MARGIN_TOP));
+                    view! {
+                        <line class="chart-grid" x1=MARGIN_LEFT x2=WIDTH - MARGIN_RIGHT y1=y y2=y />
+                        <text class="chart-label" x=MARGIN_LEFT - 6.0 y=y text-anchor="end" dominant-baseline="middle">
+                            {format_compact(*tick)}
+                        </text>
+                    }
+                }).collect_view()}
+                {x_labels.into_iter().map(|(x, anchor, label)| view! {
+                    <text class="chart-label" x=x y=HEIGHT - 6.0 text-anchor=anchor>{label}</text>
+                }).collect_view()}
+                {series.iter().enumerate().map(|(i, s)| view! {
+                    <path
+                        class="chart-line"
+                        d=line_path(&s.points, x_domain, y_max)
+                        stroke=color(i)
+                        stroke-dasharray=s.dashed.then_some("6 4")
+                    >
+                        <title>{s.label.clone()}</title>
+                    </path>
+                }).collect_view()}
+            </svg>
+            <Legend labels=series.iter().map(|s| s.label.clone()).collect() />
+        }
+        .into_any()
+    };
+
+    view! { <div class="chart-container">{chart}</div> }
+}
+
+/// Horizontal bars, one per label, scaled to the largest value.
+#[component]
+pub fn BarChart(#[prop(into)] bars: Signal<Vec<(String, f64)>>, unit: &'static str) -> impl IntoView {
+    const ROW: f64 = 28.0;
+    const LABEL_WIDTH: f64 = 180.0;
+
+    let chart = move || {
+        let bars = bars.get();
+        if bars.is_empty() {
+            return view! { <p class="chart-empty">"Nothing to chart yet."</p> }.into_any();
+        }
+        let max = bars.iter().map(|(_, v)| *v).fold(0.0, f64::max);
+        let height = ROW * bars.len() as f64;
+        // Leave room on the right for the value label.
+        let bar_max = WIDTH - LABEL_WIDTH - 80.0;
+
+        view! {
+            <svg class="chart" viewBox=format!("0 0 {} {}", WIDTH, height) role="img">
+                {bars.into_iter().enumerate().map(|(i, (label, value))| {
+                    let y = i as f64 * ROW;
+                    let w = scale(value.max(0.0), (0.0, max), (0.0, bar_max));
+                    view! {
+                        <text class="chart-label" x=LABEL_WIDTH - 8.0 y=y + ROW / 2.0 text-anchor="end" dominant-baseline="middle">
+                            {label}
+                        </text>
+                        <rect class="chart-bar" x=LABEL_WIDTH y=y + 4.0 width=w height=ROW - 8.0 fill=color(i) />
+                        <text class="chart-label" x=LABEL_WIDTH + w + 6.0 y=y + ROW / 2.0 dominant-baseline="middle">
+                            {format!("{} {}", format_compact(value), unit)}
+                        </text>
+                    }
+                }).collect_view()}
+            </svg>
+        }
+        .into_any()
+    };
+
+    view! { <div class="chart-container">{chart}</div> }
+}
+
+#[component]
+fn Legend(labels: Vec<String>) -> impl IntoView {
+    (labels.len() > 1).then(|| view! {
+        <ul class="chart-legend">
+            {labels.into_iter().enumerate().map(|(i, label)| view! {
+                <li><span class="chart-swatch" style=format!("background: {}", color(i))></span>{label}</li>
+            }).collect_view()}
+        </ul>
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_are_round_and_cover_the_maximum() {
+        assert_eq!(nice_ticks(87.0, 4), vec![0.0, 50.0, 100.0]);
+        assert_eq!(nice_ticks(9_500.0, 4), vec![0.0, 5_000.0, 10_000.0]);
+        assert_eq!(nice_ticks(0.0, 4), vec![0.0, 1.0]);
+        let ticks = nice_ticks(3.3, 4);
+        assert_eq!(ticks.last(), Some(&4.0));
+    }
+
+    #[test]
+    fn compact_labels() {
+        assert_eq!(format_compact(950.0), "950");
+        assert_eq!(format_compact(1_340.0), "1.3k");
+        assert_eq!(format_compact(10_000.0), "10k");
+        assert_eq!(format_compact(2_500_000.0), "2.5M");
+        assert_eq!(format_compact(0.5), "0.5");
+    }
+}
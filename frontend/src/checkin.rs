@@ -0,0 +1,275 @@
+//! Daily check-ins: mood, sleep and a note once a day, and how mood lines up
+//! with a tracker's cravings. Also the JSON export of everything stored locally.
+
+use crate::model::{CheckIn, ExportData, Tracker, TrackerEvent};
+use crate::store::{CheckInStore, TrackerStore};
+use chrono::{Local, NaiveDate, TimeZone, Utc};
+use leptos::prelude::*;
+use leptos::web_sys;
+use std::collections::HashMap;
+
+pub const MOODS: [(u8, &str, &str); 5] = [
+    (1, "😞", "Awful"),
+    (2, "🙁", "Bad"),
+    (3, "😐", "Okay"),
+    (4, "🙂", "Good"),
+    (5, "😄", "Great"),
+];
+pub const MAX_NOTE_LEN: usize = 280;
+/// Days with a check-in needed before a correlation is worth showing.
+const MIN_CORRELATION_DAYS: usize = 5;
+
+pub fn mood_emoji(mood: u8) -> &'static str {
+    MOODS.iter().find(|(m, _, _)| *m == mood).map_or("", |(_, emoji, _)| *emoji)
+}
+
+/// Mood against cravings on checked-in days.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MoodCorrelation {
+    /// `(mood, days, average cravings that day)` for each mood that occurred.
+    pub by_mood: Vec<(u8, usize, f64)>,
+    /// Pearson's r between mood and craving count; `None` with too few days
+    /// or no variation in either.
+    pub r: Option<f64>,
+}
+
+/// Pairs each check-in with the number of cravings logged that day in `tz`.
+pub fn correlate<Tz: TimeZone>(check_ins: &[CheckIn], events: &[TrackerEvent], tz: &Tz) -> MoodCorrelation {
+    let mut cravings: HashMap<NaiveDate, usize> = HashMap::new();
+    for event in events {
+        if let TrackerEvent::Craving { at, .. } = event {
+            *cravings.entry(at.with_timezone(tz).date_naive()).or_default() += 1;
+        }
+    }
+    let pairs: Vec<(f64, f64)> = check_ins
+        .iter()
+        .map(|c| (c.mood as f64, cravings.get(&c.date).copied().unwrap_or(0) as f64))
+        .collect();
+
+    let by_mood = MOODS
+        .iter()
+        .filter_map(|(mood, _, _)| {
+            let days: Vec<f64> = pairs.iter().filter(|(m, _)| *m == *mood as f64).map(|(_, c)| *c).collect();
+            (!days.is_empty()).then(|| (*mood, days.len(), days.iter().sum::<f64>() / days.len() as f64))
+        })
+        .collect();
+
+    MoodCorrelation { by_mood, r: (pairs.len() >= MIN_CORRELATION_DAYS).then(|| pearson(&pairs)).flatten() }
+}
+
+fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    let n = pairs.len() as f64;
+    let (mean_x, mean_y) = pairs.iter().fold((0.0, 0.0), |(x, y), (a, b)| (x + a / n, y + b / n));
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    (var_x > 0.0 && var_y > 0.0).then(|| cov / (var_x * var_y).sqrt())
+}
+
+/// Plain-language reading of Pearson's r.
+fn describe(r: f64) -> &'static str {
+    match r {
+        r if r <= -0.5 => "Lower moods clearly go with more cravings.",
+        r if r <= -0.2 => "Lower moods tend to go with more cravings.",
+        r if r < 0.2 => "Mood and cravings don't seem related so far.",
+        r if r < 0.5 => "Better moods tend to go with more cravings.",
+        _ => "Better moods clearly go with more cravings.",
+    }
+}
+
+/// "How are you today?" on the tracker dashboard; shows today's answer once given.
+#[component]
+pub fn CheckInPrompt() -> impl IntoView {
+    let store = use_context::<CheckInStore>().expect("CheckInStore not found");
+    let today = Local::now().date_naive();
+    let todays = Memo::new(move |_| store.check_ins.with(|all| all.iter().find(|c| c.date == today).cloned()));
+    let editing = RwSignal::new(false);
+
+    let mood = RwSignal::new(0u8);
+    let sleep = RwSignal::new("8".to_string());
+    let note = RwSignal::new(String::new());
+
+    let edit = move |_| {
+        if let Some(c) = todays.get_untracked() {
+            mood.set(c.mood);
+            sleep.set(c.sleep_hours.to_string());
+            note.set(c.note);
+        }
+        editing.set(true);
+    };
+    let save = move |ev: leptos::ev::SubmitEvent| {
+        ev.prevent_default();
+        if mood.get_untracked() == 0 {
+            return;
+        }
+        store.record(CheckIn {
+            date: today,
+            mood: mood.get_untracked(),
+            sleep_hours: sleep.get_untracked().parse::<f32>().unwrap_or(0.0).clamp(0.0, 24.0),
+            note: note.get_untracked().trim().chars().take(MAX_NOTE_LEN).collect(),
+        });
+        editing.set(false);
+    };
+
+    view! {
+        <div class="check-in">
+            {move || match todays.get() {
+                Some(c) if !editing.get() => view! {
+                    <div class="check-in-done">
+                        "Today: " {mood_emoji(c.mood)} " · " {format!("{}h sleep", c.sleep_hours)}
+                        {(!c.note.is_empty()).then(|| format!(" · {}", c.note))}
+                        " "
+                        <button class="winamp-btn-small" on:click=edit>"EDIT"</button>
+                    </div>
+                }.into_any(),
+                _ => view! {
+                    <form class="check-in-form" on:submit=save>
+                        <h3>"How are you today?"</h3>
+                        <div class="mood-picker" role="radiogroup" aria-label="Mood">
+                            {MOODS.into_iter().map(|(value, emoji, label)| view! {
+                                <button
+                                    type="button"
+                                    role="radio"
+                                    class=move || if mood.get() == value { "mood-option active" } else { "mood-option" }
+                                    aria-checked=move || (mood.get() == value).to_string()
+                                    aria-label=label
+                                    title=label
+                                    on:click=move |_| mood.set(value)
+                                >
+                                    {emoji}
+                                </button>
+                            }).collect_view()}
+                        </div>
+                        <div class="form-group">
+                            <label>"Hours of sleep:"</label>
+                            <input type="number" min="0" max="24" step="0.5"
+                                prop:value=sleep on:input=move |ev| sleep.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>"Anything on your mind?"</label>
+                            <input type="text" maxlength=MAX_NOTE_LEN
+                                prop:value=note on:input=move |ev| note.set(event_target_value(&ev)) />
+                        </div>
+                        <button type="submit" class="winamp-btn" disabled=move || mood.get() == 0>"CHECK IN"</button>
+                    </form>
+                }.into_any(),
+            }}
+        </div>
+    }
+}
+
+/// Mood against cravings for one tracker, from the check-ins so far.
+#[component]
+pub fn MoodCravings(tracker: Tracker) -> impl IntoView {
+    let store = use_context::<CheckInStore>().expect("CheckInStore not found");
+    let correlation = Memo::new(move |_| store.check_ins.with(|all| correlate(all, &tracker.events, &Local)));
+
+    move || {
+        let MoodCorrelation { by_mood, r } = correlation.get();
+        (!by_mood.is_empty()).then(|| view! {
+            <div class="mood-cravings">
+                <h3>"Mood and cravings"</h3>
+                <table class="stats-table">
+                    <thead><tr><th>"Mood"</th><th>"Days"</th><th>"Cravings per day"</th></tr></thead>
+                    <tbody>
+                        {by_mood.into_iter().map(|(mood, days, avg)| view! {
+                            <tr><td>{mood_emoji(mood)}</td><td>{days}</td><td>{format!("{:.1}", avg)}</td></tr>
+                        }).collect_view()}
+                    </tbody>
+                </table>
+                <p class="stats-projection">
+                    {match r {
+                        Some(r) => format!("{} (r = {:.2})", describe(r), r),
+                        None => format!("Check in on at least {} days to see a trend.", MIN_CORRELATION_DAYS),
+                    }}
+                </p>
+            </div>
+        })
+    }
+}
+
+/// Downloads every tracker and check-in as a JSON file.
+#[component]
+pub fn ExportButton() -> impl IntoView {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let c_store = use_context::<CheckInStore>().expect("CheckInStore not found");
+
+    let export = move |_| {
+        let data = ExportData {
+            exported_at: Utc::now(),
+            trackers: t_store.trackers.get_untracked(),
+            check_ins: c_store.check_ins.get_untracked(),
+        };
+        let Ok(json) = serde_json::to_string_pretty(&data) else { return };
+        let filename = format!("you-quitter-{}.json", Local::now().format("%Y-%m-%d"));
+        if download(&filename, &json).is_none() {
+            leptos::logging::error!("Export: download failed");
+        }
+    };
+
+    view! { <button class="winamp-btn" on:click=export>"EXPORT DATA"</button> }
+}
+
+fn download(filename: &str, json: &str) -> Option<()> {
+    use leptos::wasm_bindgen::JsCast;
+
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type("application/json");
+    let parts = js_sys::Array::of1(&json.into());
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options).ok()?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).ok()?;
+
+    let document = web_sys::window()?.document()?;
+    let link: web_sys::HtmlAnchorElement = document.create_element("a").ok()?.dyn_into().ok()?;
+    link.set_href(&url);
+    link.set_download(filename);
+    link.click();
+    let _ = web_sys::Url::revoke_object_url(&url);
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn check_in(day: i64, mood: u8) -> CheckIn {
+        CheckIn { date: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap() + Duration::days(day), mood, sleep_hours: 7.5, note: String::new() }
+    }
+
+    fn cravings_on(day: i64, n: usize) -> Vec<TrackerEvent> {
+        let at = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap() + Duration::days(day);
+        (0..n).map(|_| crate::journal::craving(at, 5, Vec::new(), "", "")).collect()
+    }
+
+    #[test]
+    fn low_moods_with_more_cravings_correlate_negatively() {
+        let check_ins: Vec<_> = [(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 1)].iter().map(|(d, m)| check_in(*d, *m)).collect();
+        let events: Vec<_> = [(0, 4), (1, 3), (2, 2), (3, 1), (5, 2)].iter().flat_map(|(d, n)| cravings_on(*d, *n)).collect();
+
+        let correlation = correlate(&check_ins, &events, &Utc);
+        assert_eq!(correlation.by_mood[0], (1, 2, 3.0));
+        assert_eq!(correlation.by_mood.last(), Some(&(5, 1, 0.0)));
+        assert!(correlation.r.unwrap() < -0.8);
+    }
+
+    #[test]
+    fn no_trend_from_a_few_days() {
+        let check_ins = vec![check_in(0, 2), check_in(1, 4)];
+        assert_eq!(correlate(&check_ins, &cravings_on(0, 1), &Utc).r, None);
+    }
+
+    #[test]
+    fn export_round_trips() {
+        let data = ExportData {
+            exported_at: Utc::now(),
+            trackers: vec![Tracker::new_abstinence(uuid::Uuid::new_v4(), Utc::now(), 3.0, None, "Me".to_string())],
+            check_ins: vec![check_in(0, 4)],
+        };
+        let json = serde_json::to_string(&data).unwrap();
+        assert_eq!(serde_json::from_str::<ExportData>(&json).unwrap(), data);
+    }
+}
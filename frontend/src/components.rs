@@ -1,11 +1,20 @@
 use leptos::prelude::*;
-use crate::model::{Habit, Tracker};
+use crate::model::{Habit, Tracker, TrackerEvent};
 use crate::store::{HabitStore, TrackerStore};
 use chrono::{DateTime, Utc};
 use leptos::web_sys;
-use shared::tracker::TrackerState;
-use shared::tracker::Theme as ProtoTheme;
-use prost::Message;
+use shared::tracker::{BorderStyle, Palette, ThemeTokens, TrackerState, VisualizerAnimation, VisualizerMode};
+use crate::visualizer::{CanvasStyle, CanvasVisualizer, MODES};
+use crate::rng::Rng;
+use crate::stats::TrackerStats;
+use crate::heatmap::TrackerHeatmap;
+use crate::journal::CravingJournal;
+use crate::checkin::{CheckInPrompt, ExportButton, MoodCravings};
+use crate::badges::{tracker_achievements, AchievementBadges};
+use crate::notifications::NotificationToggle;
+use crate::encouragements::EncouragementPanel;
+use crate::groups::TrackerGroups;
+use crate::leaderboards::{open_leaderboard, LeaderboardOptIn};
 
 // ========================
 // DASHBOARD (TRACKERS)
@@ -18,6 +27,7 @@ pub fn TrackersView() -> impl IntoView {
 
     view! {
         <div class="dashboard">
+            <CheckInPrompt />
             <div class="dashboard-header" style="margin-bottom: 20px; border-bottom: 1px dashed #333; padding-bottom: 20px;">
                 <TrackerCreator on_close=move || {} />
             </div>
@@ -35,6 +45,9 @@ pub fn TrackersView() -> impl IntoView {
                      view! { <div/> }.into_any()
                 }}
             </div>
+            <div class="dashboard-footer">
+                <ExportButton />
+            </div>
         </div>
     }
 }
@@ -66,15 +79,16 @@ pub fn TrackerListItem(tracker: Tracker) -> impl IntoView {
             match habit.get() {
                 Some(h) => {
                     let h_clone = h.clone();
+                    let icon_habit = h.clone();
                     let t_clone = tracker.clone();
                     now.track();
                     let count = t_clone.get_abstinence_count();
                     
                     let click_handler = move |_| {
                         // Construct URL state
-                        let (start_date, units_per_day, theme_id, user_name) = match &t_clone.tracker_type {
-                            crate::model::TrackerType::Abstinence { start_date, units_per_day, theme_id, user_name } => {
-                                (start_date.timestamp(), *units_per_day, theme_id.map(|id| id.to_string()), user_name.clone())
+                        let (start_date, units_per_day, theme_id, user_name, visualizer_mode) = match &t_clone.tracker_type {
+                            crate::model::TrackerType::Abstinence { start_date, units_per_day, theme_id, user_name, visualizer_mode } => {
+                                (start_date.timestamp(), *units_per_day, theme_id.map(|id| id.to_string()), user_name.clone(), *visualizer_mode)
                             }
                         };
                         let state = TrackerState {
@@ -82,6 +96,10 @@ pub fn TrackerListItem(tracker: Tracker) -> impl IntoView {
                             units_per_day,
                             theme_id,
                             user_name,
+                            visualizer_mode: visualizer_mode.map(|m| m as i32),
+                            achievements: Vec::new(),
+                            leaderboard_entry: t_clone.share_entry().map(|id| id.to_string()),
+                            last_relapse_timestamp: t_clone.last_relapse().map(|at| at.timestamp()),
                         };
                         let encoded = state.encode_to_url();
                         let url = format!("/{}#{}", h_clone.slug, encoded);
@@ -95,16 +113,15 @@ pub fn TrackerListItem(tracker: Tracker) -> impl IntoView {
                         set_view.set(crate::AppView::TrackerDetail(t_clone.id));
                     };
                     
-                    let start_date_str = match tracker.tracker_type {
-                        crate::model::TrackerType::Abstinence { start_date, .. } => start_date.format("%d/%m/%Y %H:%M").to_string(),
-                    };
+                    // The count restarts at the last relapse, and so does the date.
+                    let start_date_str = tracker.clean_since().format("%d/%m/%Y %H:%M").to_string();
 
                     view! {
                         <div 
                             class="habit-item tracker-item" 
                             on:click=click_handler
                         >
-                            <span class="icon">{h_clone.icon}</span>
+                            <span class="icon"><HabitIcon habit=icon_habit /></span>
                             <span class="name">"NOT " {h_clone.name}</span>
                             <span class="details">{format!("{:.8} {} since {}", count, h_clone.unit_name, start_date_str)}</span>
                         </div>
@@ -146,7 +163,28 @@ pub fn TrackerDetailView(tracker_id: uuid::Uuid) -> impl IntoView {
         {move || match tracker.get() {
             Some(t) => {
                 let h = h_store.get_habit(t.habit_id);
-                 view! { <TrackerCardInner tracker=t habit=h /> }.into_any()
+                let stats = h.clone().map(|habit| view! { <TrackerStats tracker=t.clone() habit=habit /> });
+                let notify = h.clone().map(|habit| view! { <NotificationToggle tracker=t.clone() habit=habit /> });
+                let leaderboard = h.clone().map(|habit| view! { <LeaderboardOptIn tracker=t.clone() habit=habit /> });
+                let heatmap_tracker = t.clone();
+                let journal_tracker = t.clone();
+                let mood_tracker = t.clone();
+                let groups_tracker = t.clone();
+                let log_relapse = move |_| t_store.log_event(tracker_id, TrackerEvent::Relapse { at: Utc::now() });
+                view! {
+                    <TrackerCardInner tracker=t habit=h>
+                        <div class="tracker-log-actions">
+                            <button class="winamp-btn" on:click=log_relapse>"LOG RELAPSE"</button>
+                        </div>
+                        {notify}
+                        <TrackerHeatmap tracker=heatmap_tracker />
+                        <CravingJournal tracker=journal_tracker />
+                        <MoodCravings tracker=mood_tracker />
+                        <TrackerGroups tracker=groups_tracker />
+                        {leaderboard}
+                        {stats}
+                    </TrackerCardInner>
+                }.into_any()
             },
             None => view! { <div class="error">"Tracker not found"</div> }.into_any()
         }}
@@ -154,8 +192,17 @@ pub fn TrackerDetailView(tracker_id: uuid::Uuid) -> impl IntoView {
 }
 
 #[component]
-pub fn TrackerCardInner(tracker: Tracker, habit: Option<Habit>) -> impl IntoView {
-    let _t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+pub fn TrackerCardInner(
+    tracker: Tracker,
+    habit: Option<Habit>,
+    /// Overrides the theme's icon limit, e.g. while previewing an unsaved theme.
+    #[prop(default = None)] icon_limit: Option<usize>,
+    /// Overrides the theme's visualizer tokens for the canvas renderer.
+    #[prop(optional, into)] canvas_style: Option<Signal<CanvasStyle>>,
+    /// Rendered under the card, inside the theme's scope.
+    #[prop(optional)] children: Option<Children>,
+) -> impl IntoView {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
     let theme_store = use_context::<crate::store::ThemeStore>().expect("ThemeStore not found");
     
     if habit.is_none() {
@@ -176,7 +223,8 @@ pub fn TrackerCardInner(tracker: Tracker, habit: Option<Habit>) -> impl IntoView
     }
 
     let habit_name = habit.name.clone();
-    let habit_icon_viz = habit.icon.clone();
+    let habit_icon_viz = shared::habit::display_icon(&habit.icon).to_string();
+    let habit_icon_image = habit.icon_image_url();
     let habit_unit = habit.unit_name.clone();
     let habit_slug = habit.slug.clone();
     
@@ -187,16 +235,37 @@ pub fn TrackerCardInner(tracker: Tracker, habit: Option<Habit>) -> impl IntoView
         tracker_for_calc.get_abstinence_count()
     });
     
-    let (user_name, start_date_str) = match &tracker.tracker_type {
-        crate::model::TrackerType::Abstinence { user_name, start_date, .. } => {
-            (user_name.clone(), start_date.format("%Y-%m-%d %H:%M").to_string())
+    let user_name = match &tracker.tracker_type {
+        crate::model::TrackerType::Abstinence { user_name, .. } => user_name.clone(),
+    };
+    let start_date_str = tracker.clean_since().format("%Y-%m-%d %H:%M").to_string();
+
+    let (tracker_mode, grid_columns) = match &tracker.tracker_type {
+        crate::model::TrackerType::Abstinence { visualizer_mode, units_per_day, .. } => {
+            (RwSignal::new(*visualizer_mode), crate::layouts::grid_columns(*units_per_day))
+        }
+    };
+    let set_mode = {
+        let tracker = tracker.clone();
+        move |ev| {
+            let mode = VisualizerMode::from_str_name(&event_target_value(&ev));
+            tracker_mode.set(mode);
+            let mut updated = tracker.clone();
+            match &mut updated.tracker_type {
+                crate::model::TrackerType::Abstinence { visualizer_mode, .. } => *visualizer_mode = mode,
+            }
+            t_store.update_tracker(updated);
         }
     };
 
+    let include_badges = RwSignal::new(false);
     let share_tracker = {
         let slug = habit_slug.clone();
+        let badges: Vec<String> = tracker_achievements(&tracker).iter().map(|a| a.id.to_string()).collect();
+        let entry = tracker.share_entry().map(|id| id.to_string());
+        let last_relapse = tracker.last_relapse().map(|at| at.timestamp());
         let (start_date, units_per_day, theme_id, u_name) = match &tracker.tracker_type {
-            crate::model::TrackerType::Abstinence { start_date, units_per_day, theme_id, user_name } => {
+            crate::model::TrackerType::Abstinence { start_date, units_per_day, theme_id, user_name, .. } => {
                 (start_date.timestamp(), *units_per_day, theme_id.map(|id| id.to_string()), user_name.clone())
             }
         };
@@ -206,6 +275,10 @@ pub fn TrackerCardInner(tracker: Tracker, habit: Option<Habit>) -> impl IntoView
                 units_per_day,
                 theme_id: theme_id.clone(),
                 user_name: u_name.clone(),
+                visualizer_mode: tracker_mode.get_untracked().map(|m| m as i32),
+                achievements: if include_badges.get_untracked() { badges.clone() } else { Vec::new() },
+                leaderboard_entry: entry.clone(),
+                last_relapse_timestamp: last_relapse,
             };
             let encoded = state.encode_to_url();
             let origin = web_sys::window().unwrap().location().origin().unwrap();
@@ -221,76 +294,162 @@ pub fn TrackerCardInner(tracker: Tracker, habit: Option<Habit>) -> impl IntoView
         }
     };
 
-    let icon_limit = theme.as_ref().and_then(|t| t.icon_limit.map(|l| l as usize));
+    let icon_limit = icon_limit.or_else(|| theme.as_ref().and_then(|t| t.icon_limit.map(|l| l as usize)));
+    let canvas_style = canvas_style.unwrap_or_else(|| {
+        let style = CanvasStyle::from_tokens(theme.as_ref().and_then(|t| t.tokens.as_ref()));
+        Signal::derive(move || style.clone())
+    });
+    let mode = Signal::derive(move || tracker_mode.get().unwrap_or_else(|| canvas_style.with(|s| s.mode)));
+    let theme_attr = theme.as_ref().map(|t| t.id.to_string());
 
+    // Saved themes are scoped server-side to `[data-theme="<id>"]`; the sandbox
+    // around it is out of their reach and keeps fixed-position overlays inside the card.
     view! {
-        <div class="habit-card full-view">
-            {theme.map(|t| view! { <style>{t.css}</style> })}
-            <div class="habit-header">
-                <span class="habit-name">{user_name} " is NOT " {move || habit_name.clone()} " since " {start_date_str.clone()}</span>
-            </div>
+        <div class="theme-sandbox">
+            <div data-theme=theme_attr>
+                {theme.map(|t| view! { <style>{t.css}</style> })}
+                <div class="habit-card full-view">
+                    <div class="habit-header">
+                        <span class="habit-name">{user_name} " is NOT " {move || habit_name.clone()} " since " {start_date_str.clone()}</span>
+                    </div>
             
-            <div class="habit-stats">
-                 {move || {
-                    let c = count.get();
-                    format!("{:.8} {} not consumed since then", c, habit_unit)
-                 }}
-            </div>
+                    <div class="habit-stats">
+                         {move || {
+                            let c = count.get();
+                            format!("{:.8} {} not consumed since then", c, habit_unit)
+                         }}
+                    </div>
 
-            <Visualizer count=count.get() icon=habit_icon_viz.clone() limit=icon_limit />
+                    <AchievementBadges tracker=tracker.clone() />
 
-            <div class="tracker-card-actions">
-                <button class="winamp-btn" on:click=share_tracker>"SHARE"</button>
+                    <Visualizer count=count icon=habit_icon_viz.clone() image=habit_icon_image.clone() limit=icon_limit
+                        style=canvas_style mode=mode grid_columns=grid_columns seed=tracker.layout_seed() />
+
+                    <div class="tracker-card-actions">
+                        <select
+                            class="visualizer-mode"
+                            prop:value=move || tracker_mode.get().map(|m| m.as_str_name()).unwrap_or_default()
+                            on:change=set_mode
+                        >
+                            <option value="">"Theme default"</option>
+                            {MODES.iter().map(|(m, label)| view! { <option value=m.as_str_name()>{*label}</option> }).collect_view()}
+                        </select>
+                        <label class="share-option">
+                            <input type="checkbox" prop:checked=include_badges
+                                on:change=move |ev| include_badges.set(event_target_checked(&ev)) />
+                            " Include badges"
+                        </label>
+                        <button class="winamp-btn" on:click=share_tracker>"SHARE"</button>
+                    </div>
+
+                    <EncouragementPanel tracker=tracker.clone() />
+                </div>
+                {children.map(|children| children())}
             </div>
         </div>
     }.into_any()
 }
 
+/// Default cap for the DOM renderer, which pays for one animated node per icon.
+const MAX_DOM_ICONS: usize = 10_000;
+
+/// Draws one icon per unit on a canvas, or as animated DOM nodes where canvas
+/// is unavailable. `limit` caps the icon count in either case; the DOM
+/// fallback only roams.
 #[component]
-pub fn Visualizer(count: f64, icon: String, limit: Option<usize>) -> impl IntoView {
-    let int_count = count.floor() as usize;
-    // Default to 10,000 if no limit is set, otherwise use the limit
-    let display_count = int_count.min(limit.unwrap_or(10000));
+pub fn Visualizer(
+    #[prop(into)] count: Signal<f64>,
+    icon: String,
+    /// Uploaded icon image; `icon` is then only its alt text.
+    #[prop(default = None)] image: Option<String>,
+    limit: Option<usize>,
+    #[prop(into)] style: Signal<CanvasStyle>,
+    #[prop(into)] mode: Signal<VisualizerMode>,
+    #[prop(default = 7)] grid_columns: usize,
+    /// Layout seed, see [`Tracker::layout_seed`].
+    seed: u64,
+) -> impl IntoView {
+    let (dom_mode, set_dom_mode) = signal(false);
 
     view! {
         <div class="visualizer-container">
-            <div class="visualizer-grid">
-                {(0..display_count).map(|_| {
+            {move || if dom_mode.get() {
+                view! { <DomVisualizer count=count icon=icon.clone() image=image.clone() limit=limit seed=seed /> }.into_any()
+            } else {
+                view! {
+                    <CanvasVisualizer
+                        count=count
+                        icon=icon.clone()
+                        image=image.clone()
+                        limit=limit
+                        style=style
+                        mode=mode
+                        grid_columns=grid_columns
+                        seed=seed
+                        on_unsupported=move || set_dom_mode.set(true)
+                    />
+                }.into_any()
+            }}
+        </div>
+    }
+}
+
+/// Fallback renderer: one CSS-animated node per icon. Nodes are keyed by
+/// index, so a growing count only appends new icons.
+#[component]
+fn DomVisualizer(
+    count: Signal<f64>,
+    icon: String,
+    image: Option<String>,
+    limit: Option<usize>,
+    seed: u64,
+) -> impl IntoView {
+    let display_count = Memo::new(move |_| {
+        (count.get().floor().max(0.0) as usize).min(limit.unwrap_or(MAX_DOM_ICONS))
+    });
+
+    view! {
+        <div class="visualizer-grid">
+            <For
+                each=move || 0..display_count.get()
+                key=|i| *i
+                children=move |index| {
                     let i = icon.clone();
+                    let mut rng = Rng::for_index(seed, index);
                     // Initial random position
-                    let top = format!("{}%", (rand_f64() * 100.0));
-                    let left = format!("{}%", (rand_f64() * 100.0));
+                    let top = format!("{}%", (rng.next_f64() * 100.0));
+                    let left = format!("{}%", (rng.next_f64() * 100.0));
                     
                     // Movement offsets for Brownian-style motion
-                    let tx = format!("{}vw", (rand_f64() * 40.0) - 20.0);
-                    let ty = format!("{}vh", (rand_f64() * 40.0) - 20.0);
-                    let tr = format!("{}deg", (rand_f64() * 360.0));
+                    let tx = format!("{}vw", (rng.next_f64() * 40.0) - 20.0);
+                    let ty = format!("{}vh", (rng.next_f64() * 40.0) - 20.0);
+                    let tr = format!("{}deg", (rng.next_f64() * 360.0));
                     
                     // Randomized timing
-                    let dur = format!("{}s", 15.0 + rand_f64() * 15.0);
-                    let delay = format!("-{}s", rand_f64() * 30.0);
+                    let dur = format!("{}s", 15.0 + rng.next_f64() * 15.0);
+                    let delay = format!("-{}s", rng.next_f64() * 30.0);
                     
                     let style = format!(
                         "top: {}; left: {}; --tx: {}; --ty: {}; --tr: {}; animation-duration: {}; animation-delay: {};",
                         top, left, tx, ty, tr, dur, delay
                     );
 
-                    view! { <span class="viz-item" style=style>{move || i.clone()}</span> }
-                }).collect::<Vec<_>>()}
-            </div>
+                    match image.clone() {
+                        Some(src) => view! { <img class="viz-item viz-image" src=src alt=i style=style /> }.into_any(),
+                        None => view! { <span class="viz-item" style=style>{i}</span> }.into_any(),
+                    }
+                }
+            />
         </div>
     }
 }
 
-// Helper for randomness in WASM
-fn rand_f64() -> f64 {
-    #[cfg(target_arch = "wasm32")]
-    {
-        js_sys::Math::random()
-    }
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        0.5
+/// A habit's uploaded icon image, or its emoji when it has none.
+#[component]
+pub fn HabitIcon(habit: Habit) -> impl IntoView {
+    match habit.icon_image_url() {
+        Some(src) => view! { <img class="habit-icon-image" src=src alt=habit.icon /> }.into_any(),
+        None => view! { <span>{habit.icon}</span> }.into_any(),
     }
 }
 
@@ -300,8 +459,8 @@ fn rand_f64() -> f64 {
 
 #[component]
 pub fn HabitManager() -> impl IntoView {
-    let store = use_context::<HabitStore>().expect("HabitStore not found");
-    let habits = store.habits;
+    let set_view = use_context::<WriteSignal<crate::AppView>>().expect("AppView setter not found");
+    let auth = use_context::<crate::store::AuthStore>().expect("AuthStore not found");
     let (show_form, set_show_form) = signal(false);
 
     view! {
@@ -313,17 +472,12 @@ pub fn HabitManager() -> impl IntoView {
                     <div class="habit-library">
                         <div class="library-header">
                             <h3>"Habit Library"</h3>
-                            <button class="winamp-btn" on:click=move |_| set_show_form.set(true)>"NEW HABIT"</button>
-                        </div>
-                        <div class="habit-grid">
-                            <For
-                                each=move || habits.get()
-                                key=|h| h.id
-                                children=move |habit| {
-                                    view! { <HabitItem habit=habit /> }
-                                }
-                            />
+                            {move || auth.user.get().is_some().then(|| view! {
+                                <button class="winamp-btn" on:click=move |_| set_show_form.set(true)>"NEW HABIT"</button>
+                            })}
                         </div>
+                        {move || auth.user.get().is_none().then(|| view! { <crate::auth::LoginPrompt action="add habits" /> })}
+                        <HabitPicker on_select=Callback::new(move |id| set_view.set(crate::AppView::HabitDetail(id))) />
                     </div>
                 }.into_any()
             }}
@@ -332,18 +486,119 @@ pub fn HabitManager() -> impl IntoView {
 }
 
 #[component]
-pub fn HabitItem(habit: Habit) -> impl IntoView {
-    let set_view = use_context::<WriteSignal<crate::AppView>>().expect("AppView setter not found");
+pub fn HabitItem(
+    habit: Habit,
+    #[prop(into)] on_select: Callback<uuid::Uuid>,
+    #[prop(into)] selected: Signal<bool>,
+) -> impl IntoView {
     let id = habit.id;
     
     view! {
         <div 
-            class="habit-item tracker-item" 
-            on:click=move |_| set_view.set(crate::AppView::HabitDetail(id))
+            class=move || if selected.get() { "habit-item tracker-item selected" } else { "habit-item tracker-item" }
+            on:click=move |_| on_select.run(id)
         >
-            <span class="icon">{habit.icon}</span>
+            <span class="icon"><HabitIcon habit=habit.clone() /></span>
             <span class="name">{habit.name}</span>
-            <span class="details">{format!("{}", habit.unit_name)}</span>
+            <span class="details">{habit.unit_name}</span>
+        </div>
+    }
+}
+
+/// Searchable habit list grouped by category. Typing or picking a tag searches on the server.
+#[component]
+pub fn HabitPicker(
+    #[prop(into)] on_select: Callback<uuid::Uuid>,
+    #[prop(optional, into)] selected: Option<Signal<Option<uuid::Uuid>>>,
+) -> impl IntoView {
+    let store = use_context::<HabitStore>().expect("HabitStore not found");
+
+    let (query, set_query) = signal(String::new());
+    let (tag, set_tag) = signal(String::new());
+    // `None` means no filter is active and the whole library is shown.
+    let results = RwSignal::new(None::<Vec<Habit>>);
+    let latest_request = StoredValue::new(0u32);
+
+    Effect::new(move |_| {
+        let (q, t) = (query.get(), tag.get());
+        let request = latest_request.get_value() + 1;
+        latest_request.set_value(request);
+        if q.trim().is_empty() && t.is_empty() {
+            results.set(None);
+            return;
+        }
+        leptos::task::spawn_local(async move {
+            let found = store.search(q, t).await.unwrap_or_default();
+            if latest_request.get_value() == request {
+                results.set(Some(found));
+            }
+        });
+    });
+
+    let groups = Memo::new(move |_| {
+        let listed = |habits: &[Habit]| habits.iter().filter(|h| h.listed()).cloned().collect::<Vec<_>>();
+        match results.get() {
+            Some(found) => crate::model::group_by_category(&listed(&found)),
+            None => store.habits.with(|all| crate::model::group_by_category(&listed(all))),
+        }
+    });
+    let all_tags = Memo::new(move |_| {
+        let mut tags: Vec<String> = store.habits.with(|all| all.iter().flat_map(|h| h.tags.clone()).collect());
+        tags.sort();
+        tags.dedup();
+        tags
+    });
+
+    view! {
+        <div class="habit-picker">
+            <input
+                type="search"
+                placeholder="Search habits..."
+                on:input=move |ev| set_query.set(event_target_value(&ev))
+                prop:value=query
+            />
+            <div class="tag-filter">
+                <For
+                    each=move || all_tags.get()
+                    key=|t| t.clone()
+                    children=move |t| {
+                        let value = t.clone();
+                        let active = move || tag.get() == value;
+                        let toggle = t.clone();
+                        view! {
+                            <button
+                                class=move || if active() { "winamp-btn-small active" } else { "winamp-btn-small" }
+                                on:click=move |_| set_tag.update(|current| {
+                                    *current = if *current == toggle { String::new() } else { toggle.clone() };
+                                })
+                            >
+                                {format!("#{}", t)}
+                            </button>
+                        }
+                    }
+                />
+            </div>
+            {move || {
+                let groups = groups.get();
+                if groups.is_empty() {
+                    return view! { <p class="hint">"No habits found"</p> }.into_any();
+                }
+                groups.into_iter().map(|(category, habits)| {
+                    let title = if category.is_empty() { "Other".to_string() } else { category };
+                    view! {
+                        <div class="habit-group">
+                            <h4>{title}</h4>
+                            <div class="habit-grid">
+                                {habits.into_iter().map(|habit| {
+                                    let id = habit.id;
+                                    let is_selected = Signal::derive(move || selected.is_some_and(|s| s.get() == Some(id)));
+                                    view! { <HabitItem habit=habit on_select=on_select selected=is_selected /> }
+                                }).collect_view()}
+                            </div>
+                        </div>
+                    }
+                }).collect_view().into_any()
+            }}
         </div>
     }
 }
@@ -356,16 +611,91 @@ pub fn HabitForm(#[prop(into)] on_close: Callback<()>) -> impl IntoView {
     let (slug, set_slug) = signal("".to_string());
     let (icon, set_icon) = signal("🍺".to_string());
     let (unit, set_unit) = signal("Beers".to_string());
+    let (category, set_category) = signal("".to_string());
+    let (tags, set_tags) = signal("".to_string());
+    // The slug follows the name until the user edits it by hand.
+    let (slug_edited, set_slug_edited) = signal(false);
+    let availability = RwSignal::new(None::<crate::model::SlugAvailability>);
+    let latest_check = StoredValue::new(0u32);
+
+    let slug_error = Memo::new(move |_| shared::habit::validate_slug(&slug.get()).err());
+    let icon_valid = Memo::new(move |_| shared::habit::is_single_emoji(icon.get().trim()));
+    // Optional uploaded image as (content type, bytes); the emoji stays as its fallback.
+    let icon_file = RwSignal::new(None::<(String, Vec<u8>)>);
+    let (icon_file_error, set_icon_file_error) = signal::<Option<String>>(None);
+
+    let on_icon_file = move |ev: leptos::ev::Event| {
+        let input: web_sys::HtmlInputElement = event_target(&ev);
+        icon_file.set(None);
+        set_icon_file_error.set(None);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else { return };
+        let content_type = file.type_();
+        if content_type != "image/png" && content_type != "image/svg+xml" {
+            set_icon_file_error.set(Some("Icon images must be PNG or SVG".to_string()));
+            return;
+        }
+        if file.size() > shared::habit::MAX_ICON_IMAGE_BYTES as f64 {
+            set_icon_file_error.set(Some(format!(
+                "Icon images must be at most {} KB",
+                shared::habit::MAX_ICON_IMAGE_BYTES / 1024
+            )));
+            return;
+        }
+        leptos::task::spawn_local(async move {
+            if let Ok(buffer) = wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await {
+                icon_file.set(Some((content_type, js_sys::Uint8Array::new(&buffer).to_vec())));
+            }
+        });
+    };
+
+    Effect::new(move |_| {
+        let s = slug.get();
+        let check = latest_check.get_value() + 1;
+        latest_check.set_value(check);
+        availability.set(None);
+        if shared::habit::validate_slug(&s).is_err() {
+            return;
+        }
+        leptos::task::spawn_local(async move {
+            let result = store.check_slug(s).await;
+            if latest_check.get_value() == check {
+                availability.set(result);
+            }
+        });
+    });
+
+    let on_name = move |ev| {
+        let value = event_target_value(&ev);
+        if !slug_edited.get_untracked() {
+            set_slug.set(shared::habit::slugify(&value));
+        }
+        set_name.set(value);
+    };
 
     let create = move |_| {
-        let h = Habit::new(
+        if slug_error.get().is_some() || !icon_valid.get() { return; }
+        let mut h = Habit::new(
             &name.get(),
             &slug.get(),
             &icon.get(),
             &unit.get(),
         );
-        store.add_habit(h);
-        on_close.run(());
+        h.category = shared::habit::normalize_category(&category.get());
+        h.tags = shared::habit::parse_tags(&tags.get());
+        let id = h.id;
+        store.add_habit(h, move || {
+            let Some((content_type, bytes)) = icon_file.get_untracked() else {
+                on_close.run(());
+                return;
+            };
+            leptos::task::spawn_local(async move {
+                // The habit is saved either way; a rejected image just leaves the emoji in place.
+                if let Err(e) = store.upload_icon(id, content_type, bytes).await {
+                    leptos::logging::error!("Icon upload failed: {}", e);
+                }
+                on_close.run(());
+            });
+        });
     };
 
     view! {
@@ -373,23 +703,69 @@ pub fn HabitForm(#[prop(into)] on_close: Callback<()>) -> impl IntoView {
             <h3>"Define New Habit"</h3>
             <div class="form-group">
                 <label>"Name:"</label>
-                <input type="text" on:input=move |ev| set_name.set(event_target_value(&ev)) value=name />
+                <input type="text" on:input=on_name value=name />
             </div>
              <div class="form-group">
                 <label>"URL Slug (unique):"</label>
-                <input type="text" on:input=move |ev| set_slug.set(event_target_value(&ev)) value=slug />
+                <input
+                    type="text"
+                    on:input=move |ev| {
+                        set_slug_edited.set(true);
+                        set_slug.set(event_target_value(&ev));
+                    }
+                    prop:value=slug
+                />
+                {move || match (slug_error.get(), availability.get()) {
+                    (Some(e), _) if !slug.get().is_empty() => view! { <p class="theme-css-error">{e.to_string()}</p> }.into_any(),
+                    (_, Some(a)) if a.available => view! { <p class="hint">{format!("/{} is available", a.slug)}</p> }.into_any(),
+                    (_, Some(a)) => {
+                        let message = a.error.map(|e| e.message).unwrap_or_default();
+                        view! {
+                            <p class="theme-css-error">
+                                {message}
+                                {a.suggestion.map(|suggestion| {
+                                    let label = format!("USE {}", suggestion);
+                                    view! {
+                                        " "
+                                        <button class="winamp-btn-small" on:click=move |_| set_slug.set(suggestion.clone())>{label}</button>
+                                    }
+                                })}
+                            </p>
+                        }.into_any()
+                    }
+                    _ => view! { <span/> }.into_any(),
+                }}
             </div>
             <div class="form-group">
                 <label>"Icon (Emoji):"</label>
                 <input type="text" on:input=move |ev| set_icon.set(event_target_value(&ev)) value=icon />
+                {move || (!icon_valid.get()).then(|| view! {
+                    <p class="theme-css-error">"Icon must be a single emoji"</p>
+                })}
+            </div>
+            <div class="form-group">
+                <label>"Icon Image (optional PNG/SVG, replaces the emoji):"</label>
+                <input type="file" accept="image/png,image/svg+xml" on:change=on_icon_file />
+                {move || icon_file_error.get().map(|e| view! { <p class="theme-css-error">{e}</p> })}
             </div>
              <div class="form-group">
                 <label>"Unit Name:"</label>
                 <input type="text" on:input=move |ev| set_unit.set(event_target_value(&ev)) value=unit />
             </div>
+            <div class="form-group">
+                <label>"Category:"</label>
+                <input type="text" placeholder="e.g. Drinks" on:input=move |ev| set_category.set(event_target_value(&ev)) value=category />
+            </div>
+            <div class="form-group">
+                <label>"Tags (comma separated):"</label>
+                <input type="text" placeholder="alcohol, social" on:input=move |ev| set_tags.set(event_target_value(&ev)) value=tags />
+            </div>
+            {move || store.save_error.get().map(|e| view! {
+                <div class="theme-css-error">{format!("Server rejected habit: {}", e.message)}</div>
+            })}
             
             <div class="actions">
-                <button class="winamp-btn" on:click=create>"SAVE TO DB"</button>
+                <button class="winamp-btn" on:click=create disabled=move || slug_error.get().is_some() || !icon_valid.get()>"SAVE TO DB"</button>
                 <button class="winamp-btn" on:click=move |_| on_close.run(())>"CANCEL"</button>
             </div>
         </div>
@@ -404,55 +780,51 @@ pub fn HabitForm(#[prop(into)] on_close: Callback<()>) -> impl IntoView {
 pub fn TrackerCreator(#[prop(into)] on_close: Callback<()>) -> impl IntoView {
     let h_store = use_context::<HabitStore>().expect("HabitStore not found");
     let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
-    let theme_store = use_context::<crate::store::ThemeStore>().expect("ThemeStore not found");
     let habits = h_store.habits;
-    let themes = theme_store.themes;
     
-    let (selected_habit_id, set_selected_habit_id) = signal::<Option<String>>(None);
+    let (selected_habit_id, set_selected_habit_id) = signal::<Option<uuid::Uuid>>(None);
     let (units_per_day, set_units_per_day) = signal("1.0".to_string());
     let (user_name, set_user_name) = signal("Bogdan".to_string());
-    let (theme_id, set_theme_id) = signal("".to_string());
+    let (theme_id, set_theme_id) = signal::<Option<uuid::Uuid>>(None);
     let (start_date_str, set_start_date_str) = signal(
         Utc::now().format("%Y-%m-%dT%H:%M").to_string()
     );
 
     let create = move |_| {
-        if let Some(id_str) = selected_habit_id.get() {
-            if let Ok(habit_id) = uuid::Uuid::parse_str(&id_str) {
-                let dt_str = start_date_str.get();
-                let naive = chrono::NaiveDateTime::parse_from_str(&dt_str, "%Y-%m-%dT%H:%M")
-                    .unwrap_or_else(|_| Utc::now().naive_local());
-                let dt_utc = DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc);
-
-                let upd: f64 = units_per_day.get().parse().unwrap_or(1.0);
-                let u_name = user_name.get();
-                let t_id = if theme_id.get().is_empty() {
-                    None
-                } else {
-                    uuid::Uuid::parse_str(&theme_id.get()).ok()
-                };
+        if let Some(habit_id) = selected_habit_id.get() {
+            let dt_str = start_date_str.get();
+            let naive = chrono::NaiveDateTime::parse_from_str(&dt_str, "%Y-%m-%dT%H:%M")
+                .unwrap_or_else(|_| Utc::now().naive_local());
+            let dt_utc = DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc);
 
-                let t = Tracker::new_abstinence(habit_id, dt_utc, upd, t_id, u_name.clone());
-                t_store.add_tracker(t);
+            let upd: f64 = units_per_day.get().parse().unwrap_or(1.0);
+            let u_name = user_name.get();
+            let t_id = theme_id.get();
 
-                // Update URL to reflect this new tracker
-                if let Some(h) = habits.get().iter().find(|h| h.id == habit_id) {
-                    let state = TrackerState {
-                        start_timestamp: dt_utc.timestamp(),
-                        units_per_day: upd,
-                        theme_id: t_id.map(|id| id.to_string()),
-                        user_name: u_name,
-                    };
-                    let encoded = state.encode_to_url();
-                    let url = format!("/{}#{}", h.slug, encoded);
-                    
-                    if let Ok(history) = web_sys::window().unwrap().history() {
-                        let _ = history.push_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&url));
-                    }
-                }
+            let t = Tracker::new_abstinence(habit_id, dt_utc, upd, t_id, u_name.clone());
+            t_store.add_tracker(t);
+
+            // Update URL to reflect this new tracker
+            if let Some(h) = habits.get().iter().find(|h| h.id == habit_id) {
+                let state = TrackerState {
+                    start_timestamp: dt_utc.timestamp(),
+                    units_per_day: upd,
+                    theme_id: t_id.map(|id| id.to_string()),
+                    user_name: u_name,
+                    visualizer_mode: None,
+                    achievements: Vec::new(),
+                    leaderboard_entry: None,
+                    last_relapse_timestamp: None,
+                };
+                let encoded = state.encode_to_url();
+                let url = format!("/{}#{}", h.slug, encoded);
                 
-                on_close.run(());
+                if let Ok(history) = web_sys::window().unwrap().history() {
+                    let _ = history.push_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&url));
+                }
             }
+            
+            on_close.run(());
         }
     };
 
@@ -462,16 +834,10 @@ pub fn TrackerCreator(#[prop(into)] on_close: Callback<()>) -> impl IntoView {
             
             <div class="form-group">
                 <label>"Select Habit from DB:"</label>
-                <select on:change=move |ev| set_selected_habit_id.set(Some(event_target_value(&ev)))>
-                    <option value="">"-- Choose a Habit --"</option>
-                    <For
-                        each=move || habits.get()
-                        key=|h| h.id
-                        children=move |habit| {
-                            view! { <option value=habit.id.to_string()>{habit.name}</option> }
-                        }
-                    />
-                </select>
+                <HabitPicker
+                    on_select=Callback::new(move |id| set_selected_habit_id.set(Some(id)))
+                    selected=selected_habit_id
+                />
                 {move || if habits.get().is_empty() {
                     view! { <p class="hint">"Loading habits from server..."</p> }.into_any()
                 } else {
@@ -507,16 +873,7 @@ pub fn TrackerCreator(#[prop(into)] on_close: Callback<()>) -> impl IntoView {
 
                         <div class="form-group">
                             <label>"Theme:"</label>
-                            <select on:change=move |ev| set_theme_id.set(event_target_value(&ev))>
-                                <option value="">"Default (None)"</option>
-                                <For
-                                    each=move || themes.get()
-                                    key=|t| t.id
-                                    children=move |theme| {
-                                        view! { <option value=theme.id.to_string()>{theme.name}</option> }
-                                    }
-                                />
-                            </select>
+                            <ThemeGallery on_select=Callback::new(move |id| set_theme_id.set(id)) selected=theme_id />
                         </div>
                         
                         <div class="actions">
@@ -548,6 +905,8 @@ pub fn HabitDetailView(habit_id: uuid::Uuid) -> impl IntoView {
     view! {
         {move || match habit.get() {
             Some(h) => {
+                let board_habit = h.clone();
+                let h_controls = h.clone();
                 view! {
                     <div class="habit-card full-view">
                         <div class="habit-header">
@@ -555,14 +914,22 @@ pub fn HabitDetailView(habit_id: uuid::Uuid) -> impl IntoView {
                         </div>
                         
                         <div class="habit-info-container">
-                            <h2>{h.icon} " " {h.name}</h2>
+                            <h2><HabitIcon habit=h.clone() /> " " {h.name}</h2>
                             <p><strong>"Slug:"</strong> " " {h.slug}</p>
                             <p><strong>"Unit:"</strong> " " {h.unit_name}</p>
+                            {(!h.category.is_empty()).then(|| view! {
+                                <p><strong>"Category:"</strong> " " {h.category.clone()}</p>
+                            })}
+                            {(!h.tags.is_empty()).then(|| view! {
+                                <p><strong>"Tags:"</strong> " " {h.tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" ")}</p>
+                            })}
                         </div>
                         
                         <div class="habit-detail-actions">
                              <button class="winamp-btn" on:click=move |_| set_view.set(crate::AppView::Habits)>"< BACK"</button>
+                             <button class="winamp-btn" on:click=move |_| open_leaderboard(set_view, &board_habit)>"LEADERBOARD"</button>
                         </div>
+                        <HabitControls habit=h_controls />
                     </div>
                 }.into_any()
             },
@@ -571,39 +938,188 @@ pub fn HabitDetailView(habit_id: uuid::Uuid) -> impl IntoView {
     }
 }
 
+/// Edit and delete for the habit's creator or an admin, plus hide and merge for admins.
 #[component]
-pub fn ThemeItemDisplay(theme: crate::model::Theme) -> impl IntoView {
-    let (expanded, set_expanded) = signal(false);
-    
+fn HabitControls(habit: Habit) -> impl IntoView {
+    let h_store = use_context::<HabitStore>().expect("HabitStore not found");
+    let auth = use_context::<crate::store::AuthStore>().expect("AuthStore not found");
+    let set_view = use_context::<WriteSignal<crate::AppView>>().expect("AppView setter not found");
+    let editing = RwSignal::new(false);
+    let merge_into = RwSignal::new(String::new());
+    let error = RwSignal::new(None::<String>);
+    let id = habit.id;
+
+    let done = move |result: Result<(), String>, then: Option<crate::AppView>| match result {
+        Ok(()) => {
+            if let Some(view) = then {
+                set_view.set(view);
+            }
+        }
+        Err(e) => error.set(Some(e)),
+    };
+    let delete = move |_| {
+        let window = web_sys::window().unwrap();
+        if window.confirm_with_message("Delete this habit for everyone?").unwrap_or(false) {
+            leptos::task::spawn_local(async move { done(h_store.delete_habit(id).await, Some(crate::AppView::Habits)) });
+        }
+    };
+    let merge = move |_| {
+        let Ok(into) = uuid::Uuid::parse_str(&merge_into.get_untracked()) else { return };
+        leptos::task::spawn_local(async move { done(h_store.merge(id, into).await, Some(crate::AppView::HabitDetail(into))) });
+    };
+
+    move || {
+        let Some(user) = auth.user.get().filter(|u| u.can_edit(habit.created_by)) else {
+            return ().into_any();
+        };
+        let hidden = habit.hidden;
+        let editable = habit.clone();
+        view! {
+            <div class="habit-controls">
+                {move || editing.get().then(|| view! {
+                    <HabitEditForm habit=editable.clone() on_close=move || editing.set(false) />
+                })}
+                <div class="habit-detail-actions">
+                    <button class="winamp-btn" on:click=move |_| editing.update(|e| *e = !*e)>"EDIT"</button>
+                    <button class="winamp-btn delete" on:click=delete>"DELETE"</button>
+                    {user.is_admin.then(|| view! {
+                        <button class="winamp-btn" on:click=move |_| leptos::task::spawn_local(async move { done(h_store.set_hidden(id, !hidden).await, None) })>
+                            {if hidden { "UNHIDE" } else { "HIDE" }}
+                        </button>
+                        <select on:change=move |ev| merge_into.set(event_target_value(&ev))>
+                            <option value="">"-- Merge into --"</option>
+                            {h_store.habits.get().into_iter().filter(|h| h.id != id && h.listed()).map(|h| view! {
+                                <option value=h.id.to_string()>{format!("{} {}", h.icon, h.name)}</option>
+                            }).collect_view()}
+                        </select>
+                        <button class="winamp-btn" disabled=move || merge_into.get().is_empty() on:click=merge>"MERGE"</button>
+                    })}
+                </div>
+                {move || error.get().map(|e| view! { <p class="error">{e}</p> })}
+            </div>
+        }.into_any()
+    }
+}
+
+/// Edits a saved habit. The slug can't change, since share links use it.
+#[component]
+fn HabitEditForm(habit: Habit, #[prop(into)] on_close: Callback<()>) -> impl IntoView {
+    let store = use_context::<HabitStore>().expect("HabitStore not found");
+    let name = RwSignal::new(habit.name.clone());
+    let icon = RwSignal::new(habit.icon.clone());
+    let unit = RwSignal::new(habit.unit_name.clone());
+    let category = RwSignal::new(habit.category.clone());
+    let tags = RwSignal::new(habit.tags.join(", "));
+    let icon_valid = Memo::new(move |_| shared::habit::is_single_emoji(icon.get().trim()));
+    let error = RwSignal::new(None::<String>);
+
+    let save = move |_| {
+        let edited = Habit {
+            name: name.get_untracked(),
+            icon: icon.get_untracked(),
+            unit_name: unit.get_untracked(),
+            category: shared::habit::normalize_category(&category.get_untracked()),
+            tags: shared::habit::parse_tags(&tags.get_untracked()),
+            ..habit.clone()
+        };
+        error.set(None);
+        leptos::task::spawn_local(async move {
+            match store.update_habit(edited).await {
+                Ok(()) => on_close.run(()),
+                Err(e) => error.set(Some(e)),
+            }
+        });
+    };
+
     view! {
-        <div class="theme-item">
-            <div class="theme-item-header" style="display: flex; justify-content: space-between; align-items: center;">
-                <span>{theme.name} " (" {move || theme.icon_limit.map(|l| l.to_string()).unwrap_or("No Limit".to_string())} " icons)"</span>
-                <button class="winamp-btn-small" on:click=move |_| set_expanded.update(|e| *e = !*e)>
-                    {move || if expanded.get() { "HIDE" } else { "SHOW" }}
-                </button>
+        <div class="creator-form">
+            <div class="form-group">
+                <label>"Name:"</label>
+                <input type="text" on:input=move |ev| name.set(event_target_value(&ev)) prop:value=name />
+            </div>
+            <div class="form-group">
+                <label>"Icon (Emoji):"</label>
+                <input type="text" on:input=move |ev| icon.set(event_target_value(&ev)) prop:value=icon />
+                {move || (!icon_valid.get()).then(|| view! {
+                    <p class="theme-css-error">"Icon must be a single emoji"</p>
+                })}
+            </div>
+            <div class="form-group">
+                <label>"Unit Name:"</label>
+                <input type="text" on:input=move |ev| unit.set(event_target_value(&ev)) prop:value=unit />
+            </div>
+            <div class="form-group">
+                <label>"Category:"</label>
+                <input type="text" on:input=move |ev| category.set(event_target_value(&ev)) prop:value=category />
+            </div>
+            <div class="form-group">
+                <label>"Tags (comma separated):"</label>
+                <input type="text" on:input=move |ev| tags.set(event_target_value(&ev)) prop:value=tags />
+            </div>
+            {move || error.get().map(|e| view! { <div class="theme-css-error">{e}</div> })}
+            <div class="actions">
+                <button class="winamp-btn" on:click=save disabled=move || !icon_valid.get()>"SAVE"</button>
+                <button class="winamp-btn" on:click=move |_| on_close.run(())>"CANCEL"</button>
             </div>
-            {move || if expanded.get() {
-                view! {
-                    <pre class="theme-item-css">
-                        {theme.css.clone()}
-                    </pre>
-                }.into_any()
-            } else {
-                view! { <div/> }.into_any()
-            }}
         </div>
     }
 }
 
+/// The icon limit a new theme starts with.
+const DEFAULT_ICON_LIMIT: &str = "500";
+
 #[component]
 pub fn ThemeManager() -> impl IntoView {
     let theme_store = use_context::<crate::store::ThemeStore>().expect("ThemeStore not found");
     let set_view = use_context::<WriteSignal<crate::AppView>>().expect("AppView setter not found");
+    let auth = use_context::<crate::store::AuthStore>().expect("AuthStore not found");
     
     let (name, set_name) = signal("".to_string());
     let (css, set_css) = signal("".to_string());
-    let (icon_limit, set_icon_limit) = signal("500".to_string());
+    let (icon_limit, set_icon_limit) = signal(DEFAULT_ICON_LIMIT.to_string());
+    let tokens = RwSignal::new(ThemeTokens::winamp());
+
+    let parsed_limit = Signal::derive(move || icon_limit.get().trim().parse::<usize>().ok());
+    let css_error = Memo::new(move |_| shared::css::Stylesheet::parse(&css.get()).err());
+    let token_error = Memo::new(move |_| tokens.with(|t| t.validate().err()));
+    // Mirrors what the server compiles: tokens first, raw CSS as an override.
+    let preview_css = Signal::derive(move || format!("{}\n{}", tokens.with(|t| t.to_css()), css.get()));
+
+    // The theme being edited, or `None` while creating a new one.
+    let editing = RwSignal::new(None::<uuid::Uuid>);
+
+    let load = move |t: crate::model::Theme| {
+        set_icon_limit.set(t.icon_limit.map(|l| l.to_string()).unwrap_or_default());
+        tokens.set(t.tokens.unwrap_or_else(ThemeTokens::winamp));
+        set_css.set(shared::css::unscope_theme_css(&t.css_override, &t.id.to_string()).unwrap_or(t.css_override));
+    };
+    let start_from = move |ev| {
+        let id = event_target_value(&ev);
+        let Ok(id) = uuid::Uuid::parse_str(&id) else { return };
+        if let Some(t) = theme_store.themes.get().into_iter().find(|t| t.id == id) {
+            // Starting from another theme makes a new one, so the theme being
+            // edited keeps its own content.
+            if editing.get_untracked().is_some() || name.get().trim().is_empty() {
+                set_name.set(format!("{} (copy)", t.name));
+            }
+            editing.set(None);
+            load(t);
+        }
+    };
+    let edit = move |id: uuid::Uuid| {
+        if let Some(t) = theme_store.themes.get_untracked().into_iter().find(|t| t.id == id) {
+            set_name.set(t.name.clone());
+            load(t);
+            editing.set(Some(id));
+        }
+    };
+    let reset = move || {
+        set_name.set("".to_string());
+        set_css.set("".to_string());
+        set_icon_limit.set(DEFAULT_ICON_LIMIT.to_string());
+        tokens.set(ThemeTokens::winamp());
+        editing.set(None);
+    };
 
     let create = move |_| {
         let n = name.get();
@@ -615,27 +1131,24 @@ pub fn ThemeManager() -> impl IntoView {
             limit_str.parse().ok()
         };
         
-        if n.is_empty() || c.is_empty() { return; }
+        if n.is_empty() || css_error.get().is_some() || token_error.get().is_some() { return; }
         
-        let proto = ProtoTheme {
-            id: uuid::Uuid::new_v4().to_string(),
+        let theme = crate::model::Theme {
+            id: editing.get().unwrap_or_else(uuid::Uuid::new_v4),
             name: n,
-            css: c,
+            css: String::new(),
             icon_limit: limit,
+            tokens: Some(tokens.get()),
+            css_override: c,
+            usage_count: 0,
+            votes: 0,
+            created_by: None,
         };
-        
-        let mut buf = Vec::new();
-        proto.encode(&mut buf).unwrap();
-        
-        leptos::task::spawn_local(async move {
-            let _ = gloo_net::http::Request::post("/api/themes")
-                .header("Content-Type", "application/octet-stream")
-                .body(buf)
-                .unwrap()
-                .send()
-                .await;
-            let _ = web_sys::window().unwrap().location().reload();
-        });
+        if editing.get().is_some() {
+            theme_store.update_theme(theme, reset);
+        } else {
+            theme_store.add_theme(theme, reset);
+        }
     };
 
     view! {
@@ -645,40 +1158,448 @@ pub fn ThemeManager() -> impl IntoView {
                 <button class="winamp-btn" on:click=move |_| set_view.set(crate::AppView::Trackers)>"BACK"</button>
             </div>
             
-            <div class="theme-form-section">
-                <h4>"Create New Theme"</h4>
-                <div class="form-group">
-                    <label>"Theme Name:"</label>
-                    <input type="text" on:input=move |ev| set_name.set(event_target_value(&ev)) value=name />
-                </div>
-                <div class="form-group">
-                    <label>"Icon Limit (Performance, leave empty for none):"</label>
-                    <input type="number" on:input=move |ev| set_icon_limit.set(event_target_value(&ev)) value=icon_limit />
-                </div>
-                <div class="form-group">
-                    <label>"CSS Content:"</label>
-                    <textarea 
-                        class="theme-css-textarea"
-                        on:input=move |ev| set_css.set(event_target_value(&ev)) 
-                        prop:value=css 
-                    />
-                </div>
-                <div class="actions">
-                    <button class="winamp-btn" on:click=create>"SAVE THEME"</button>
+            <div class="theme-editor">
+                <div class="theme-form-section">
+                    <h4>{move || if editing.get().is_some() { "Edit Theme" } else { "Create New Theme" }}</h4>
+                    {move || editing.get().is_some().then(|| view! {
+                        <button class="winamp-btn-small" on:click=move |_| reset()>"CANCEL EDIT"</button>
+                    })}
+                    {move || auth.user.get().is_none().then(|| view! { <crate::auth::LoginPrompt action="save themes" /> })}
+                    <div class="form-group">
+                        <label>"Theme Name:"</label>
+                        <input type="text" on:input=move |ev| set_name.set(event_target_value(&ev)) prop:value=name />
+                    </div>
+                    <div class="form-group">
+                        <label>"Start from Existing Theme:"</label>
+                        <select on:change=start_from>
+                            <option value="">"-- Blank --"</option>
+                            <For
+                                each=move || theme_store.themes.get()
+                                key=|t| t.id
+                                children=move |theme| {
+                                    view! { <option value=theme.id.to_string()>{theme.name}</option> }
+                                }
+                            />
+                        </select>
+                    </div>
+                    <div class="form-group">
+                        <label>"Icon Limit (Performance, leave empty for none):"</label>
+                        <input type="number" on:input=move |ev| set_icon_limit.set(event_target_value(&ev)) prop:value=icon_limit />
+                    </div>
+                    <ThemeTokensForm tokens=tokens />
+                    {move || token_error.get().map(|e| view! {
+                        <div class="theme-css-error">{format!("Token error: {}", e)}</div>
+                    })}
+                    <details class="advanced-css" open=move || !css.get().is_empty()>
+                        <summary>"Advanced: Raw CSS Override"</summary>
+                        <div class="form-group">
+                            <textarea 
+                                class="theme-css-textarea"
+                                spellcheck="false"
+                                on:input=move |ev| set_css.set(event_target_value(&ev)) 
+                                prop:value=css 
+                            />
+                        </div>
+                    </details>
+                    {move || css_error.get().map(|e| view! {
+                        <div class="theme-css-error">{format!("CSS error at {}", e)}</div>
+                    })}
+                    {move || theme_store.save_error.get().map(|e| view! {
+                        <div class="theme-css-error">{format!("Server rejected theme: {}", e)}</div>
+                    })}
+                    <div class="actions">
+                        <button class="winamp-btn" on:click=create disabled=move || auth.user.get().is_none() || css_error.get().is_some() || token_error.get().is_some()>"SAVE THEME"</button>
+                    </div>
                 </div>
+
+                <ThemePreview
+                    css=preview_css
+                    icon_limit=parsed_limit
+                    canvas_style=Signal::derive(move || tokens.with(|t| CanvasStyle::from_tokens(Some(t))))
+                />
             </div>
             
             <div style="margin-top: 20px;">
-                <h4>"Existing Themes:"</h4>
-                <div class="theme-list">
-                    <For
-                        each=move || theme_store.themes.get()
-                        key=|t| t.id
-                        children=move |theme| {
-                            view! { <ThemeItemDisplay theme=theme /> }
+                <h4>"Theme Gallery:"</h4>
+                <ThemeGallery on_edit=Callback::new(edit) />
+            </div>
+        </div>
+    }
+}
+
+/// Form inputs for every design token; edits apply to the preview immediately.
+#[component]
+pub fn ThemeTokensForm(tokens: RwSignal<ThemeTokens>) -> impl IntoView {
+    let border_style = move || tokens.with(|t| t.border_style.to_string());
+
+    view! {
+        <div class="token-form">
+            <h4>"Palette"</h4>
+            <div class="token-palette">
+                <PaletteInput tokens=tokens label="Background" field=|p| &mut p.background />
+                <PaletteInput tokens=tokens label="Surface" field=|p| &mut p.surface />
+                <PaletteInput tokens=tokens label="Text" field=|p| &mut p.text />
+                <PaletteInput tokens=tokens label="Accent" field=|p| &mut p.accent />
+                <PaletteInput tokens=tokens label="Muted" field=|p| &mut p.muted />
+            </div>
+
+            <div class="form-group">
+                <label>"Font Stack:"</label>
+                <input
+                    type="text"
+                    prop:value=move || tokens.with(|t| t.font_stack.clone())
+                    on:input=move |ev| {
+                        let v = event_target_value(&ev);
+                        tokens.update(|t| t.font_stack = v);
+                    }
+                />
+            </div>
+
+            <div class="form-group">
+                <label>"Border Style:"</label>
+                <select
+                    prop:value=border_style
+                    on:change=move |ev| {
+                        let v = event_target_value(&ev).parse().unwrap_or(0);
+                        tokens.update(|t| t.border_style = v);
+                    }
+                >
+                    <option value=(BorderStyle::Solid as i32).to_string()>"Solid"</option>
+                    <option value=(BorderStyle::Dashed as i32).to_string()>"Dashed"</option>
+                    <option value=(BorderStyle::Dotted as i32).to_string()>"Dotted"</option>
+                    <option value=(BorderStyle::Double as i32).to_string()>"Double"</option>
+                    <option value=(BorderStyle::None as i32).to_string()>"None"</option>
+                </select>
+            </div>
+            <NumberToken tokens=tokens label="Border Width (px):" step="1"
+                get=|t| t.border_width_px as f64 set=|t, v| t.border_width_px = v as i32 />
+            <NumberToken tokens=tokens label="Border Radius (px):" step="1"
+                get=|t| t.border_radius_px as f64 set=|t, v| t.border_radius_px = v as i32 />
+
+            <h4>"Visualizer"</h4>
+            <div class="form-group">
+                <label>"Default Mode:"</label>
+                <select
+                    prop:value=move || tokens.with(|t| t.default_mode.to_string())
+                    on:change=move |ev| {
+                        let v = event_target_value(&ev).parse().unwrap_or(0);
+                        tokens.update(|t| t.default_mode = v);
+                    }
+                >
+                    {MODES.iter().map(|(m, label)| view! { <option value=(*m as i32).to_string()>{*label}</option> }).collect_view()}
+                </select>
+            </div>
+            <NumberToken tokens=tokens label="Animation Duration (s):" step="0.5"
+                get=|t| visualizer(t).duration_seconds set=|t, v| visualizer_mut(t).duration_seconds = v />
+            <NumberToken tokens=tokens label="Roam Scale:" step="0.1"
+                get=|t| visualizer(t).roam_scale set=|t, v| visualizer_mut(t).roam_scale = v />
+            <NumberToken tokens=tokens label="Icon Size (rem):" step="0.1"
+                get=|t| visualizer(t).icon_size_rem set=|t, v| visualizer_mut(t).icon_size_rem = v />
+            <div class="form-group">
+                <label>
+                    <input
+                        type="checkbox"
+                        prop:checked=move || tokens.with(|t| visualizer(t).glow)
+                        on:change=move |ev| {
+                            let checked = event_target_checked(&ev);
+                            tokens.update(|t| visualizer_mut(t).glow = checked);
                         }
                     />
-                </div>
+                    " Glow"
+                </label>
+            </div>
+        </div>
+    }
+}
+
+fn visualizer(tokens: &ThemeTokens) -> VisualizerAnimation {
+    tokens.visualizer.unwrap_or_else(VisualizerAnimation::roaming)
+}
+
+fn visualizer_mut(tokens: &mut ThemeTokens) -> &mut VisualizerAnimation {
+    tokens.visualizer.get_or_insert_with(VisualizerAnimation::roaming)
+}
+
+#[component]
+fn PaletteInput(
+    tokens: RwSignal<ThemeTokens>,
+    label: &'static str,
+    field: fn(&mut Palette) -> &mut String,
+) -> impl IntoView {
+    view! {
+        <div class="form-group">
+            <label>{label}</label>
+            <input
+                type="color"
+                prop:value=move || tokens.with(|t| field(&mut t.palette.clone().unwrap_or_else(Palette::winamp)).clone())
+                on:input=move |ev| {
+                    let v = event_target_value(&ev);
+                    tokens.update(|t| *field(t.palette.get_or_insert_with(Palette::winamp)) = v);
+                }
+            />
+        </div>
+    }
+}
+
+#[component]
+fn NumberToken(
+    tokens: RwSignal<ThemeTokens>,
+    label: &'static str,
+    step: &'static str,
+    get: fn(&ThemeTokens) -> f64,
+    set: fn(&mut ThemeTokens, f64),
+) -> impl IntoView {
+    view! {
+        <div class="form-group">
+            <label>{label}</label>
+            <input
+                type="number"
+                step=step
+                prop:value=move || tokens.with(|t| get(t).to_string())
+                on:input=move |ev| {
+                    if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                        tokens.update(|t| set(t, v));
+                    }
+                }
+            />
+        </div>
+    }
+}
+
+/// Renders a sample tracker card with `css` applied only inside the preview pane.
+#[component]
+pub fn ThemePreview(
+    #[prop(into)] css: Signal<String>,
+    #[prop(into)] icon_limit: Signal<Option<usize>>,
+    #[prop(into)] canvas_style: Signal<CanvasStyle>,
+) -> impl IntoView {
+    let h_store = use_context::<HabitStore>().expect("HabitStore not found");
+    let (preview_units, set_preview_units) = signal("24".to_string());
+
+    // Keep showing the last stylesheet that parsed while the user is mid-edit.
+    // Its keyframes are renamed too, or the preview's `tokens-roam` would drive
+    // every token-themed card on the page.
+    let scoped_css = Memo::new(move |prev: Option<&String>| {
+        match shared::css::Stylesheet::parse(&css.get()) {
+            Ok(mut sheet) => {
+                sheet.scope(".theme-preview-pane");
+                sheet.suffix_keyframes("preview");
+                sheet.to_css()
+            }
+            Err(_) => prev.cloned().unwrap_or_default(),
+        }
+    });
+
+    let units = Signal::derive(move || preview_units.get().trim().parse::<f64>().unwrap_or(0.0).max(0.0));
+
+    let sample_habit = Memo::new(move |_| {
+        h_store.habits.get().first().cloned()
+            .unwrap_or_else(|| Habit::new("Beer", "beer", "🍺", "Beers"))
+    });
+
+    view! {
+        <div class="theme-preview">
+            <div class="form-group">
+                <label>"Preview Units:"</label>
+                <input type="number" min="0" on:input=move |ev| set_preview_units.set(event_target_value(&ev)) prop:value=preview_units />
+            </div>
+            <p class="hint">
+                {move || {
+                    let total = units.get().floor() as usize;
+                    match icon_limit.get() {
+                        Some(limit) if total > limit => format!("Showing {} of {} icons (limit {})", limit, total, limit),
+                        Some(limit) => format!("Showing {} icons (limit {})", total, limit),
+                        None => format!("Showing {} icons (no limit)", total),
+                    }
+                }}
+            </p>
+            <div class="theme-preview-pane">
+                <style>{move || scoped_css.get()}</style>
+                {move || {
+                    let habit = sample_habit.get();
+                    // One unit per hour makes the preview count match the input.
+                    let start = Utc::now() - chrono::Duration::minutes((units.get() * 60.0).ceil() as i64);
+                    let tracker = Tracker::new_abstinence(habit.id, start, 24.0, None, "Preview".to_string());
+                    view! { <TrackerCardInner tracker=tracker habit=Some(habit) icon_limit=icon_limit.get() canvas_style=canvas_style /> }
+                }}
+            </div>
+        </div>
+    }
+}
+
+/// One gallery theme on its own, as the themes feed links to it.
+#[component]
+pub fn ThemeDetailView(theme_id: uuid::Uuid) -> impl IntoView {
+    let theme_store = use_context::<crate::store::ThemeStore>().expect("ThemeStore not found");
+    let set_view = use_context::<WriteSignal<crate::AppView>>().expect("AppView setter not found");
+
+    let theme = Memo::new(move |_| theme_store.themes.with(|themes| themes.iter().find(|t| t.id == theme_id).cloned()));
+    let loaded = Memo::new(move |_| theme_store.themes.with(|themes| !themes.is_empty()));
+
+    view! {
+        <div class="theme-detail">
+            {move || match theme.get() {
+                Some(t) => view! {
+                    <div class="gallery-card">
+                        <img class="gallery-thumb" src=format!("/api/themes/{}/thumbnail.svg", t.id) alt=t.name.clone() />
+                        <div class="gallery-card-name">{t.name}</div>
+                        <div class="gallery-card-stats">{format!("▲ {} · used by {}", t.votes, t.usage_count)}</div>
+                    </div>
+                }.into_any(),
+                None if loaded.get() => view! { <p class="empty-state">"This theme was deleted."</p> }.into_any(),
+                None => view! { <p class="hint">"Loading theme..."</p> }.into_any(),
+            }}
+            <div class="actions">
+                <button class="winamp-btn" on:click=move |_| set_view.set(crate::AppView::Themes)>"ALL THEMES"</button>
+            </div>
+        </div>
+    }
+}
+
+/// Browsable, searchable theme list. With `on_select` each card gets a USE button.
+#[component]
+pub fn ThemeGallery(
+    #[prop(optional, into)] on_select: Option<Callback<Option<uuid::Uuid>>>,
+    #[prop(optional, into)] selected: Option<Signal<Option<uuid::Uuid>>>,
+    /// Offers EDIT and DELETE on the themes the user may change.
+    #[prop(optional, into)] on_edit: Option<Callback<uuid::Uuid>>,
+) -> impl IntoView {
+    let theme_store = use_context::<crate::store::ThemeStore>().expect("ThemeStore not found");
+    let auth = use_context::<crate::store::AuthStore>().expect("AuthStore not found");
+
+    let (search, set_search) = signal(String::new());
+    let (sort, set_sort) = signal("popular".to_string());
+    let (page, set_page) = signal(1i64);
+    let (refresh, set_refresh) = signal(0u32);
+    let (gallery_error, set_gallery_error) = signal::<Option<String>>(None);
+    let results = RwSignal::new(None::<crate::store::GalleryPage>);
+    // Responses can arrive out of order; only the latest request may write `results`.
+    let latest_request = StoredValue::new(0u32);
+
+    Effect::new(move |_| {
+        let (q, s, p) = (search.get(), sort.get(), page.get());
+        refresh.track();
+        let request = latest_request.get_value() + 1;
+        latest_request.set_value(request);
+        leptos::task::spawn_local(async move {
+            let loaded = theme_store.fetch_page(q, &s, p).await;
+            if latest_request.get_value() == request {
+                results.set(loaded);
+            }
+        });
+    });
+
+    let page_count = Signal::derive(move || {
+        let total = results.with(|r| r.as_ref().map(|r| r.total).unwrap_or(0));
+        ((total + crate::store::GALLERY_PAGE_SIZE - 1) / crate::store::GALLERY_PAGE_SIZE).max(1)
+    });
+    let is_selected = move |id: Option<uuid::Uuid>| selected.is_some_and(|s| s.get() == id);
+
+    let vote = move |id: uuid::Uuid| {
+        set_gallery_error.set(None);
+        leptos::task::spawn_local(async move {
+            if theme_store.vote(id).await {
+                set_refresh.update(|r| *r += 1);
+            } else {
+                set_gallery_error.set(Some("Vote not counted, try again later".to_string()));
+            }
+        });
+    };
+    let delete = move |id: uuid::Uuid| {
+        let window = web_sys::window().unwrap();
+        if !window.confirm_with_message("Delete this theme for everyone?").unwrap_or(false) {
+            return;
+        }
+        set_gallery_error.set(None);
+        leptos::task::spawn_local(async move {
+            match theme_store.delete_theme(id).await {
+                Ok(()) => set_refresh.update(|r| *r += 1),
+                Err(e) => set_gallery_error.set(Some(e)),
+            }
+        });
+    };
+
+    view! {
+        <div class="theme-gallery">
+            <div class="gallery-controls">
+                <input
+                    type="search"
+                    placeholder="Search themes..."
+                    on:change=move |ev| {
+                        set_search.set(event_target_value(&ev));
+                        set_page.set(1);
+                    }
+                    prop:value=search
+                />
+                <select on:change=move |ev| {
+                    set_sort.set(event_target_value(&ev));
+                    set_page.set(1);
+                }>
+                    <option value="popular">"Popular"</option>
+                    <option value="new">"Newest"</option>
+                </select>
+                {on_select.map(|on_select| view! {
+                    <button
+                        class=move || if is_selected(None) { "winamp-btn-small active" } else { "winamp-btn-small" }
+                        on:click=move |_| on_select.run(None)
+                    >
+                        "DEFAULT THEME"
+                    </button>
+                })}
+            </div>
+            {move || gallery_error.get().map(|e| view! { <p class="hint">{e}</p> })}
+
+            <div class="gallery-grid">
+                {move || match results.get() {
+                    None => view! { <p class="hint">"Loading themes..."</p> }.into_any(),
+                    Some(r) if r.themes.is_empty() => view! { <p class="hint">"No themes found"</p> }.into_any(),
+                    Some(r) => r.themes.into_iter().map(|theme| {
+                        let id = theme.id;
+                        let editable = on_edit.filter(|_| auth.user.get().is_some_and(|u| u.can_edit(theme.created_by)));
+                        view! {
+                            <div class=move || if is_selected(Some(id)) { "gallery-card selected" } else { "gallery-card" }>
+                                <img
+                                    class="gallery-thumb"
+                                    src=format!("/api/themes/{}/thumbnail.svg", id)
+                                    alt=theme.name.clone()
+                                    loading="lazy"
+                                />
+                                <div class="gallery-card-name">{theme.name}</div>
+                                <div class="gallery-card-stats">
+                                    {format!("▲ {} · used by {}", theme.votes, theme.usage_count)}
+                                </div>
+                                <div class="actions">
+                                    <button class="winamp-btn-small" on:click=move |_| vote(id)>"▲ VOTE"</button>
+                                    {on_select.map(|on_select| view! {
+                                        <button class="winamp-btn-small" on:click=move |_| on_select.run(Some(id))>"USE"</button>
+                                    })}
+                                    {editable.map(|on_edit| view! {
+                                        <button class="winamp-btn-small" on:click=move |_| on_edit.run(id)>"EDIT"</button>
+                                        <button class="winamp-btn-small delete" on:click=move |_| delete(id)>"DELETE"</button>
+                                    })}
+                                </div>
+                            </div>
+                        }
+                    }).collect_view().into_any(),
+                }}
+            </div>
+
+            <div class="gallery-pager">
+                <button
+                    class="winamp-btn-small"
+                    disabled=move || page.get() <= 1
+                    on:click=move |_| set_page.update(|p| *p = (*p - 1).max(1))
+                >
+                    "< PREV"
+                </button>
+                <span>{move || format!("Page {} of {}", page.get(), page_count.get())}</span>
+                <button
+                    class="winamp-btn-small"
+                    disabled=move || page.get() >= page_count.get()
+                    on:click=move |_| set_page.update(|p| *p += 1)
+                >
+                    "NEXT >"
+                </button>
             </div>
         </div>
     }
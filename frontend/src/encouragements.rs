@@ -0,0 +1,165 @@
+//! Encouragement on published trackers. Visitors who opened a share link can
+//! leave a short message or a reaction; the owner sees everything left and can
+//! hide or delete messages.
+
+use crate::model::{EncouragementMessage, Encouragements, Tracker};
+use crate::store::{self, TrackerStore};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use shared::encouragement::{MAX_FROM_LEN, MAX_MESSAGE_LEN, REACTIONS};
+use shared::tracker::Encouragement;
+
+/// The owner's or a visitor's view, depending on whose tracker this is.
+#[component]
+pub fn EncouragementPanel(tracker: Tracker) -> impl IntoView {
+    match (&tracker.leaderboard, tracker.server_secret.is_some(), tracker.encouragement_entry) {
+        (Some(_), true, _) => view! { <OwnEncouragements tracker_id=tracker.id /> }.into_any(),
+        (None, _, Some(entry_id)) => view! { <SendEncouragement entry_id=entry_id /> }.into_any(),
+        _ => ().into_any(),
+    }
+}
+
+fn reaction_counts(wall: &Encouragements) -> impl IntoView + use<> {
+    wall.reactions
+        .iter()
+        .map(|r| view! { <span class="reaction-count">{r.reaction.clone()} " " {r.count}</span> })
+        .collect_view()
+}
+
+fn message_line(m: &EncouragementMessage) -> impl IntoView + use<> {
+    let from = if m.from.is_empty() { "Someone".to_string() } else { m.from.clone() };
+    view! {
+        <span class="encouragement-from">{from}</span>
+        <span class="encouragement-text">{m.message.clone()}</span>
+        <span class="encouragement-date">{m.created_at.format("%Y-%m-%d").to_string()}</span>
+    }
+}
+
+#[component]
+fn SendEncouragement(entry_id: uuid::Uuid) -> impl IntoView {
+    let wall = RwSignal::new(None::<Encouragements>);
+    let message = RwSignal::new(String::new());
+    let from = RwSignal::new(String::new());
+    let error = RwSignal::new(None::<String>);
+    let busy = RwSignal::new(false);
+
+    // Nothing shows when the tracker was unpublished since the link was shared.
+    spawn_local(async move {
+        if let Ok(loaded) = store::fetch_encouragements(entry_id).await {
+            wall.set(Some(loaded));
+        }
+    });
+
+    let send = move |encouragement: Encouragement| {
+        let encouragement = match encouragement.normalized() {
+            Ok(e) => e,
+            Err(e) => return error.set(Some(e.to_string())),
+        };
+        busy.set(true);
+        error.set(None);
+        spawn_local(async move {
+            match store::send_encouragement(entry_id, encouragement).await {
+                Ok(updated) => {
+                    wall.set(Some(updated));
+                    message.set(String::new());
+                }
+                Err(e) => error.set(Some(e)),
+            }
+            busy.set(false);
+        });
+    };
+    let submit = move |ev: leptos::ev::SubmitEvent| {
+        ev.prevent_default();
+        send(Encouragement { message: message.get_untracked(), reaction: String::new(), from: from.get_untracked() });
+    };
+
+    move || wall.get().map(|w| view! {
+        <div class="encouragements">
+            <h3>"Cheer them on"</h3>
+            <div class="reactions">
+                {REACTIONS.iter().map(|reaction| {
+                    let count = w.reactions.iter().find(|r| r.reaction == *reaction).map(|r| r.count).unwrap_or(0);
+                    view! {
+                        <button class="reaction-btn" disabled=move || busy.get()
+                            on:click=move |_| send(Encouragement { reaction: reaction.to_string(), from: from.get_untracked(), ..Default::default() })
+                        >
+                            {*reaction} {(count > 0).then(|| format!(" {}", count))}
+                        </button>
+                    }
+                }).collect_view()}
+            </div>
+            <form class="encouragement-form" on:submit=submit>
+                <input type="text" placeholder="Your name (optional)" maxlength=MAX_FROM_LEN
+                    prop:value=from on:input=move |ev| from.set(event_target_value(&ev)) />
+                <input type="text" placeholder="Leave a short message" maxlength=MAX_MESSAGE_LEN
+                    prop:value=message on:input=move |ev| message.set(event_target_value(&ev)) />
+                <button type="submit" class="winamp-btn" disabled=move || busy.get() || message.get().trim().is_empty()>"SEND"</button>
+            </form>
+            {move || error.get().map(|e| view! { <p class="error">{e}</p> })}
+            <ul class="encouragement-list">
+                {w.messages.iter().map(|m| view! { <li>{message_line(m)}</li> }).collect_view()}
+            </ul>
+        </div>
+    })
+}
+
+#[component]
+fn OwnEncouragements(tracker_id: uuid::Uuid) -> impl IntoView {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let wall = RwSignal::new(None::<Encouragements>);
+    let error = RwSignal::new(None::<String>);
+
+    let load = move || {
+        spawn_local(async move {
+            match t_store.own_encouragements(tracker_id).await {
+                Ok(loaded) => wall.set(Some(loaded)),
+                Err(e) => error.set(Some(e)),
+            }
+        });
+    };
+    load();
+
+    let set_hidden = move |id: uuid::Uuid, hidden: bool| {
+        spawn_local(async move {
+            match t_store.set_encouragement_hidden(tracker_id, id, hidden).await {
+                Ok(()) => load(),
+                Err(e) => error.set(Some(e)),
+            }
+        });
+    };
+    let delete = move |id: uuid::Uuid| {
+        spawn_local(async move {
+            match t_store.delete_encouragement(tracker_id, id).await {
+                Ok(()) => load(),
+                Err(e) => error.set(Some(e)),
+            }
+        });
+    };
+
+    view! {
+        <div class="encouragements own">
+            <h3>"Encouragement"</h3>
+            {move || wall.get().map(|w| view! {
+                <div class="reactions">{reaction_counts(&w)}</div>
+                {w.messages.is_empty().then(|| view! {
+                    <p class="encouragement-empty">"No messages yet. Share your tracker so friends can cheer you on."</p>
+                })}
+                <ul class="encouragement-list">
+                    {w.messages.iter().map(|m| {
+                        let (id, hidden) = (m.id, m.hidden);
+                        view! {
+                            <li class:hidden=hidden>
+                                {message_line(m)}
+                                <button class="winamp-btn-small" on:click=move |_| set_hidden(id, !hidden)>
+                                    {if hidden { "SHOW" } else { "HIDE" }}
+                                </button>
+                                <button class="winamp-btn-small delete" on:click=move |_| delete(id)>"DELETE"</button>
+                            </li>
+                        }
+                    }).collect_view()}
+                </ul>
+            })}
+            {move || error.get().map(|e| view! { <p class="error">{e}</p> })}
+        </div>
+    }
+}
@@ -0,0 +1,181 @@
+//! Live updates from `/api/events`: new, edited and deleted habits and themes,
+//! changes other devices make to our shared trackers, and activity in the
+//! groups we are in or looking at. The stream is reopened whenever what we follow changes, and
+//! every (re)open refetches habits and themes so nothing missed stays missing.
+
+use crate::model::{Habit, SharedTracker, Theme, Tracker};
+use crate::store::{HabitStore, ThemeStore, TrackerStore};
+use leptos::prelude::*;
+use leptos::wasm_bindgen::{closure::Closure, JsCast};
+use leptos::web_sys;
+use serde::Deserialize;
+
+/// Shared through context so group pages can follow their group.
+#[derive(Clone, Copy)]
+pub struct LiveEvents {
+    /// Invite codes of the group pages open right now.
+    pub watched_groups: RwSignal<Vec<String>>,
+    /// Set to a group's invite code whenever it changed.
+    pub group_changed: RwSignal<Option<String>>,
+}
+
+impl LiveEvents {
+    /// Follows `invite_code` until the calling component is cleaned up.
+    pub fn watch_group(&self, invite_code: String) {
+        let watched = self.watched_groups;
+        watched.update(|w| w.push(invite_code.clone()));
+        on_cleanup(move || {
+            watched.update(|w| {
+                if let Some(i) = w.iter().position(|c| *c == invite_code) {
+                    w.remove(i);
+                }
+            })
+        });
+    }
+}
+
+#[derive(Deserialize)]
+struct GroupChanged {
+    invite_code: String,
+}
+
+#[derive(Deserialize)]
+struct Removed {
+    id: uuid::Uuid,
+}
+
+/// An open `EventSource` and the listeners it calls, which must live as long as it.
+struct Connection {
+    source: web_sys::EventSource,
+    _listeners: Vec<Closure<dyn FnMut(web_sys::Event)>>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.source.close();
+    }
+}
+
+/// `/api/events` following our shared trackers, their groups and `watched`.
+fn stream_url(trackers: &[Tracker], watched: &[String]) -> String {
+    let shared: Vec<&Tracker> = trackers.iter().filter(|t| t.server_secret.is_some()).collect();
+    let ids: Vec<String> = shared.iter().map(|t| t.id.to_string()).collect();
+    let mut groups: Vec<&str> = shared
+        .iter()
+        .flat_map(|t| t.groups.iter().map(|g| g.invite_code.as_str()))
+        .chain(watched.iter().map(String::as_str))
+        .collect();
+    groups.sort_unstable();
+    groups.dedup();
+    // Tracker ids and invite codes are URL-safe as they are.
+    format!("/api/events?trackers={}&groups={}", ids.join(","), groups.join(","))
+}
+
+/// Opens the event stream and keeps it following the right trackers and
+/// groups. Call once, from the app root, after providing the stores.
+pub fn connect() -> LiveEvents {
+    let h_store = use_context::<HabitStore>().expect("HabitStore not found");
+    let theme_store = use_context::<ThemeStore>().expect("ThemeStore not found");
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let live = LiveEvents {
+        watched_groups: RwSignal::new(Vec::new()),
+        group_changed: RwSignal::new(None),
+    };
+
+    // Not waiting for the stream keeps the app usable where it cannot open.
+    h_store.refresh();
+    theme_store.refresh();
+
+    let url = Memo::new(move |_| t_store.trackers.with(|t| live.watched_groups.with(|w| stream_url(t, w))));
+    let connection = StoredValue::new_local(None::<Connection>);
+    Effect::new(move |_| {
+        let url = url.get();
+        // Dropping the old connection closes it.
+        connection.set_value(None);
+        connection.set_value(open(&url, h_store, theme_store, t_store, live));
+    });
+    live
+}
+
+fn open(url: &str, h_store: HabitStore, theme_store: ThemeStore, t_store: TrackerStore, live: LiveEvents) -> Option<Connection> {
+    let source = web_sys::EventSource::new(url)
+        .map_err(|e| leptos::logging::error!("Failed to open event stream: {:?}", e))
+        .ok()?;
+
+    let resync = move || {
+        h_store.refresh();
+        theme_store.refresh();
+        for code in live.watched_groups.get_untracked() {
+            live.group_changed.set(Some(code));
+        }
+    };
+    let handle = move |name: &str, data: &str| match name {
+        "habit" => {
+            if let Ok(habit) = serde_json::from_str::<Habit>(data) {
+                h_store.upsert(habit);
+            }
+        }
+        "habit_removed" => {
+            if let Ok(removed) = serde_json::from_str::<Removed>(data) {
+                h_store.remove(removed.id);
+            }
+        }
+        "theme" => {
+            if let Ok(theme) = serde_json::from_str::<Theme>(data) {
+                theme_store.upsert(theme);
+            }
+        }
+        "theme_removed" => {
+            if let Ok(removed) = serde_json::from_str::<Removed>(data) {
+                theme_store.remove(removed.id);
+            }
+        }
+        "tracker" => {
+            if let Ok(shared) = serde_json::from_str::<SharedTracker>(data) {
+                t_store.apply_shared(&shared);
+            }
+        }
+        "group" => {
+            if let Ok(changed) = serde_json::from_str::<GroupChanged>(data) {
+                live.group_changed.set(Some(changed.invite_code));
+            }
+        }
+        _ => resync(),
+    };
+
+    // The browser reconnects on its own; each open may follow missed changes.
+    let mut listeners: Vec<Closure<dyn FnMut(web_sys::Event)>> =
+        vec![Closure::new(move |_: web_sys::Event| resync())];
+    let mut names = vec!["open"];
+    for name in ["habit", "habit_removed", "theme", "theme_removed", "tracker", "group", "resync"] {
+        listeners.push(Closure::new(move |event: web_sys::Event| {
+            if let Some(data) = event.dyn_ref::<web_sys::MessageEvent>().and_then(|e| e.data().as_string()) {
+                handle(name, &data);
+            }
+        }));
+        names.push(name);
+    }
+    for (name, listener) in names.into_iter().zip(&listeners) {
+        let _ = source.add_event_listener_with_callback(name, listener.as_ref().unchecked_ref());
+    }
+    Some(Connection { source, _listeners: listeners })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::GroupLink;
+    use chrono::Utc;
+
+    #[test]
+    fn test_stream_follows_shared_trackers_and_watched_groups() {
+        let mut shared = Tracker::new_abstinence(uuid::Uuid::new_v4(), Utc::now(), 1.0, None, "Sam".to_string());
+        shared.server_secret = Some("secret".to_string());
+        shared.groups.push(GroupLink { invite_code: "abc".to_string(), name: "Pals".to_string() });
+        let local = Tracker::new_abstinence(uuid::Uuid::new_v4(), Utc::now(), 1.0, None, "Sam".to_string());
+
+        let url = stream_url(&[shared.clone(), local], &["xyz".to_string(), "abc".to_string()]);
+        assert_eq!(url, format!("/api/events?trackers={}&groups=abc,xyz", shared.id));
+        assert_eq!(stream_url(&[], &[]), "/api/events?trackers=&groups=");
+    }
+}
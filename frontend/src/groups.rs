@@ -0,0 +1,211 @@
+//! Accountability groups: friends quitting the same habit, their trackers
+//! side by side. A group is reached through its invite link, `/groups/{code}`.
+
+use crate::components::HabitIcon;
+use crate::events::LiveEvents;
+use crate::model::{Group, Tracker};
+use crate::store::{self, HabitStore, TrackerStore};
+use chrono::Utc;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use leptos::web_sys;
+
+const MAX_GROUP_NAME_LEN: usize = 60;
+
+pub fn group_path(invite_code: &str) -> String {
+    format!("/groups/{}", invite_code)
+}
+
+fn open_group(set_view: WriteSignal<crate::AppView>, invite_code: &str) {
+    if let Ok(history) = web_sys::window().unwrap().history() {
+        let _ = history.push_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&group_path(invite_code)));
+    }
+    set_view.set(crate::AppView::Group(invite_code.to_string()));
+}
+
+/// The tracker's groups and a form to start a new one, on the tracker page.
+#[component]
+pub fn TrackerGroups(tracker: Tracker) -> impl IntoView {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let set_view = use_context::<WriteSignal<crate::AppView>>().expect("AppView setter not found");
+    let name = RwSignal::new(String::new());
+    let error = RwSignal::new(None::<String>);
+    let busy = RwSignal::new(false);
+    let tracker_id = tracker.id;
+
+    let create = move |ev: leptos::ev::SubmitEvent| {
+        ev.prevent_default();
+        let group_name = name.get_untracked().trim().to_string();
+        if group_name.is_empty() {
+            return;
+        }
+        busy.set(true);
+        error.set(None);
+        spawn_local(async move {
+            match t_store.create_group(tracker_id, group_name).await {
+                Ok(group) => {
+                    name.set(String::new());
+                    open_group(set_view, &group.invite_code);
+                }
+                Err(e) => error.set(Some(e)),
+            }
+            busy.set(false);
+        });
+    };
+
+    view! {
+        <div class="tracker-groups">
+            <h3>"Groups"</h3>
+            {(!tracker.groups.is_empty()).then(|| view! {
+                <ul class="group-links">
+                    {tracker.groups.iter().map(|link| {
+                        let code = link.invite_code.clone();
+                        view! {
+                            <li>
+                                <a href=group_path(&code) on:click=move |ev| {
+                                    ev.prevent_default();
+                                    open_group(set_view, &code);
+                                }>{link.name.clone()}</a>
+                            </li>
+                        }
+                    }).collect_view()}
+                </ul>
+            })}
+            <form class="group-create" on:submit=create>
+                <input type="text" placeholder="Quit together: name a group" maxlength=MAX_GROUP_NAME_LEN
+                    prop:value=name on:input=move |ev| name.set(event_target_value(&ev)) />
+                <button type="submit" class="winamp-btn" disabled=move || busy.get() || name.get().trim().is_empty()>
+                    "START GROUP"
+                </button>
+            </form>
+            {move || error.get().map(|e| view! { <p class="error">{e}</p> })}
+        </div>
+    }
+}
+
+/// A group's members side by side with live counts, plus joining and leaving.
+#[component]
+pub fn GroupView(invite_code: String) -> impl IntoView {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let h_store = use_context::<HabitStore>().expect("HabitStore not found");
+    let live = use_context::<LiveEvents>().expect("LiveEvents not found");
+    let group = RwSignal::new(None::<Result<Group, String>>);
+    let action_error = RwSignal::new(None::<String>);
+    let code = StoredValue::new(invite_code);
+
+    let load = move || {
+        spawn_local(async move {
+            let loaded = store::fetch_group(&code.get_value()).await;
+            group.set(Some(loaded));
+        });
+    };
+    load();
+
+    // Joins, leaves and relapses arrive over the event stream; counts tick locally.
+    live.watch_group(code.get_value());
+    Effect::new(move |_| {
+        if live.group_changed.get().is_some_and(|changed| changed == code.get_value()) {
+            load();
+        }
+    });
+
+    let (now, set_now) = signal(Utc::now());
+    {
+        use leptos::leptos_dom::helpers::set_interval_with_handle;
+        let handle = StoredValue::new_local(
+            set_interval_with_handle(move || set_now.set(Utc::now()), std::time::Duration::from_secs(1)),
+        );
+        // Unlike the dashboard's, this stops when the group page is left.
+        on_cleanup(move || handle.with_value(|h| h.iter().for_each(|h| h.clear())));
+    }
+
+    let join = move |tracker_id: uuid::Uuid| {
+        action_error.set(None);
+        spawn_local(async move {
+            match t_store.join_group(tracker_id, &code.get_value()).await {
+                Ok(joined) => group.set(Some(Ok(joined))),
+                Err(e) => action_error.set(Some(e)),
+            }
+        });
+    };
+    let leave = move |tracker_id: uuid::Uuid| {
+        action_error.set(None);
+        spawn_local(async move {
+            match t_store.leave_group(tracker_id, &code.get_value()).await {
+                Ok(()) => load(),
+                Err(e) => action_error.set(Some(e)),
+            }
+        });
+    };
+    let copy_invite = move |_| {
+        let window = web_sys::window().unwrap();
+        let url = format!("{}{}", window.location().origin().unwrap_or_default(), group_path(&code.get_value()));
+        let _ = window.navigator().clipboard().write_text(&url);
+        let _ = window.alert_with_message("Invite link copied to clipboard!");
+    };
+
+    move || match group.get() {
+        None => view! { <p class="loading">"Loading group..."</p> }.into_any(),
+        Some(Err(e)) => view! { <div class="error">{e}</div> }.into_any(),
+        Some(Ok(g)) => {
+            let habit = h_store.get_habit(g.habit_id);
+            let unit = habit.as_ref().map(|h| h.unit_name.to_lowercase()).unwrap_or_default();
+            let local: Vec<Tracker> = t_store.trackers.get().into_iter().filter(|t| t.habit_id == g.habit_id).collect();
+            let is_member = |id: uuid::Uuid| g.members.iter().any(|m| m.tracker_id == id);
+            let joinable: Vec<Tracker> = local.iter().filter(|t| !is_member(t.id)).cloned().collect();
+            let mine: Vec<uuid::Uuid> = local.iter().map(|t| t.id).filter(|id| is_member(*id)).collect();
+
+            view! {
+                <div class="group-view">
+                    <div class="group-header">
+                        <h2>
+                            {habit.clone().map(|h| view! { <HabitIcon habit=h /> })}
+                            " " {g.name.clone()}
+                        </h2>
+                        <button class="winamp-btn-small" on:click=copy_invite>"COPY INVITE LINK"</button>
+                    </div>
+                    <div class="group-members">
+                        {g.members.iter().map(|m| {
+                            let schedule = m.schedule();
+                            let clean_since = m.clean_since();
+                            let own = mine.contains(&m.tracker_id);
+                            let tracker_id = m.tracker_id;
+                            let unit = unit.clone();
+                            view! {
+                                <div class="group-member" class:own=own>
+                                    <div class="group-member-name">{m.user_name.clone()} {own.then_some(" (you)")}</div>
+                                    <div class="group-member-count">
+                                        {move || format!("{:.2}", schedule.units_at(now.get()))}
+                                    </div>
+                                    <div class="group-member-unit">{format!("{} avoided since start", unit)}</div>
+                                    <div class="group-member-days">
+                                        {move || format!("{} days clean", (now.get() - clean_since).num_days().max(0))}
+                                    </div>
+                                    {own.then(|| view! {
+                                        <button class="winamp-btn-small delete" on:click=move |_| leave(tracker_id)>"LEAVE"</button>
+                                    })}
+                                </div>
+                            }
+                        }).collect_view()}
+                    </div>
+                    <div class="group-join">
+                        {match (habit, joinable.is_empty(), mine.is_empty()) {
+                            (None, _, _) => view! { <p>"This group's habit isn't available here."</p> }.into_any(),
+                            (Some(h), true, true) => view! {
+                                <p>"Start a tracker for " {h.name} " to join this group."</p>
+                            }.into_any(),
+                            (Some(_), true, false) => ().into_any(),
+                            (Some(_), false, _) => joinable.into_iter().map(|t| {
+                                let crate::model::TrackerType::Abstinence { user_name, .. } = &t.tracker_type;
+                                let label = format!("JOIN AS {}", user_name.to_uppercase());
+                                let tracker_id = t.id;
+                                view! { <button class="winamp-btn" on:click=move |_| join(tracker_id)>{label}</button> }
+                            }).collect_view().into_any(),
+                        }}
+                    </div>
+                    {move || action_error.get().map(|e| view! { <p class="error">{e}</p> })}
+                </div>
+            }.into_any()
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! GitHub-style calendar of the last year: one cell per day, coloured by
+//! whether the day was clean, had cravings, or had a relapse.
+//!
+//! Colours come from the `--heatmap-*` custom properties, so themes can set them.
+
+use crate::model::{Tracker, TrackerEvent, TrackerType};
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone};
+use leptos::ev::KeyboardEvent;
+use leptos::prelude::*;
+
+/// Columns shown; one per week.
+pub const WEEKS: usize = 53;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DayStatus {
+    /// Before the tracker started.
+    Untracked,
+    Clean,
+    Craving,
+    Relapse,
+}
+
+impl DayStatus {
+    fn class(self) -> &'static str {
+        match self {
+            DayStatus::Untracked => "heatmap-day untracked",
+            DayStatus::Clean => "heatmap-day clean",
+            DayStatus::Craving => "heatmap-day craving",
+            DayStatus::Relapse => "heatmap-day relapse",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            DayStatus::Untracked => "not tracked",
+            DayStatus::Clean => "clean",
+            DayStatus::Craving => "cravings logged",
+            DayStatus::Relapse => "relapse",
+        }
+    }
+}
+
+/// The Sunday that starts the first column when the last column holds `today`.
+pub fn calendar_start(today: NaiveDate) -> NaiveDate {
+    let back = today.weekday().num_days_from_sunday() as i64 + (WEEKS as i64 - 1) * 7;
+    today - Duration::days(back)
+}
+
+/// Every day from [`calendar_start`] to `today` with its status. Days are
+/// taken in `tz`, so an event late in the evening lands on the user's day.
+pub fn day_statuses<Tz: TimeZone>(tracker: &Tracker, today: NaiveDate, tz: &Tz) -> Vec<(NaiveDate, DayStatus)> {
+    let start = match &tracker.tracker_type {
+        TrackerType::Abstinence { start_date, .. } => start_date.with_timezone(tz).date_naive(),
+    };
+    let first = calendar_start(today);
+    let days = (today - first).num_days().max(0) as usize + 1;
+    let mut statuses: Vec<_> = (0..days)
+        .map(|i| {
+            let day = first + Duration::days(i as i64);
+            (day, if day < start { DayStatus::Untracked } else { DayStatus::Clean })
+        })
+        .collect();
+
+    for event in &tracker.events {
+        let day = event.at().with_timezone(tz).date_naive();
+        let Some((_, status)) = usize::try_from((day - first).num_days()).ok().and_then(|i| statuses.get_mut(i)) else {
+            continue;
+        };
+        // A relapse outranks cravings on the same day.
+        *status = match (event, *status) {
+            (TrackerEvent::Relapse { .. }, _) | (_, DayStatus::Relapse) => DayStatus::Relapse,
+            (TrackerEvent::Craving { .. }, _) => DayStatus::Craving,
+        };
+    }
+    statuses
+}
+
+/// The cell `key` moves to from `index`. Columns are weeks, so up and down
+/// move a day and left and right move a week.
+pub fn step(index: usize, key: &str, len: usize) -> Option<usize> {
+    let last = len.checked_sub(1)?;
+    let target = match key {
+        "ArrowUp" => index.checked_sub(1)?,
+        "ArrowDown" => index + 1,
+        "ArrowLeft" => index.checked_sub(7)?,
+        "ArrowRight" => index + 7,
+        "Home" => 0,
+        "End" => last,
+        _ => return None,
+    };
+    (target <= last).then_some(target)
+}
+
+#[component]
+pub fn TrackerHeatmap(tracker: Tracker) -> impl IntoView {
+    let today = Local::now().date_naive();
+    let days = day_statuses(&tracker, today, &Local);
+    let id_prefix = format!("heatmap-{}", tracker.id);
+    // Start on today so the first arrow press moves from the present.
+    let active = RwSignal::new(days.len().saturating_sub(1));
+    let len = days.len();
+
+    let on_keydown = move |ev: KeyboardEvent| {
+        if let Some(next) = step(active.get_untracked(), &ev.key(), len) {
+            ev.prevent_default();
+            active.set(next);
+        }
+    };
+
+    let cell_id = {
+        let prefix = id_prefix.clone();
+        move |i: usize| format!("{}-{}", prefix, i)
+    };
+    let active_id = {
+        let cell_id = cell_id.clone();
+        move || cell_id(active.get())
+    };
+    let cells = days
+        .iter()
+        .enumerate()
+        .map(|(i, (day, status))| {
+            let title = format!("{}: {}", day.format("%Y-%m-%d"), status.label());
+            view! {
+                <div
+                    id=cell_id(i)
+                    role="option"
+                    class=status.class()
+                    class:active=move || active.get() == i
+                    aria-selected=move || (active.get() == i).to_string()
+                    aria-label=title.clone()
+                    title=title
+                    on:click=move |_| active.set(i)
+                ></div>
+            }
+        })
+        .collect_view();
+    let detail = move || {
+        days.get(active.get())
+            .map(|(day, status)| format!("{}: {}", day.format("%A %B %-d, %Y"), status.label()))
+    };
+
+    view! {
+        <div class="heatmap">
+            <div
+                class="heatmap-grid"
+                role="listbox"
+                tabindex="0"
+                aria-label="Daily history, use the arrow keys to move between days"
+                aria-activedescendant=active_id
+                on:keydown=on_keydown
+            >
+                {cells}
+            </div>
+            <p class="heatmap-detail" aria-live="polite">{detail}</p>
+            <ul class="heatmap-legend">
+                <li><span class="heatmap-day clean"></span>"Clean"</li>
+                <li><span class="heatmap-day craving"></span>"Craving"</li>
+                <li><span class="heatmap-day relapse"></span>"Relapse"</li>
+            </ul>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Utc, Weekday};
+    use uuid::Uuid;
+
+    #[test]
+    fn calendar_covers_whole_weeks_up_to_today() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let start = calendar_start(today);
+        assert_eq!(start.weekday(), Weekday::Sun);
+        assert_eq!((today - start).num_days() as usize / 7, WEEKS - 1);
+    }
+
+    #[test]
+    fn events_colour_their_day() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let at = |d: u32, h: u32| Utc.with_ymd_and_hms(2026, 10, d, h, 0, 0).unwrap();
+        let mut tracker = Tracker::new_abstinence(Uuid::new_v4(), at(10, 12), 1.0, None, "User".to_string());
+        tracker.events = vec![
+            TrackerEvent::Relapse { at: at(12, 9) },
+            crate::journal::craving(at(12, 20), 4, Vec::new(), "", ""),
+            crate::journal::craving(at(14, 8), 7, Vec::new(), "", ""),
+        ];
+
+        let days = day_statuses(&tracker, today, &Utc);
+        let status = |d: u32| days.iter().find(|(day, _)| day.day() == d && day.month() == 10 && day.year() == 2026).unwrap().1;
+        assert_eq!(days.last().unwrap().0, today);
+        assert_eq!(status(9), DayStatus::Untracked);
+        assert_eq!(status(10), DayStatus::Clean);
+        assert_eq!(status(12), DayStatus::Relapse);
+        assert_eq!(status(14), DayStatus::Craving);
+    }
+
+    #[test]
+    fn arrow_keys_move_by_day_and_week() {
+        assert_eq!(step(10, "ArrowDown", 20), Some(11));
+        assert_eq!(step(10, "ArrowRight", 20), Some(17));
+        assert_eq!(step(15, "ArrowRight", 20), None);
+        assert_eq!(step(3, "ArrowLeft", 20), None);
+        assert_eq!(step(0, "ArrowUp", 20), None);
+        assert_eq!(step(5, "End", 20), Some(19));
+        assert_eq!(step(5, "a", 20), None);
+    }
+}
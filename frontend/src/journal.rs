@@ -0,0 +1,281 @@
+//! Craving journal: entries logged against a tracker and what they have in
+//! common — the usual triggers and the time of day cravings hit.
+
+use crate::model::{Tracker, TrackerEvent};
+use crate::store::TrackerStore;
+use chrono::{DateTime, Local, TimeZone, Timelike, Utc};
+use leptos::prelude::*;
+
+/// Offered as one-click triggers; anything else can be typed in.
+pub const SUGGESTED_TRIGGERS: [&str; 6] = ["stress", "social", "boredom", "tiredness", "celebration", "loneliness"];
+pub const MAX_LOCATION_LEN: usize = 60;
+pub const MAX_NOTE_LEN: usize = 1000;
+
+/// A craving entry with its free-text fields trimmed and capped.
+pub fn craving(at: DateTime<Utc>, intensity: u8, triggers: Vec<String>, location: &str, note: &str) -> TrackerEvent {
+    TrackerEvent::Craving {
+        at,
+        intensity: intensity.clamp(1, 10),
+        triggers: shared::habit::normalize_tags(triggers.iter().map(String::as_str)),
+        location: location.trim().chars().take(MAX_LOCATION_LEN).collect(),
+        note: note.trim().chars().take(MAX_NOTE_LEN).collect(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeOfDay {
+    Night,
+    Morning,
+    Afternoon,
+    Evening,
+}
+
+impl TimeOfDay {
+    pub const ALL: [TimeOfDay; 4] = [TimeOfDay::Morning, TimeOfDay::Afternoon, TimeOfDay::Evening, TimeOfDay::Night];
+
+    pub fn from_hour(hour: u32) -> Self {
+        match hour {
+            6..=11 => TimeOfDay::Morning,
+            12..=17 => TimeOfDay::Afternoon,
+            18..=23 => TimeOfDay::Evening,
+            _ => TimeOfDay::Night,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeOfDay::Morning => "Morning (6–12)",
+            TimeOfDay::Afternoon => "Afternoon (12–18)",
+            TimeOfDay::Evening => "Evening (18–24)",
+            TimeOfDay::Night => "Night (0–6)",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JournalSummary {
+    pub cravings: usize,
+    /// Over entries that recorded an intensity.
+    pub average_intensity: Option<f64>,
+    /// Most common first; ties in alphabetical order.
+    pub triggers: Vec<(String, usize)>,
+    /// Cravings per [`TimeOfDay`], in [`TimeOfDay::ALL`] order.
+    pub times_of_day: Vec<(TimeOfDay, usize)>,
+}
+
+/// Summarises the craving entries in `events`, bucketing times of day in `tz`.
+pub fn summarize<Tz: TimeZone>(events: &[TrackerEvent], tz: &Tz) -> JournalSummary {
+    let mut summary = JournalSummary {
+        times_of_day: TimeOfDay::ALL.iter().map(|t| (*t, 0)).collect(),
+        ..Default::default()
+    };
+    let (mut intensity_sum, mut rated) = (0u32, 0u32);
+
+    for event in events {
+        let TrackerEvent::Craving { at, intensity, triggers, .. } = event else { continue };
+        summary.cravings += 1;
+        if *intensity > 0 {
+            intensity_sum += *intensity as u32;
+            rated += 1;
+        }
+        for trigger in triggers {
+            match summary.triggers.iter_mut().find(|(t, _)| t == trigger) {
+                Some((_, count)) => *count += 1,
+                None => summary.triggers.push((trigger.clone(), 1)),
+            }
+        }
+        let bucket = TimeOfDay::from_hour(at.with_timezone(tz).hour());
+        if let Some((_, count)) = summary.times_of_day.iter_mut().find(|(t, _)| *t == bucket) {
+            *count += 1;
+        }
+    }
+
+    summary.average_intensity = (rated > 0).then(|| intensity_sum as f64 / rated as f64);
+    summary.triggers.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+    summary
+}
+
+/// The journal under a tracker: a form for new entries, the summary and past entries.
+#[component]
+pub fn CravingJournal(tracker: Tracker) -> impl IntoView {
+    let summary = summarize(&tracker.events, &Local);
+    let mut entries: Vec<TrackerEvent> = tracker
+        .events
+        .iter()
+        .filter(|e| matches!(e, TrackerEvent::Craving { .. }))
+        .cloned()
+        .collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.at()));
+
+    view! {
+        <div class="journal">
+            <h3>"Craving Journal"</h3>
+            <CravingForm tracker_id=tracker.id />
+            {(summary.cravings > 0).then(|| view! { <JournalSummaryView summary=summary /> })}
+            <ul class="journal-entries">
+                {entries.into_iter().map(|entry| view! { <JournalEntry entry=entry /> }).collect_view()}
+            </ul>
+        </div>
+    }
+}
+
+#[component]
+fn CravingForm(tracker_id: uuid::Uuid) -> impl IntoView {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let intensity = RwSignal::new(5u8);
+    let selected = RwSignal::new(Vec::<String>::new());
+    let other_triggers = RwSignal::new(String::new());
+    let location = RwSignal::new(String::new());
+    let note = RwSignal::new(String::new());
+
+    let toggle = move |trigger: &'static str| {
+        selected.update(|s| match s.iter().position(|t| t == trigger) {
+            Some(i) => {
+                s.remove(i);
+            }
+            None => s.push(trigger.to_string()),
+        });
+    };
+
+    let save = move |ev: leptos::ev::SubmitEvent| {
+        ev.prevent_default();
+        let mut triggers = selected.get_untracked();
+        triggers.extend(shared::habit::parse_tags(&other_triggers.get_untracked()));
+        let event = craving(Utc::now(), intensity.get_untracked(), triggers, &location.get_untracked(), &note.get_untracked());
+        t_store.log_event(tracker_id, event);
+    };
+
+    view! {
+        <form class="journal-form" on:submit=save>
+            <div class="form-group">
+                <label>"Intensity: " {move || intensity.get()} "/10"</label>
+                <input
+                    type="range" min="1" max="10"
+                    prop:value=move || intensity.get().to_string()
+                    on:input=move |ev| intensity.set(event_target_value(&ev).parse().unwrap_or(5))
+                />
+            </div>
+            <div class="form-group">
+                <label>"Triggers:"</label>
+                <div class="tag-filter">
+                    {SUGGESTED_TRIGGERS.into_iter().map(|trigger| view! {
+                        <button
+                            type="button"
+                            class=move || if selected.with(|s| s.iter().any(|t| t == trigger)) { "winamp-btn-small active" } else { "winamp-btn-small" }
+                            aria-pressed=move || selected.with(|s| s.iter().any(|t| t == trigger)).to_string()
+                            on:click=move |_| toggle(trigger)
+                        >
+                            {trigger}
+                        </button>
+                    }).collect_view()}
+                </div>
+                <input type="text" placeholder="other triggers, comma separated"
+                    prop:value=other_triggers on:input=move |ev| other_triggers.set(event_target_value(&ev)) />
+            </div>
+            <div class="form-group">
+                <label>"Where were you?"</label>
+                <input type="text" maxlength=MAX_LOCATION_LEN placeholder="home, work, a bar…"
+                    prop:value=location on:input=move |ev| location.set(event_target_value(&ev)) />
+            </div>
+            <div class="form-group">
+                <label>"Note:"</label>
+                <textarea maxlength=MAX_NOTE_LEN
+                    prop:value=note on:input=move |ev| note.set(event_target_value(&ev))></textarea>
+            </div>
+            <button type="submit" class="winamp-btn">"LOG CRAVING"</button>
+        </form>
+    }
+}
+
+#[component]
+fn JournalSummaryView(summary: JournalSummary) -> impl IntoView {
+    let busiest = summary.times_of_day.iter().map(|(_, n)| *n).max().unwrap_or(0).max(1);
+    view! {
+        <div class="journal-summary">
+            <p>
+                {summary.cravings} " cravings logged"
+                {summary.average_intensity.map(|avg| format!(", average intensity {:.1}/10", avg))}
+            </p>
+            {(!summary.triggers.is_empty()).then(|| view! {
+                <h4>"Most common triggers"</h4>
+                <ol class="journal-top-triggers">
+                    {summary.triggers.iter().take(5).map(|(trigger, n)| view! {
+                        <li>{trigger.clone()} " ×" {*n}</li>
+                    }).collect_view()}
+                </ol>
+            })}
+            <h4>"Time of day"</h4>
+            <table class="journal-times">
+                {summary.times_of_day.iter().map(|(time, n)| view! {
+                    <tr>
+                        <td>{time.label()}</td>
+                        <td><span class="journal-bar" style=format!("width: {}%", n * 100 / busiest)></span></td>
+                        <td>{*n}</td>
+                    </tr>
+                }).collect_view()}
+            </table>
+        </div>
+    }
+}
+
+#[component]
+fn JournalEntry(entry: TrackerEvent) -> impl IntoView {
+    let TrackerEvent::Craving { at, intensity, triggers, location, note } = entry else {
+        return ().into_any();
+    };
+    view! {
+        <li class="journal-entry">
+            <div class="journal-entry-header">
+                <span>{at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()}</span>
+                {(intensity > 0).then(|| view! { <span class="journal-intensity">{intensity} "/10"</span> })}
+                {(!location.is_empty()).then(|| view! { <span class="journal-location">"@ " {location}</span> })}
+            </div>
+            {(!triggers.is_empty()).then(|| view! {
+                <div class="journal-triggers">{triggers.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" ")}</div>
+            })}
+            {(!note.is_empty()).then(|| view! { <p class="journal-note">{note}</p> })}
+        </li>
+    }
+    .into_any()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 18, hour, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn entries_are_cleaned_up() {
+        let TrackerEvent::Craving { intensity, triggers, location, .. } =
+            craving(at(9), 42, vec!["Stress".into(), "stress".into(), " Work Drinks ".into()], &"x".repeat(100), "")
+        else {
+            unreachable!()
+        };
+        assert_eq!(intensity, 10);
+        assert_eq!(triggers, vec!["stress", "work-drinks"]);
+        assert_eq!(location.len(), MAX_LOCATION_LEN);
+    }
+
+    #[test]
+    fn summary_ranks_triggers_and_buckets_times() {
+        let events = vec![
+            craving(at(9), 4, vec!["social".into()], "", ""),
+            craving(at(20), 8, vec!["stress".into(), "social".into()], "", ""),
+            craving(at(21), 6, vec!["boredom".into(), "stress".into()], "", ""),
+            TrackerEvent::Relapse { at: at(22) },
+        ];
+        let summary = summarize(&events, &Utc);
+        assert_eq!(summary.cravings, 3);
+        assert_eq!(summary.average_intensity, Some(6.0));
+        assert_eq!(summary.triggers[0], ("social".to_string(), 2));
+        assert_eq!(summary.triggers[1], ("stress".to_string(), 2));
+        assert_eq!(summary.triggers[2], ("boredom".to_string(), 1));
+        let count = |t| summary.times_of_day.iter().find(|(x, _)| *x == t).unwrap().1;
+        assert_eq!(count(TimeOfDay::Morning), 1);
+        assert_eq!(count(TimeOfDay::Evening), 2);
+        assert_eq!(count(TimeOfDay::Night), 0);
+    }
+}
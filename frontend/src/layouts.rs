@@ -0,0 +1,283 @@
+//! Layouts for the visualizer modes other than roaming. Everything here is
+//! plain geometry in canvas pixels so it can be tested natively.
+
+/// Where one icon is drawn: its centre, rotation in degrees and size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub x: f64,
+    pub y: f64,
+    pub rotation: f64,
+    pub size: f64,
+}
+
+/// Extra strokes drawn under the icons.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decoration {
+    Jar { left: f64, top: f64, width: f64, height: f64 },
+    Marker { y: f64, label: String },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layout {
+    pub placements: Vec<Placement>,
+    pub decorations: Vec<Decoration>,
+}
+
+/// Largest icon size not above `base` at which `n` icons fit `per_row(size) * rows(size)`.
+fn fit_size(n: usize, base: f64, fits: impl Fn(f64) -> usize) -> f64 {
+    let mut size = base;
+    while size > 1.0 && fits(size) < n {
+        size *= 0.95;
+    }
+    size
+}
+
+/// Icons fill a jar from the bottom, shrinking once the jar would overflow.
+pub fn jar(n: usize, width: f64, height: f64, base: f64) -> Layout {
+    let jar_w = (width * 0.5).min(height * 0.8);
+    let jar_h = height * 0.9;
+    let left = (width - jar_w) / 2.0;
+    let top = height * 0.05;
+    let pad = jar_w * 0.06;
+    let (inner_w, inner_h) = (jar_w - 2.0 * pad, jar_h - 2.0 * pad);
+
+    let size = fit_size(n, base, |s| {
+        (inner_w / s).floor() as usize * (inner_h / s).floor() as usize
+    });
+    let per_row = ((inner_w / size).floor() as usize).max(1);
+    let bottom = top + jar_h - pad;
+
+    let placements = (0..n)
+        .map(|i| {
+            let (row, col) = (i / per_row, i % per_row);
+            Placement {
+                x: left + pad + (col as f64 + 0.5) * size,
+                y: bottom - (row as f64 + 0.5) * size,
+                // A slight tilt so the jar looks poured rather than stacked.
+                rotation: ((i * 37) % 31) as f64 - 15.0,
+                size,
+            }
+        })
+        .collect();
+
+    Layout {
+        placements,
+        decorations: vec![Decoration::Jar { left, top, width: jar_w, height: jar_h }],
+    }
+}
+
+const STACK_WIDTH: usize = 10;
+
+/// A tower ten icons wide with labelled height markers on the left.
+pub fn stack(n: usize, width: f64, height: f64, base: f64) -> Layout {
+    let rows = n.div_ceil(STACK_WIDTH).max(1);
+    let usable_h = height * 0.9;
+    let size = base.min(width * 0.4 / STACK_WIDTH as f64).min(usable_h / rows as f64);
+    let left = (width - size * STACK_WIDTH as f64) / 2.0;
+    let bottom = height * 0.95;
+
+    let placements = (0..n)
+        .map(|i| {
+            let (row, col) = (i / STACK_WIDTH, i % STACK_WIDTH);
+            Placement {
+                x: left + (col as f64 + 0.5) * size,
+                y: bottom - (row as f64 + 0.5) * size,
+                rotation: 0.0,
+                size,
+            }
+        })
+        .collect();
+
+    // Markers every "nice" number of units, at least 30px apart.
+    let units_per_px = STACK_WIDTH as f64 / size;
+    let step = nice_step(units_per_px * 30.0);
+    let decorations = (1..)
+        .map(|k| k * step)
+        .take_while(|units| *units <= n.max(step))
+        .map(|units| Decoration::Marker {
+            y: bottom - (units as f64 / STACK_WIDTH as f64) * size,
+            label: units.to_string(),
+        })
+        .collect();
+
+    Layout { placements, decorations }
+}
+
+/// The smallest of 1, 2, 5, 10, 20, 50, … that is at least `min`.
+fn nice_step(min: f64) -> usize {
+    let mut magnitude = 1;
+    loop {
+        for m in [1, 2, 5] {
+            if (m * magnitude) as f64 >= min {
+                return m * magnitude;
+            }
+        }
+        magnitude *= 10;
+    }
+}
+
+/// A calendar-like grid, one cell per unit and `columns` cells per row.
+pub fn grid(n: usize, columns: usize, width: f64, height: f64, base: f64) -> Layout {
+    let columns = columns.max(1);
+    let rows = n.div_ceil(columns).max(1);
+    let size = base.min(width / columns as f64).min(height / rows as f64);
+    let left = (width - size * columns as f64) / 2.0;
+
+    let placements = (0..n)
+        .map(|i| Placement {
+            x: left + ((i % columns) as f64 + 0.5) * size,
+            y: ((i / columns) as f64 + 0.5) * size,
+            rotation: 0.0,
+            size,
+        })
+        .collect();
+
+    Layout { placements, decorations: Vec::new() }
+}
+
+/// Grid columns for a tracker: one row per day when that fits, else a week per row.
+pub fn grid_columns(units_per_day: f64) -> usize {
+    if units_per_day < 1.0 {
+        7
+    } else {
+        (units_per_day.round() as usize).clamp(1, 48)
+    }
+}
+
+const GRAVITY: f64 = 1500.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Body {
+    x: f64,
+    y: f64,
+    vy: f64,
+    rest_y: f64,
+}
+
+/// A heap of icons under gravity. Each icon lands on the column it falls in,
+/// or slides to a lower neighbour first, which gives a sand-pile slope.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pile {
+    width: f64,
+    height: f64,
+    size: f64,
+    columns: Vec<f64>,
+    bodies: Vec<Body>,
+}
+
+impl Pile {
+    /// Picks an icon size at which `capacity` icons fit in the lower half of the canvas.
+    pub fn new(width: f64, height: f64, base: f64, capacity: usize) -> Self {
+        let size = fit_size(capacity, base, |s| {
+            (width / s).floor() as usize * (height * 0.5 / s).floor() as usize
+        });
+        let columns = ((width / size).floor() as usize).max(1);
+        Self { width, height, size, columns: vec![0.0; columns], bodies: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bodies.len()
+    }
+
+    pub fn fits(&self, width: f64, height: f64, n: usize) -> bool {
+        self.width == width && self.height == height && n <= self.bodies.len().max(self.capacity())
+    }
+
+    fn capacity(&self) -> usize {
+        self.columns.len() * (self.height * 0.5 / self.size).floor() as usize
+    }
+
+    /// Adds an icon above column `at` (0..1). With `drop` it falls from the top,
+    /// otherwise it starts where it would land.
+    pub fn add(&mut self, at: f64, drop: bool) {
+        let last = self.columns.len() - 1;
+        let mut col = ((at * self.columns.len() as f64) as usize).min(last);
+        loop {
+            let here = self.columns[col];
+            let lower = [col.checked_sub(1), (col < last).then_some(col + 1)]
+                .into_iter()
+                .flatten()
+                .filter(|&c| self.columns[c] + self.size <= here)
+                .min_by(|&a, &b| self.columns[a].total_cmp(&self.columns[b]));
+            match lower {
+                Some(c) => col = c,
+                None => break,
+            }
+        }
+
+        let rest_y = self.height - self.columns[col] - self.size / 2.0;
+        self.columns[col] += self.size;
+        let x = (col as f64 + 0.5) * (self.width / self.columns.len() as f64);
+        let y = if drop { -self.size } else { rest_y };
+        self.bodies.push(Body { x, y, vy: 0.0, rest_y });
+    }
+
+    pub fn step(&mut self, dt: f64) {
+        for body in self.bodies.iter_mut().filter(|b| b.y < b.rest_y) {
+            body.vy += GRAVITY * dt;
+            body.y = (body.y + body.vy * dt).min(body.rest_y);
+        }
+    }
+
+    pub fn placements(&self, out: &mut Vec<Placement>) {
+        out.extend(self.bodies.iter().map(|b| Placement { x: b.x, y: b.y, rotation: 0.0, size: self.size }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inside(p: &Placement, width: f64, height: f64) -> bool {
+        p.x >= 0.0 && p.x <= width && p.y >= 0.0 && p.y <= height
+    }
+
+    #[test]
+    fn static_layouts_stay_on_the_canvas() {
+        for n in [0, 1, 9, 500, 20_000] {
+            for layout in [jar(n, 400.0, 300.0, 20.0), stack(n, 400.0, 300.0, 20.0), grid(n, 24, 400.0, 300.0, 20.0)] {
+                assert_eq!(layout.placements.len(), n);
+                assert!(layout.placements.iter().all(|p| inside(p, 400.0, 300.0)));
+            }
+        }
+    }
+
+    #[test]
+    fn grid_rows_are_days_or_weeks() {
+        assert_eq!(grid_columns(0.5), 7);
+        assert_eq!(grid_columns(12.4), 12);
+        assert_eq!(grid_columns(500.0), 48);
+    }
+
+    #[test]
+    fn stack_markers_use_round_numbers() {
+        let layout = stack(1000, 400.0, 300.0, 20.0);
+        let labels: Vec<_> = layout
+            .decorations
+            .iter()
+            .filter_map(|d| match d {
+                Decoration::Marker { label, .. } => Some(label.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(labels.first(), Some(&"200"));
+        assert_eq!(labels.last(), Some(&"1000"));
+    }
+
+    #[test]
+    fn pile_settles_into_a_slope() {
+        let mut pile = Pile::new(100.0, 100.0, 10.0, 20);
+        for _ in 0..6 {
+            pile.add(0.5, true);
+        }
+        for _ in 0..200 {
+            pile.step(1.0 / 60.0);
+        }
+        let mut placements = Vec::new();
+        pile.placements(&mut placements);
+        assert!(placements.iter().all(|p| inside(p, 100.0, 100.0)));
+        // Dropping on one spot spreads sideways instead of building a single column.
+        let tallest = pile.columns.iter().cloned().fold(0.0, f64::max);
+        assert!(tallest <= 2.0 * pile.size);
+    }
+}
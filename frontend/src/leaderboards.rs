@@ -0,0 +1,206 @@
+//! Opt-in public leaderboards, one per habit, reached at `/leaderboards/{slug}`.
+//! Trackers are listed under a nickname only.
+
+use crate::components::HabitIcon;
+use crate::model::{Habit, LeaderboardEntry, Tracker};
+use crate::store::{self, HabitStore, TrackerStore};
+use chrono::Utc;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use leptos::web_sys;
+
+const MAX_NICKNAME_LEN: usize = 24;
+
+pub fn leaderboard_path(habit: &Habit) -> String {
+    format!("/leaderboards/{}", habit.slug)
+}
+
+pub fn open_leaderboard(set_view: WriteSignal<crate::AppView>, habit: &Habit) {
+    if let Ok(history) = web_sys::window().unwrap().history() {
+        let _ = history.push_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&leaderboard_path(habit)));
+    }
+    set_view.set(crate::AppView::Leaderboard(habit.id));
+}
+
+/// Publishing the tracker to its habit's leaderboard, on the tracker page.
+#[component]
+pub fn LeaderboardOptIn(tracker: Tracker, habit: Habit) -> impl IntoView {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let set_view = use_context::<WriteSignal<crate::AppView>>().expect("AppView setter not found");
+    let listed = tracker.leaderboard.clone();
+    let nickname = RwSignal::new(listed.as_ref().map(|l| l.nickname.clone()).unwrap_or_default());
+    let error = RwSignal::new(None::<String>);
+    let busy = RwSignal::new(false);
+    let tracker_id = tracker.id;
+
+    let publish = move |ev: leptos::ev::SubmitEvent| {
+        ev.prevent_default();
+        busy.set(true);
+        error.set(None);
+        spawn_local(async move {
+            if let Err(e) = t_store.publish(tracker_id, nickname.get_untracked()).await {
+                error.set(Some(e));
+            }
+            busy.set(false);
+        });
+    };
+    let unpublish = move |_| {
+        busy.set(true);
+        error.set(None);
+        spawn_local(async move {
+            if let Err(e) = t_store.unpublish(tracker_id).await {
+                error.set(Some(e));
+            }
+            busy.set(false);
+        });
+    };
+    let view_board = {
+        let habit = habit.clone();
+        move |ev: leptos::ev::MouseEvent| {
+            ev.prevent_default();
+            open_leaderboard(set_view, &habit);
+        }
+    };
+
+    view! {
+        <div class="leaderboard-opt-in">
+            <h3>"Leaderboard"</h3>
+            {match &listed {
+                Some(l) => view! {
+                    <p>"Listed as " <strong>{l.nickname.clone()}</strong> " on the "
+                        <a href=leaderboard_path(&habit) on:click=view_board>{format!("{} leaderboard", habit.name)}</a> "."
+                    </p>
+                }.into_any(),
+                None => view! {
+                    <p class="leaderboard-privacy">"Only a nickname, your streak and your count are shown. Your name stays private."</p>
+                }.into_any(),
+            }}
+            <form class="leaderboard-publish" on:submit=publish>
+                <input type="text" placeholder="Nickname" maxlength=MAX_NICKNAME_LEN
+                    prop:value=nickname on:input=move |ev| nickname.set(event_target_value(&ev)) />
+                <button type="submit" class="winamp-btn" disabled=move || busy.get() || nickname.get().trim().chars().count() < 2>
+                    {if listed.is_some() { "RENAME" } else { "PUBLISH" }}
+                </button>
+                {listed.is_some().then(|| view! {
+                    <button type="button" class="winamp-btn delete" disabled=move || busy.get() on:click=unpublish>"REMOVE"</button>
+                })}
+            </form>
+            {move || error.get().map(|e| view! { <p class="error">{e}</p> })}
+        </div>
+    }
+}
+
+/// A habit's leaderboard with live counts, by streak or by units avoided.
+#[component]
+pub fn LeaderboardView(habit_id: uuid::Uuid) -> impl IntoView {
+    let h_store = use_context::<HabitStore>().expect("HabitStore not found");
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let set_view = use_context::<WriteSignal<crate::AppView>>().expect("AppView setter not found");
+    let habit = Memo::new(move |_| h_store.get_habit(habit_id));
+    let sort = RwSignal::new("streak");
+    let entries = RwSignal::new(None::<Result<Vec<LeaderboardEntry>, String>>);
+    let reported = RwSignal::new(Vec::<uuid::Uuid>::new());
+    let report_error = RwSignal::new(None::<String>);
+
+    Effect::new(move |_| {
+        let (Some(h), sort) = (habit.get(), sort.get()) else { return };
+        spawn_local(async move {
+            entries.set(Some(store::fetch_leaderboard(&h.slug, sort).await));
+        });
+    });
+
+    let (now, set_now) = signal(Utc::now());
+    {
+        use leptos::leptos_dom::helpers::set_interval_with_handle;
+        let handle = StoredValue::new_local(
+            set_interval_with_handle(move || set_now.set(Utc::now()), std::time::Duration::from_secs(1)),
+        );
+        on_cleanup(move || handle.with_value(|h| h.iter().for_each(|h| h.clear())));
+    }
+
+    let report = move |entry_id: uuid::Uuid| {
+        let window = web_sys::window().unwrap();
+        if !window.confirm_with_message("Report this entry for an offensive nickname or made-up numbers?").unwrap_or(false) {
+            return;
+        }
+        report_error.set(None);
+        spawn_local(async move {
+            match store::flag_entry(entry_id).await {
+                Ok(()) => reported.update(|r| r.push(entry_id)),
+                Err(e) => report_error.set(Some(e)),
+            }
+        });
+    };
+    let sort_button = move |key: &'static str, label: &'static str| view! {
+        <button
+            class=move || if sort.get() == key { "winamp-btn-small active" } else { "winamp-btn-small" }
+            on:click=move |_| sort.set(key)
+        >{label}</button>
+    };
+
+    move || match habit.get() {
+        None => view! { <div class="error">"Habit not found"</div> }.into_any(),
+        Some(h) => {
+            let unit = h.unit_name.to_lowercase();
+            let own: Vec<uuid::Uuid> = t_store
+                .trackers
+                .get()
+                .iter()
+                .filter_map(|t| t.leaderboard.as_ref().map(|l| l.entry_id))
+                .collect();
+            view! {
+                <div class="leaderboard-view">
+                    <div class="leaderboard-header">
+                        <h2><HabitIcon habit=h.clone() /> " " {h.name.clone()} " leaderboard"</h2>
+                        <div class="leaderboard-sort">
+                            {sort_button("streak", "STREAK")}
+                            {sort_button("units", "AVOIDED")}
+                        </div>
+                    </div>
+                    {move || match entries.get() {
+                        None => view! { <p class="loading">"Loading leaderboard..."</p> }.into_any(),
+                        Some(Err(e)) => view! { <div class="error">{e}</div> }.into_any(),
+                        Some(Ok(list)) if list.is_empty() => view! {
+                            <p>"Nobody is on this leaderboard yet. Publish a tracker from its page to be the first."</p>
+                        }.into_any(),
+                        Some(Ok(list)) => view! {
+                            <ol class="leaderboard">
+                                {list.into_iter().map(|entry| {
+                                    let mine = own.contains(&entry.entry_id);
+                                    let schedule = entry.schedule();
+                                    let clean_since = entry.clean_since;
+                                    let entry_id = entry.entry_id;
+                                    let unit = unit.clone();
+                                    view! {
+                                        <li class="leaderboard-entry" class:own=mine>
+                                            <span class="leaderboard-nickname">{entry.nickname} {mine.then_some(" (you)")}</span>
+                                            <span class="leaderboard-streak">
+                                                {move || format!("{} days", (now.get() - clean_since).num_days().max(0))}
+                                            </span>
+                                            <span class="leaderboard-units">
+                                                {move || format!("{:.0} {} since start", schedule.units_at(now.get()), unit)}
+                                            </span>
+                                            {(!mine).then(|| view! {
+                                                {move || if reported.get().contains(&entry_id) {
+                                                    view! { <span class="leaderboard-reported">"Reported"</span> }.into_any()
+                                                } else {
+                                                    view! {
+                                                        <button class="winamp-btn-small" on:click=move |_| report(entry_id)>"REPORT"</button>
+                                                    }.into_any()
+                                                }}
+                                            })}
+                                        </li>
+                                    }
+                                }).collect_view()}
+                            </ol>
+                        }.into_any(),
+                    }}
+                    {move || report_error.get().map(|e| view! { <p class="error">{e}</p> })}
+                    <div class="habit-detail-actions">
+                        <button class="winamp-btn" on:click=move |_| set_view.set(crate::AppView::HabitDetail(habit_id))>"< BACK"</button>
+                    </div>
+                </div>
+            }.into_any()
+        }
+    }
+}
@@ -1,13 +1,28 @@
 use leptos::prelude::*;
 
+mod auth;
+mod badges;
+mod charts;
+mod checkin;
 mod components;
+mod encouragements;
+mod events;
+mod groups;
+mod heatmap;
+mod journal;
+mod layouts;
+mod leaderboards;
 mod model;
+mod notifications;
+mod rng;
+mod stats;
 mod store;
+mod visualizer;
 
 use store::{HabitStore, TrackerStore};
-use components::{TrackersView, HabitManager, TrackerCreator, TrackerDetailView, HabitDetailView, ThemeManager};
+use components::{TrackersView, HabitManager, TrackerCreator, TrackerDetailView, HabitDetailView, ThemeManager, ThemeDetailView};
 #[cfg(target_arch = "wasm32")]
-use shared::tracker::TrackerState;
+use shared::tracker::{TrackerState, VisualizerMode};
 #[cfg(target_arch = "wasm32")]
 use crate::model::{Tracker, TrackerType};
 use uuid::Uuid;
@@ -20,22 +35,35 @@ pub enum AppView {
     Habits,
     NewTracker,
     Themes,
+    Stats,
     TrackerDetail(Uuid),
     HabitDetail(Uuid),
+    Theme(Uuid),
+    Group(String),
+    Leaderboard(Uuid),
+    Login,
 }
 
 #[component]
 pub fn App() -> impl IntoView {
     let h_store = HabitStore::new();
-    let t_store = TrackerStore::new();
+    let n_store = store::NotificationStore::new();
+    let t_store = TrackerStore::new(h_store, n_store);
     let theme_store = store::ThemeStore::new();
+    provide_context(store::AuthStore::new());
     provide_context(h_store);
+    provide_context(store::CheckInStore::new());
+    provide_context(n_store);
     provide_context(t_store);
     provide_context(theme_store);
+    provide_context(events::connect());
     
     let (current_view, set_current_view) = signal(AppView::Trackers);
     provide_context(set_current_view); // Provide the setter to components so they can navigate
 
+    notifications::start_scheduler();
+
+    #[cfg(target_arch = "wasm32")]
     let habits = h_store.habits;
     #[cfg(target_arch = "wasm32")]
     let trackers = t_store.trackers;
@@ -47,15 +75,24 @@ pub fn App() -> impl IntoView {
 
     // Routing Logic
     let route = move || {
-        // Only route if habits are loaded
-        if habits.get().is_empty() { return; }
-
         #[cfg(target_arch = "wasm32")]
         {
             let window = leptos::web_sys::window().unwrap();
             let location = window.location();
             let pathname = location.pathname().unwrap_or_default();
             let hash = location.hash().unwrap_or_default();
+
+            // Login links carry no habit, so they need not wait for habits.
+            // Rerouting once habits arrive must not redeem the link twice.
+            if pathname == "/login" {
+                if current_view.get_untracked() != AppView::Login {
+                    set_current_view.set(AppView::Login);
+                }
+                return;
+            }
+
+            // Only route if habits are loaded
+            if habits.get().is_empty() { return; }
             
             leptos::logging::log!("Routing check: Path='{}', Hash='{}'", pathname, hash);
 
@@ -73,10 +110,29 @@ pub fn App() -> impl IntoView {
                 set_current_view.set(AppView::Themes);
                 return;
             }
+            if let Some(id) = pathname.strip_prefix("/themes/").and_then(|id| Uuid::parse_str(id).ok()) {
+                set_current_view.set(AppView::Theme(id));
+                return;
+            }
+            if pathname == "/stats" {
+                set_current_view.set(AppView::Stats);
+                return;
+            }
             if pathname == "/new-tracker" {
                 set_current_view.set(AppView::NewTracker);
                 return;
             }
+            if let Some(code) = pathname.strip_prefix("/groups/").filter(|c| !c.is_empty()) {
+                set_current_view.set(AppView::Group(code.to_string()));
+                return;
+            }
+            if let Some(slug) = pathname.strip_prefix("/leaderboards/") {
+                match habits.get().iter().find(|h| h.slug == slug) {
+                    Some(h) => set_current_view.set(AppView::Leaderboard(h.id)),
+                    None => set_current_view.set(AppView::Habits),
+                }
+                return;
+            }
 
             if hash.len() > 1 {
                 let encoded = &hash[1..];
@@ -89,6 +145,10 @@ pub fn App() -> impl IntoView {
                             
                             if let Some(h) = habits.get().iter().find(|h| h.slug == slug).cloned() {
                                 let dt = DateTime::from_timestamp(state.start_timestamp, 0).unwrap();
+                                let url_mode = state.visualizer_mode.and_then(|m| VisualizerMode::try_from(m).ok());
+                                let url_badges = shared_badges(&state);
+                                let url_entry = state.leaderboard_entry.as_deref().and_then(|id| uuid::Uuid::parse_str(id).ok());
+                                let url_relapse = state.last_relapse_timestamp.and_then(|ts| DateTime::from_timestamp(ts, 0));
                                 
                                 // Check for exact existing tracker for this habit and timestamp
                                 // We need to check against the CURRENT trackers in store
@@ -105,7 +165,7 @@ pub fn App() -> impl IntoView {
                                     let mut needs_update = false;
                                     let mut updated_tracker = existing.clone();
                                     
-                                    if let TrackerType::Abstinence { units_per_day, theme_id, user_name, .. } = &mut updated_tracker.tracker_type {
+                                    if let TrackerType::Abstinence { units_per_day, theme_id, user_name, visualizer_mode } = &mut updated_tracker.tracker_type {
                                         let url_theme_id = state.theme_id.as_ref().and_then(|id_str| uuid::Uuid::parse_str(id_str).ok());
                                         
                                         if (*units_per_day - state.units_per_day).abs() > f64::EPSILON {
@@ -120,8 +180,31 @@ pub fn App() -> impl IntoView {
                                             *user_name = state.user_name.clone();
                                             needs_update = true;
                                         }
+                                        if *visualizer_mode != url_mode {
+                                            *visualizer_mode = url_mode;
+                                            needs_update = true;
+                                        }
                                     }
                                     
+                                    // Links without badges (e.g. from the tracker list) keep the ones already shared.
+                                    if !url_badges.is_empty() && updated_tracker.shared_achievements != url_badges {
+                                        updated_tracker.shared_achievements = url_badges;
+                                        needs_update = true;
+                                    }
+
+                                    // Our own published tracker needs no link back to itself.
+                                    if url_entry.is_some() && updated_tracker.leaderboard.is_none() && updated_tracker.encouragement_entry != url_entry {
+                                        updated_tracker.encouragement_entry = url_entry;
+                                        needs_update = true;
+                                    }
+
+                                    // Counts restart at the sharer's last relapse, as on their card.
+                                    if let Some(at) = url_relapse
+                                        && updated_tracker.merge_relapse(at)
+                                    {
+                                        needs_update = true;
+                                    }
+
                                     if needs_update {
                                         leptos::logging::log!("Routing: Updating existing tracker {} from URL state", existing.id);
                                         t_store.update_tracker(updated_tracker);
@@ -131,7 +214,15 @@ pub fn App() -> impl IntoView {
                                 } else {
                                     leptos::logging::log!("Routing: Creating new tracker for habit {}", h.id);
                                     let t_id = state.theme_id.and_then(|id_str| uuid::Uuid::parse_str(&id_str).ok());
-                                    let tracker = Tracker::new_abstinence(h.id, dt, state.units_per_day, t_id, state.user_name);
+                                    let mut tracker = Tracker::new_abstinence(h.id, dt, state.units_per_day, t_id, state.user_name);
+                                    match &mut tracker.tracker_type {
+                                        TrackerType::Abstinence { visualizer_mode, .. } => *visualizer_mode = url_mode,
+                                    }
+                                    tracker.shared_achievements = url_badges;
+                                    tracker.encouragement_entry = url_entry;
+                                    if let Some(at) = url_relapse {
+                                        tracker.merge_relapse(at);
+                                    }
                                     t_store.add_tracker(tracker.clone());
                                     tracker.id
                                 };
@@ -148,6 +239,9 @@ pub fn App() -> impl IntoView {
                         set_current_view.set(AppView::Trackers);
                     }
                 }
+            } else if let Some(h) = habits.get().iter().find(|h| h.slug == pathname.trim_matches('/')) {
+                // A bare slug, as the habits feed links to it.
+                set_current_view.set(AppView::HabitDetail(h.id));
             } else if pathname != "/" {
                  // Handle case where we have slug but no hash (maybe show habit details? or redirect?)
                  // For now, if just slug is present, maybe show Dashboard or HabitDetail if we supported it via slug
@@ -211,7 +305,19 @@ pub fn App() -> impl IntoView {
                     >
                         "THEMES"
                     </button>
+                    <button 
+                        class=move || if current_view.get() == AppView::Stats { "winamp-btn active" } else { "winamp-btn" }
+                        on:click=move |_| {
+                            set_current_view.set(AppView::Stats);
+                            if let Ok(history) = web_sys::window().unwrap().history() {
+                                let _ = history.push_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some("/stats"));
+                            }
+                        }
+                    >
+                        "STATS"
+                    </button>
                 </nav>
+                <auth::AccountMenu />
             </header>
 
             <main>
@@ -222,8 +328,13 @@ pub fn App() -> impl IntoView {
                         <TrackerCreator on_close=move || set_current_view.set(AppView::Trackers) /> 
                     }.into_any(),
                     AppView::Themes => view! { <ThemeManager /> }.into_any(),
+                    AppView::Stats => view! { <stats::StatsView /> }.into_any(),
                     AppView::TrackerDetail(id) => view! { <TrackerDetailView tracker_id=id /> }.into_any(),
                     AppView::HabitDetail(id) => view! { <HabitDetailView habit_id=id /> }.into_any(),
+                    AppView::Theme(id) => view! { <ThemeDetailView theme_id=id /> }.into_any(),
+                    AppView::Group(code) => view! { <groups::GroupView invite_code=code /> }.into_any(),
+                    AppView::Leaderboard(id) => view! { <leaderboards::LeaderboardView habit_id=id /> }.into_any(),
+                    AppView::Login => view! { <auth::LoginView /> }.into_any(),
                 }}
             </main>
             
@@ -234,6 +345,17 @@ pub fn App() -> impl IntoView {
     }
 }
 
+/// Achievement ids from a share link, dropping any this build doesn't know.
+#[cfg(target_arch = "wasm32")]
+fn shared_badges(state: &TrackerState) -> Vec<String> {
+    state
+        .achievements
+        .iter()
+        .filter(|id| shared::achievements::find(id).is_some())
+        .cloned()
+        .collect()
+}
+
 #[wasm_bindgen::prelude::wasm_bindgen(start)]
 pub fn main() {
     console_error_panic_hook::set_once();
@@ -1,13 +1,25 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use uuid::Uuid;
+use shared::tracker::{ThemeTokens, VisualizerMode};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Theme {
     pub id: Uuid,
     pub name: String,
+    /// Compiled stylesheet (tokens followed by the override), scoped by the server.
     pub css: String,
     pub icon_limit: Option<i32>,
+    #[serde(default)]
+    pub tokens: Option<ThemeTokens>,
+    #[serde(default)]
+    pub css_override: String,
+    #[serde(default)]
+    pub usage_count: i64,
+    #[serde(default)]
+    pub votes: i64,
+    #[serde(default)]
+    pub created_by: Option<Uuid>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -17,6 +29,20 @@ pub struct Habit {
     pub name: String,
     pub icon: String,
     pub unit_name: String,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub has_icon_image: bool,
+    #[serde(default)]
+    pub created_by: Option<Uuid>,
+    /// Hidden by an admin, and left out of the library.
+    #[serde(default)]
+    pub hidden: bool,
+    /// The habit an admin merged this duplicate into.
+    #[serde(default)]
+    pub merged_into: Option<Uuid>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -26,14 +52,218 @@ pub enum TrackerType {
         units_per_day: f64,
         theme_id: Option<Uuid>,
         user_name: String,
+        /// `None` follows the theme's default mode.
+        #[serde(default)]
+        visualizer_mode: Option<VisualizerMode>,
     },
 }
 
+/// Something the user logged against a tracker.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum TrackerEvent {
+    /// A journal entry. Entries logged before the journal existed only have `at`.
+    Craving {
+        at: DateTime<Utc>,
+        /// 1–10, or 0 when not recorded.
+        #[serde(default)]
+        intensity: u8,
+        #[serde(default)]
+        triggers: Vec<String>,
+        #[serde(default)]
+        location: String,
+        #[serde(default)]
+        note: String,
+    },
+    Relapse { at: DateTime<Utc> },
+}
+
+impl TrackerEvent {
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            TrackerEvent::Craving { at, .. } | TrackerEvent::Relapse { at } => *at,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Tracker {
     pub id: Uuid,
     pub habit_id: Uuid,
     pub tracker_type: TrackerType,
+    #[serde(default)]
+    pub events: Vec<TrackerEvent>,
+    /// Achievement ids that came with a share link, shown alongside those
+    /// unlocked locally since the events behind them stay with the sharer.
+    #[serde(default)]
+    pub shared_achievements: Vec<String>,
+    /// Handed out when the tracker is first copied to the server for a group;
+    /// proves this browser owns the copy.
+    #[serde(default)]
+    pub server_secret: Option<String>,
+    #[serde(default)]
+    pub groups: Vec<GroupLink>,
+    #[serde(default)]
+    pub leaderboard: Option<LeaderboardListing>,
+    /// The leaderboard entry of the published tracker a share link came from;
+    /// encouragement left here goes to its owner.
+    #[serde(default)]
+    pub encouragement_entry: Option<Uuid>,
+}
+
+/// How a tracker is listed on its habit's public leaderboard.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LeaderboardListing {
+    pub entry_id: Uuid,
+    pub nickname: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct LeaderboardEntry {
+    pub entry_id: Uuid,
+    pub nickname: String,
+    pub start_date: DateTime<Utc>,
+    pub units_per_day: f64,
+    pub clean_since: DateTime<Utc>,
+}
+
+impl LeaderboardEntry {
+    pub fn schedule(&self) -> shared::milestones::Schedule {
+        shared::milestones::Schedule { start: self.start_date, units_per_day: self.units_per_day }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct EncouragementMessage {
+    pub id: Uuid,
+    pub message: String,
+    pub from: String,
+    pub created_at: DateTime<Utc>,
+    pub hidden: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ReactionCount {
+    pub reaction: String,
+    pub count: i64,
+}
+
+/// Messages and reactions left on a published tracker.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Encouragements {
+    pub messages: Vec<EncouragementMessage>,
+    pub reactions: Vec<ReactionCount>,
+}
+
+/// What the server answers a publish with.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Publication {
+    pub entry_id: Uuid,
+    #[serde(default)]
+    pub tracker_secret: Option<String>,
+}
+
+/// A group a tracker belongs to, enough to list and link to it.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GroupLink {
+    pub invite_code: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GroupMember {
+    pub tracker_id: Uuid,
+    pub user_name: String,
+    pub start_date: DateTime<Utc>,
+    pub units_per_day: f64,
+    #[serde(default)]
+    pub last_relapse_at: Option<DateTime<Utc>>,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl GroupMember {
+    pub fn schedule(&self) -> shared::milestones::Schedule {
+        shared::milestones::Schedule { start: self.start_date, units_per_day: self.units_per_day }
+    }
+
+    /// The start date, or the last relapse when there was one since.
+    pub fn clean_since(&self) -> DateTime<Utc> {
+        self.last_relapse_at.map_or(self.start_date, |at| at.max(self.start_date))
+    }
+}
+
+/// The server copy of one of our trackers, as streamed by `/api/events` after
+/// another device holding it changed it.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SharedTracker {
+    pub id: Uuid,
+    pub user_name: String,
+    pub start_date: DateTime<Utc>,
+    pub units_per_day: f64,
+    pub last_relapse_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Group {
+    pub invite_code: String,
+    pub name: String,
+    pub habit_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub members: Vec<GroupMember>,
+}
+
+/// What joining or creating a group returns.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Membership {
+    pub group: Group,
+    #[serde(default)]
+    pub tracker_secret: Option<String>,
+}
+
+/// The daily "how are you today?" answer. One per local day, across all trackers.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CheckIn {
+    pub date: NaiveDate,
+    /// 1 (awful) to 5 (great).
+    pub mood: u8,
+    pub sleep_hours: f32,
+    #[serde(default)]
+    pub note: String,
+}
+
+/// Milestone notification preferences, shared by all trackers.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct NotificationSettings {
+    /// Trackers that opted in.
+    #[serde(default)]
+    pub trackers: Vec<Uuid>,
+    /// Local hours; notifications due from `quiet_start` until `quiet_end`
+    /// wait for `quiet_end`. Equal hours disable quiet hours.
+    pub quiet_start: u8,
+    pub quiet_end: u8,
+    /// The last milestone each tracker was notified about.
+    #[serde(default)]
+    pub last_notified: std::collections::HashMap<Uuid, DateTime<Utc>>,
+}
+
+impl NotificationSettings {
+    pub fn quiet_hours(&self) -> shared::milestones::QuietHours {
+        shared::milestones::QuietHours { start: self.quiet_start, end: self.quiet_end }
+    }
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self { trackers: Vec::new(), quiet_start: 22, quiet_end: 8, last_notified: Default::default() }
+    }
+}
+
+/// Everything kept in local storage, as written by the export button.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ExportData {
+    pub exported_at: DateTime<Utc>,
+    pub trackers: Vec<Tracker>,
+    #[serde(default)]
+    pub check_ins: Vec<CheckIn>,
 }
 
 impl Habit {
@@ -44,8 +274,87 @@ impl Habit {
             name: name.to_string(),
             icon: icon.to_string(),
             unit_name: unit.to_string(),
+            category: String::new(),
+            tags: Vec::new(),
+            has_icon_image: false,
+            created_by: None,
+            hidden: false,
+            merged_into: None,
+        }
+    }
+
+    /// Whether the habit belongs in the library and pickers.
+    pub fn listed(&self) -> bool {
+        !self.hidden && self.merged_into.is_none()
+    }
+}
+
+/// The habit `id` stands for, following merges to the habit that replaced it.
+pub fn resolve_habit(habits: &[Habit], id: Uuid) -> Option<&Habit> {
+    let mut habit = habits.iter().find(|h| h.id == id)?;
+    // Bounded, in case merges ever form a loop.
+    for _ in 0..habits.len() {
+        match habit.merged_into.and_then(|into| habits.iter().find(|h| h.id == into)) {
+            Some(replacement) => habit = replacement,
+            None => break,
         }
     }
+    Some(habit)
+}
+
+impl Habit {
+    /// URL of the uploaded image icon, if the habit has one.
+    pub fn icon_image_url(&self) -> Option<String> {
+        self.has_icon_image.then(|| format!("/api/habits/{}/icon", self.id))
+    }
+}
+
+/// The logged-in account, from `/api/auth/me`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct User {
+    pub id: Uuid,
+    pub email: String,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+impl User {
+    /// Whether this user may edit or delete something `owner` created.
+    pub fn can_edit(&self, owner: Option<Uuid>) -> bool {
+        self.is_admin || owner == Some(self.id)
+    }
+}
+
+/// Error body returned by the habit endpoints.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ApiError {
+    pub code: String,
+    #[serde(default)]
+    pub field: Option<String>,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SlugAvailability {
+    pub slug: String,
+    pub available: bool,
+    #[serde(default)]
+    pub error: Option<ApiError>,
+    #[serde(default)]
+    pub suggestion: Option<String>,
+}
+
+/// Groups habits by category, uncategorised last, keeping each group's order.
+pub fn group_by_category(habits: &[Habit]) -> Vec<(String, Vec<Habit>)> {
+    let mut groups: Vec<(String, Vec<Habit>)> = Vec::new();
+    for habit in habits {
+        match groups.iter_mut().find(|(c, _)| *c == habit.category) {
+            Some((_, group)) => group.push(habit.clone()),
+            None => groups.push((habit.category.clone(), vec![habit.clone()])),
+        }
+    }
+    groups.sort_by(|(a, _), (b, _)| (a.is_empty(), a).cmp(&(b.is_empty(), b)));
+    groups
 }
 
 impl Tracker {
@@ -58,14 +367,141 @@ impl Tracker {
                 units_per_day,
                 theme_id,
                 user_name,
+                visualizer_mode: None,
             },
+            events: Vec::new(),
+            shared_achievements: Vec::new(),
+            server_secret: None,
+            groups: Vec::new(),
+            leaderboard: None,
+            encouragement_entry: None,
+        }
+    }
+
+    /// Seed for the visualizer layout. Tracker ids are minted per device, so
+    /// this hashes what a share link carries instead: the habit and start time.
+    pub fn layout_seed(&self) -> u64 {
+        let start = match &self.tracker_type {
+            TrackerType::Abstinence { start_date, .. } => start_date.timestamp(),
+        };
+        shared::hash::fnv1a(self.habit_id.as_bytes().iter().chain(start.to_le_bytes().iter()))
+    }
+
+    /// The server copy of the tracker, as sent when creating or joining a group.
+    pub fn group_proto(&self) -> shared::tracker::GroupTracker {
+        let TrackerType::Abstinence { start_date, units_per_day, user_name, .. } = &self.tracker_type;
+        shared::tracker::GroupTracker {
+            id: self.id.to_string(),
+            habit_id: self.habit_id.to_string(),
+            state: Some(shared::tracker::TrackerState {
+                start_timestamp: start_date.timestamp(),
+                units_per_day: *units_per_day,
+                theme_id: None,
+                user_name: user_name.clone(),
+                visualizer_mode: None,
+                achievements: Vec::new(),
+                leaderboard_entry: None,
+                last_relapse_timestamp: None,
+            }),
+            last_relapse_timestamp: self.last_relapse().map(|at| at.timestamp()),
+        }
+    }
+
+    /// The leaderboard entry a share link carries so viewers can send
+    /// encouragement: ours when published, else the one we were shared.
+    pub fn share_entry(&self) -> Option<Uuid> {
+        self.leaderboard.as_ref().map(|l| l.entry_id).or(self.encouragement_entry)
+    }
+
+    pub fn last_relapse(&self) -> Option<DateTime<Utc>> {
+        self.events
+            .iter()
+            .filter_map(|e| match e {
+                TrackerEvent::Relapse { at } => Some(*at),
+                TrackerEvent::Craving { .. } => None,
+            })
+            .max()
+    }
+
+    /// When the streak running at `at` began: the start date, or the last
+    /// relapse before then. Groups and leaderboards rank by the same.
+    pub fn clean_since_at(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let TrackerType::Abstinence { start_date, .. } = &self.tracker_type;
+        self.events
+            .iter()
+            .filter_map(|e| match e {
+                TrackerEvent::Relapse { at: relapse } if *relapse <= at => Some(*relapse),
+                _ => None,
+            })
+            .fold(*start_date, DateTime::max)
+    }
+
+    /// The start date, or the last relapse when there was one since.
+    pub fn clean_since(&self) -> DateTime<Utc> {
+        let TrackerType::Abstinence { start_date, .. } = &self.tracker_type;
+        self.last_relapse().map_or(*start_date, |at| at.max(*start_date))
+    }
+
+    /// Takes on changes another device made to the server copy. A newer
+    /// relapse is added as an event; cravings stay with the device they were
+    /// logged on. Returns whether anything changed.
+    pub fn apply_shared(&mut self, shared: &SharedTracker) -> bool {
+        let before = self.clone();
+        let TrackerType::Abstinence { start_date, units_per_day, user_name, .. } = &mut self.tracker_type;
+        *start_date = shared.start_date;
+        *units_per_day = shared.units_per_day;
+        *user_name = shared.user_name.clone();
+        if let Some(at) = shared.last_relapse_at {
+            self.merge_relapse(at);
         }
+        *self != before
+    }
+
+    /// Adds a relapse logged elsewhere, such as one a share link carries,
+    /// unless a later one is already known. Returns whether it was added.
+    pub fn merge_relapse(&mut self, at: DateTime<Utc>) -> bool {
+        let newer = self.last_relapse().is_none_or(|last| at > last);
+        if newer {
+            self.events.push(TrackerEvent::Relapse { at });
+        }
+        newer
+    }
+
+    /// What milestone notifications and projections are computed from; the
+    /// current streak, like the card.
+    pub fn schedule(&self) -> shared::milestones::Schedule {
+        match &self.tracker_type {
+            TrackerType::Abstinence { units_per_day, .. } => {
+                shared::milestones::Schedule { start: self.clean_since(), units_per_day: *units_per_day }
+            }
+        }
+    }
+
+    /// What the achievement rules in `shared` are evaluated against.
+    pub fn history(&self) -> shared::achievements::History {
+        let (start, units_per_day) = match &self.tracker_type {
+            TrackerType::Abstinence { start_date, units_per_day, .. } => (*start_date, *units_per_day),
+        };
+        let (mut cravings, mut relapses) = (Vec::new(), Vec::new());
+        for event in &self.events {
+            match event {
+                TrackerEvent::Craving { at, .. } => cravings.push(*at),
+                TrackerEvent::Relapse { at } => relapses.push(*at),
+            }
+        }
+        shared::achievements::History { start, units_per_day, cravings, relapses }
     }
 
     pub fn get_abstinence_count(&self) -> f64 {
+        self.count_at(Utc::now())
+    }
+
+    /// Units avoided in the streak running at `at`, so a relapse starts the
+    /// count again.
+    pub fn count_at(&self, at: DateTime<Utc>) -> f64 {
         match &self.tracker_type {
-            TrackerType::Abstinence { start_date, units_per_day, .. } => {
-                let duration = Utc::now() - *start_date;
+            TrackerType::Abstinence { units_per_day, .. } => {
+                let duration = at - self.clean_since_at(at);
                 let hours = duration.num_minutes() as f64 / 60.0;
                 let habit_freq_hours = if *units_per_day > 0.0 {
                     24.0 / units_per_day
@@ -86,7 +522,6 @@ impl Tracker {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::TimeZone;
 
     #[test]
     fn test_habit_creation() {
@@ -95,6 +530,36 @@ mod tests {
         assert_eq!(habit.slug, "test-slug");
     }
 
+    #[test]
+    fn test_group_by_category() {
+        let mut beer = Habit::new("Beer", "beer", "🍺", "Beers");
+        beer.category = "Drinks".to_string();
+        let nails = Habit::new("Nail biting", "nails", "💅", "Bites");
+        let mut wine = Habit::new("Wine", "wine", "🍷", "Glasses");
+        wine.category = "Drinks".to_string();
+
+        let groups = group_by_category(&[nails, beer, wine]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "Drinks");
+        assert_eq!(groups[0].1.len(), 2);
+        assert_eq!(groups[1].0, "");
+    }
+
+    #[test]
+    fn test_resolve_habit_follows_merges() {
+        let beer = Habit::new("Beer", "beer", "🍺", "Beers");
+        let mut beers = Habit::new("Beers", "beers", "🍺", "Beers");
+        beers.merged_into = Some(beer.id);
+        let mut pints = Habit::new("Pints", "pints", "🍺", "Pints");
+        pints.merged_into = Some(beers.id);
+        let habits = [beer.clone(), beers, pints.clone()];
+
+        assert_eq!(resolve_habit(&habits, pints.id).map(|h| h.id), Some(beer.id));
+        assert_eq!(resolve_habit(&habits, beer.id).map(|h| h.id), Some(beer.id));
+        assert!(resolve_habit(&habits, Uuid::new_v4()).is_none());
+        assert!(!pints.listed());
+    }
+
     #[test]
     fn test_tracker_abstinence_creation() {
         let habit_id = Uuid::new_v4();
@@ -109,6 +574,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_layout_seed_follows_share_link() {
+        let habit_id = Uuid::new_v4();
+        let start = Utc::now();
+        let mine = Tracker::new_abstinence(habit_id, start, 2.0, None, "Me".to_string());
+        let shared = Tracker::new_abstinence(habit_id, start, 3.0, None, "Friend".to_string());
+        assert_ne!(mine.id, shared.id);
+        assert_eq!(mine.layout_seed(), shared.layout_seed());
+        let later = Tracker::new_abstinence(habit_id, start + chrono::Duration::hours(1), 2.0, None, "Me".to_string());
+        assert_ne!(mine.layout_seed(), later.layout_seed());
+    }
+
     #[test]
     fn test_abstinence_calculation() {
         // Mock current time by creating a start date in the past
@@ -123,7 +600,14 @@ mod tests {
                 units_per_day: 1.0, // 1 unit per day = 24h frequency
                 theme_id: None,
                 user_name: "User".to_string(),
+                visualizer_mode: None,
             },
+            events: Vec::new(),
+            shared_achievements: Vec::new(),
+            server_secret: None,
+            groups: Vec::new(),
+            leaderboard: None,
+            encouragement_entry: None,
         };
 
         // 48h duration with 1 unit/day = 2.0 units
@@ -132,4 +616,83 @@ mod tests {
         // Allow small float error due to execution time
         assert!((count - 2.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_relapses_restart_the_count() {
+        use chrono::Duration;
+        let start = Utc::now() - Duration::days(10);
+        let mut tracker = Tracker::new_abstinence(Uuid::new_v4(), start, 2.0, None, "Sam".to_string());
+        let relapse = start + Duration::days(7);
+        tracker.events.push(TrackerEvent::Relapse { at: relapse });
+
+        assert_eq!(tracker.clean_since(), relapse);
+        assert_eq!(tracker.clean_since_at(start + Duration::days(6)), start);
+        assert_eq!(tracker.count_at(start + Duration::days(6)), 12.0);
+        assert_eq!(tracker.count_at(relapse + Duration::days(1)), 2.0);
+        assert_eq!(tracker.schedule().start, relapse);
+    }
+
+    #[test]
+    fn test_group_proto_leaves_local_details_behind() {
+        let mut tracker = Tracker::new_abstinence(Uuid::new_v4(), Utc::now(), 3.0, Some(Uuid::new_v4()), "Sam".to_string());
+        tracker.shared_achievements.push("first-day".to_string());
+        let proto = tracker.group_proto();
+        assert_eq!(proto.id, tracker.id.to_string());
+        let state = proto.state.unwrap();
+        assert_eq!((state.user_name.as_str(), state.units_per_day), ("Sam", 3.0));
+        assert_eq!(state.theme_id, None);
+        assert!(state.achievements.is_empty());
+
+        // Trackers saved before groups existed still load.
+        let mut json = serde_json::to_value(&tracker).unwrap();
+        json.as_object_mut().unwrap().remove("groups");
+        json.as_object_mut().unwrap().remove("server_secret");
+        json.as_object_mut().unwrap().remove("leaderboard");
+        json.as_object_mut().unwrap().remove("encouragement_entry");
+        assert_eq!(serde_json::from_value::<Tracker>(json).unwrap(), tracker);
+    }
+
+    #[test]
+    fn test_share_links_count_from_the_last_relapse() {
+        use chrono::Duration;
+        let start = Utc::now() - Duration::days(10);
+        let mut owner = Tracker::new_abstinence(Uuid::new_v4(), start, 3.0, None, "Sam".to_string());
+        owner.events.push(TrackerEvent::Relapse { at: start + Duration::days(6) });
+
+        // What a viewer builds from the link, which carries whole seconds.
+        let at = DateTime::from_timestamp(owner.last_relapse().unwrap().timestamp(), 0).unwrap();
+        let mut viewer = Tracker::new_abstinence(owner.habit_id, start, 3.0, None, "Sam".to_string());
+        assert!(viewer.merge_relapse(at));
+        let now = Utc::now();
+        assert!((viewer.count_at(now) - owner.count_at(now)).abs() < 0.01);
+
+        // Opening the link again adds nothing.
+        assert!(!viewer.merge_relapse(at));
+        assert!(!owner.merge_relapse(at));
+    }
+
+    #[test]
+    fn test_apply_shared_adds_only_newer_relapses() {
+        use chrono::Duration;
+        let start = Utc::now() - Duration::days(10);
+        let mut tracker = Tracker::new_abstinence(Uuid::new_v4(), start, 3.0, None, "Sam".to_string());
+        let relapse = start + Duration::days(5);
+        tracker.events.push(TrackerEvent::Relapse { at: relapse });
+        let mut shared = SharedTracker {
+            id: tracker.id,
+            user_name: "Sam".to_string(),
+            start_date: start,
+            units_per_day: 3.0,
+            last_relapse_at: Some(relapse),
+        };
+        assert!(!tracker.apply_shared(&shared));
+
+        shared.last_relapse_at = Some(relapse + Duration::days(1));
+        shared.units_per_day = 4.0;
+        assert!(tracker.apply_shared(&shared));
+        assert_eq!(tracker.events.len(), 2);
+        assert_eq!(tracker.last_relapse(), shared.last_relapse_at);
+        assert_eq!(tracker.group_proto().last_relapse_timestamp, shared.last_relapse_at.map(|at| at.timestamp()));
+        assert!(!tracker.apply_shared(&shared));
+    }
 }
@@ -0,0 +1,255 @@
+//! Notifications for milestone moments: "30 days clean", "1,000 beers
+//! avoided". While the app is open a timer checks opted-in trackers every
+//! minute; opted-in trackers are also subscribed to Web Push so milestones
+//! arrive with the app closed. Both use the tracker id as the notification tag,
+//! so when both fire the second replaces the first.
+
+use crate::model::{Habit, NotificationSettings, Tracker};
+use crate::store::{HabitStore, NotificationStore, TrackerStore};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{Local, Utc};
+use gloo_net::http::Request;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use leptos::wasm_bindgen::{JsCast, JsValue};
+use leptos::web_sys;
+use wasm_bindgen_futures::JsFuture;
+
+/// Starts the once-a-minute check. Call once, from the app root.
+pub fn start_scheduler() {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let h_store = use_context::<HabitStore>().expect("HabitStore not found");
+    let n_store = use_context::<NotificationStore>().expect("NotificationStore not found");
+
+    register_service_worker();
+    // Refreshes the server's copy of each schedule, e.g. after a DST change,
+    // once the habits the pushes are titled with have loaded.
+    Effect::new(move |_| {
+        if !h_store.loading.get() {
+            sync_push(t_store.trackers.get_untracked(), h_store, n_store);
+        }
+    });
+    let check = move || {
+        if !permission_granted() {
+            return;
+        }
+        let settings = n_store.settings.get_untracked();
+        let now = Utc::now();
+        let offset = *Local::now().offset();
+        for tracker in t_store.trackers.get_untracked() {
+            if !settings.trackers.contains(&tracker.id) {
+                continue;
+            }
+            let Some(habit) = h_store.get_habit(tracker.habit_id) else { continue };
+            let last = settings.last_notified.get(&tracker.id).copied().unwrap_or(now);
+            if let Some(milestone) = tracker.schedule().due(settings.quiet_hours(), last, now, offset) {
+                let title = milestone.title(&habit.name, &habit.unit_name);
+                show(&title, &format!("/{}", habit.slug), &tracker.id.to_string());
+                n_store.update(|s| {
+                    s.last_notified.insert(tracker.id, milestone.at);
+                });
+            }
+        }
+    };
+    check();
+    let _ = leptos::leptos_dom::helpers::set_interval_with_handle(check, std::time::Duration::from_secs(60));
+}
+
+/// Not every browser (or an insecure origin) has service workers; plain
+/// notifications still work there.
+fn service_worker() -> Option<web_sys::ServiceWorkerContainer> {
+    let navigator = web_sys::window()?.navigator();
+    (js_sys::Reflect::has(&navigator, &JsValue::from_str("serviceWorker")) == Ok(true)).then(|| navigator.service_worker())
+}
+
+fn register_service_worker() {
+    if let Some(container) = service_worker() {
+        let _ = container.register("/sw.js");
+    }
+}
+
+pub fn permission_granted() -> bool {
+    web_sys::Notification::permission() == web_sys::NotificationPermission::Granted
+}
+
+/// Asks for permission and reports whether it was granted.
+pub async fn request_permission() -> bool {
+    let Ok(promise) = web_sys::Notification::request_permission() else { return false };
+    let _ = JsFuture::from(promise).await;
+    permission_granted()
+}
+
+/// Shows a notification through the service worker when one is active, which
+/// also works with the tab in the background, or directly otherwise.
+fn show(title: &str, url: &str, tag: &str) {
+    let options = web_sys::NotificationOptions::new();
+    options.set_tag(tag);
+    let data = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&data, &"url".into(), &url.into());
+    options.set_data(&data);
+
+    let title = title.to_string();
+    spawn_local(async move {
+        let registration = match service_worker() {
+            Some(container) => JsFuture::from(container.get_registration()).await.ok(),
+            None => None,
+        };
+        match registration.and_then(|r| r.dyn_into::<web_sys::ServiceWorkerRegistration>().ok()) {
+            Some(registration) => {
+                let _ = registration.show_notification_with_options(&title, &options);
+            }
+            None => {
+                let _ = web_sys::Notification::new_with_options(&title, &options);
+            }
+        }
+    });
+}
+
+async fn push_manager() -> Option<web_sys::PushManager> {
+    let ready = JsFuture::from(service_worker()?.ready().ok()?).await.ok()?;
+    ready.dyn_into::<web_sys::ServiceWorkerRegistration>().ok()?.push_manager().ok()
+}
+
+/// Subscribes this browser to the server's pushes for `tracker`, or refreshes
+/// the schedule the server holds. Best effort: without push, notifications
+/// still come while the app is open.
+async fn subscribe_push(tracker: Tracker, habit: Habit, settings: NotificationSettings) -> Option<()> {
+    let push_manager = push_manager().await?;
+    let key = Request::get("/api/push/vapid-public-key").send().await.ok()?.text().await.ok()?;
+    let key = URL_SAFE_NO_PAD.decode(key.trim()).ok()?;
+
+    let options = web_sys::PushSubscriptionOptionsInit::new();
+    options.set_user_visible_only(true);
+    options.set_application_server_key(&js_sys::Uint8Array::from(key.as_slice()));
+    let subscription = JsFuture::from(push_manager.subscribe_with_options(&options).ok()?).await.ok()?;
+    let subscription: serde_json::Value = serde_json::from_str(&js_sys::JSON::stringify(&subscription).ok()?.as_string()?).ok()?;
+
+    let schedule = tracker.schedule();
+    let body = serde_json::json!({
+        "subscription": subscription,
+        "tracker_id": tracker.id,
+        "habit_name": habit.name,
+        "habit_slug": habit.slug,
+        "unit_name": habit.unit_name,
+        "start_date": schedule.start,
+        "units_per_day": schedule.units_per_day,
+        "quiet_start": settings.quiet_start,
+        "quiet_end": settings.quiet_end,
+        "utc_offset_minutes": Local::now().offset().local_minus_utc() / 60,
+    });
+    let res = Request::post("/api/push/subscribe")
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .ok()?
+        .send()
+        .await
+        .ok()?;
+    res.ok().then_some(())
+}
+
+/// Stops the server's pushes for one tracker; other trackers keep theirs.
+async fn unsubscribe_push(tracker_id: uuid::Uuid) -> Option<()> {
+    let subscription = JsFuture::from(push_manager().await?.get_subscription().ok()?).await.ok()?;
+    let endpoint = subscription.dyn_into::<web_sys::PushSubscription>().ok()?.endpoint();
+    let body = serde_json::json!({ "endpoint": endpoint, "tracker_id": tracker_id });
+    Request::post("/api/push/unsubscribe")
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .ok()?
+        .send()
+        .await
+        .ok()?;
+    Some(())
+}
+
+/// Re-sends the schedules and quiet hours of those `trackers` that are opted in.
+pub fn sync_push(trackers: Vec<Tracker>, h_store: HabitStore, n_store: NotificationStore) {
+    if !permission_granted() {
+        return;
+    }
+    let settings = n_store.settings.get_untracked();
+    for tracker in trackers {
+        if !settings.trackers.contains(&tracker.id) {
+            continue;
+        }
+        if let Some(habit) = h_store.get_habit(tracker.habit_id) {
+            spawn_local({
+                let settings = settings.clone();
+                async move {
+                    let _ = subscribe_push(tracker, habit, settings).await;
+                }
+            });
+        }
+    }
+}
+
+/// Per-tracker opt-in, the next milestone and the shared quiet hours.
+#[component]
+pub fn NotificationToggle(tracker: Tracker, habit: Habit) -> impl IntoView {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let h_store = use_context::<HabitStore>().expect("HabitStore not found");
+    let n_store = use_context::<NotificationStore>().expect("NotificationStore not found");
+    let tracker_id = tracker.id;
+    let enabled = Memo::new(move |_| n_store.settings.with(|s| s.trackers.contains(&tracker_id)));
+    let denied = RwSignal::new(false);
+
+    let next = tracker.schedule().next_milestone(Utc::now());
+    let next_title = next.title(&habit.name, &habit.unit_name);
+
+    let toggle = move |ev| {
+        let on = event_target_checked(&ev);
+        if !on {
+            n_store.set_enabled(tracker_id, false);
+            spawn_local(async move {
+                let _ = unsubscribe_push(tracker_id).await;
+            });
+            return;
+        }
+        let (tracker, habit) = (tracker.clone(), habit.clone());
+        spawn_local(async move {
+            let granted = request_permission().await;
+            denied.set(!granted);
+            n_store.set_enabled(tracker_id, granted);
+            if granted {
+                let _ = subscribe_push(tracker, habit, n_store.settings.get_untracked()).await;
+            }
+        });
+    };
+    let hour_select = move |get: fn(&NotificationSettings) -> u8, set: fn(&mut NotificationSettings, u8)| {
+        view! {
+            <select
+                prop:value=move || n_store.settings.with(|s| get(s).to_string())
+                on:change=move |ev| {
+                    let hour = event_target_value(&ev).parse().unwrap_or(0);
+                    n_store.update(|s| set(s, hour));
+                    sync_push(t_store.trackers.get_untracked(), h_store, n_store);
+                }
+            >
+                {(0..24u8).map(|h| view! { <option value=h.to_string()>{format!("{:02}:00", h)}</option> }).collect_view()}
+            </select>
+        }
+    };
+
+    view! {
+        <div class="notification-settings">
+            <label>
+                <input type="checkbox" prop:checked=enabled on:change=toggle />
+                " Notify me of milestones"
+            </label>
+            {move || denied.get().then(|| view! {
+                <p class="error">"Notifications are blocked for this site; allow them in your browser settings."</p>
+            })}
+            <p class="notification-next">
+                "Next: " {next_title} " on " {next.at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()}
+            </p>
+            <Show when=move || enabled.get()>
+                <div class="notification-quiet">
+                    "Quiet hours (all trackers): "
+                    {hour_select(|s| s.quiet_start, |s, h| s.quiet_start = h)}
+                    " to "
+                    {hour_select(|s| s.quiet_end, |s, h| s.quiet_end = h)}
+                </div>
+            </Show>
+        </div>
+    }
+}
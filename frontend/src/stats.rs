@@ -0,0 +1,198 @@
+//! Statistics per tracker and across all of them: history, milestones and
+//! projections, drawn with the SVG charts in [`crate::charts`].
+
+use crate::charts::{BarChart, LineChart, Series};
+use crate::model::{Habit, Tracker, TrackerType};
+use crate::store::{HabitStore, TrackerStore};
+use chrono::{DateTime, Duration, Utc};
+use leptos::prelude::*;
+use shared::milestones::{format_thousands, next_unit_milestone};
+
+/// Samples per history line; trackers grow linearly so few are needed.
+const SERIES_POINTS: usize = 32;
+
+/// Whole and partial days since the last relapse, or since the tracker
+/// started, never negative.
+pub fn days_clean(tracker: &Tracker, now: DateTime<Utc>) -> f64 {
+    ((now - tracker.clean_since_at(now)).num_seconds() as f64 / 86_400.0).max(0.0)
+}
+
+/// The running count from the start date until `now`, evenly sampled; it
+/// drops back to zero at each relapse.
+pub fn history(tracker: &Tracker, now: DateTime<Utc>, points: usize) -> Vec<(DateTime<Utc>, f64)> {
+    let start = match &tracker.tracker_type {
+        TrackerType::Abstinence { start_date, .. } => *start_date,
+    };
+    if now <= start {
+        return Vec::new();
+    }
+    let span = (now - start).num_seconds();
+    let steps = points.max(2) - 1;
+    (0..=steps)
+        .map(|i| {
+            let at = start + Duration::seconds(span * i as i64 / steps as i64);
+            (at, tracker.count_at(at).max(0.0))
+        })
+        .collect()
+}
+
+/// When the tracker passes `target` units at its current rate.
+pub fn projected_date(tracker: &Tracker, target: f64, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    tracker.schedule().projected_date(target, now)
+}
+
+/// Charts and projections for one tracker, shown under its card.
+#[component]
+pub fn TrackerStats(tracker: Tracker, habit: Habit) -> impl IntoView {
+    let now = Utc::now();
+    let count = tracker.count_at(now).max(0.0);
+    let days = days_clean(&tracker, now);
+    let milestone = next_unit_milestone(count);
+    let unit = habit.unit_name.to_lowercase();
+
+    let mut series = vec![Series { label: format!("{} avoided", habit.unit_name), points: history(&tracker, now, SERIES_POINTS), dashed: false }];
+    let projection = projected_date(&tracker, milestone, now);
+    if let Some(date) = projection {
+        series.push(Series { label: "Projection".to_string(), points: vec![(now, count), (date, milestone)], dashed: true });
+    }
+
+    view! {
+        <div class="stats-panel">
+            <h3>"Statistics"</h3>
+            <div class="stats-summary">
+                <div class="stat"><span class="stat-value">{format!("{:.1}", days)}</span>" days clean"</div>
+                <div class="stat"><span class="stat-value">{format_thousands(count as u64)}</span>" " {unit.clone()} " avoided"</div>
+            </div>
+            {projection.map(|date| view! {
+                <p class="stats-projection">
+                    "At this rate you'll pass " {format_thousands(milestone as u64)} " " {unit.clone()}
+                    " on " {date.format("%Y-%m-%d").to_string()} "."
+                </p>
+            })}
+            <LineChart series=series />
+        </div>
+    }
+}
+
+/// Every tracker side by side: streaks, history and upcoming milestones.
+#[component]
+pub fn StatsView() -> impl IntoView {
+    let t_store = use_context::<TrackerStore>().expect("TrackerStore not found");
+    let h_store = use_context::<HabitStore>().expect("HabitStore not found");
+
+    // Trackers whose habit has loaded, paired with it.
+    let rows = Memo::new(move |_| {
+        let habits = h_store.habits.get();
+        t_store
+            .trackers
+            .get()
+            .into_iter()
+            .filter_map(|t| habits.iter().find(|h| h.id == t.habit_id).cloned().map(|h| (t, h)))
+            .collect::<Vec<_>>()
+    });
+
+    let label = |t: &Tracker, h: &Habit| match &t.tracker_type {
+        TrackerType::Abstinence { user_name, .. } => format!("{} · {}", h.name, user_name),
+    };
+
+    let streaks = Signal::derive(move || {
+        let now = Utc::now();
+        let mut bars: Vec<_> = rows.get().iter().map(|(t, h)| (label(t, h), days_clean(t, now))).collect();
+        bars.sort_by(|a, b| b.1.total_cmp(&a.1));
+        bars
+    });
+    let histories = Signal::derive(move || {
+        let now = Utc::now();
+        rows.get()
+            .iter()
+            .map(|(t, h)| Series { label: format!("{} ({})", label(t, h), h.unit_name), points: history(t, now, SERIES_POINTS), dashed: false })
+            .collect::<Vec<_>>()
+    });
+
+    view! {
+        <div class="stats-view">
+            <h2>"Statistics"</h2>
+            <Show
+                when=move || !rows.get().is_empty()
+                fallback=|| view! { <p class="empty-state">"No trackers yet."</p> }
+            >
+                <h3>"Days clean"</h3>
+                <BarChart bars=streaks unit="days" />
+
+                <h3>"Units avoided over time"</h3>
+                <LineChart series=histories />
+
+                <h3>"Next milestones"</h3>
+                <table class="stats-table">
+                    <thead>
+                        <tr><th>"Tracker"</th><th>"Avoided"</th><th>"Next milestone"</th><th>"Reached on"</th></tr>
+                    </thead>
+                    <tbody>
+                        {move || {
+                            let now = Utc::now();
+                            rows.get().into_iter().map(|(t, h)| {
+                                let count = t.count_at(now).max(0.0);
+                                let milestone = next_unit_milestone(count);
+                                let date = projected_date(&t, milestone, now)
+                                    .map(|d| d.format("%Y-%m-%d").to_string())
+                                    .unwrap_or_else(|| "—".to_string());
+                                view! {
+                                    <tr>
+                                        <td>{label(&t, &h)}</td>
+                                        <td>{format!("{} {}", format_thousands(count as u64), h.unit_name)}</td>
+                                        <td>{format_thousands(milestone as u64)}</td>
+                                        <td>{date}</td>
+                                    </tr>
+                                }
+                            }).collect_view()
+                        }}
+                    </tbody>
+                </table>
+            </Show>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn tracker(days_ago: i64, units_per_day: f64, now: DateTime<Utc>) -> Tracker {
+        Tracker::new_abstinence(Uuid::new_v4(), now - Duration::days(days_ago), units_per_day, None, "User".to_string())
+    }
+
+    #[test]
+    fn projections() {
+        let now = Utc::now();
+        let t = tracker(10, 2.0, now);
+        assert_eq!(days_clean(&t, now).round(), 10.0);
+        // 20 avoided at 2 a day: 25 is two and a half days away.
+        let date = projected_date(&t, 25.0, now).unwrap();
+        assert_eq!((date - now).num_hours(), 60);
+        assert_eq!(projected_date(&tracker(10, 0.0, now), 25.0, now), None);
+    }
+
+    #[test]
+    fn history_runs_from_zero_to_now() {
+        let now = Utc::now();
+        let points = history(&tracker(4, 1.0, now), now, 5);
+        assert_eq!(points.len(), 5);
+        assert_eq!(points[0].1, 0.0);
+        assert!((points[4].1 - 4.0).abs() < 0.01);
+        assert!(history(&tracker(-1, 1.0, now), now, 5).is_empty());
+    }
+
+    #[test]
+    fn relapses_reset_days_clean_and_history() {
+        use crate::model::TrackerEvent;
+        let now = Utc::now();
+        let mut t = tracker(4, 1.0, now);
+        t.events.push(TrackerEvent::Relapse { at: now - Duration::days(1) });
+        assert_eq!(days_clean(&t, now).round(), 1.0);
+        let points = history(&t, now, 5);
+        assert!((points[2].1 - 2.0).abs() < 0.01);
+        assert!(points[3].1 < 0.01);
+        assert!((points[4].1 - 1.0).abs() < 0.01);
+    }
+}
@@ -1,50 +1,225 @@
 use gloo_storage::{LocalStorage, Storage};
-use gloo_net::http::Request;
-use crate::model::{Habit, Tracker, Theme};
+use gloo_net::http::{Method, Request};
+use crate::model::{ApiError, CheckIn, Encouragements, Group, GroupLink, Habit, LeaderboardEntry, LeaderboardListing, Membership, NotificationSettings, Publication, SharedTracker, SlugAvailability, Tracker, TrackerEvent, TrackerType, Theme, User};
 use leptos::prelude::*;
 use leptos::task::spawn_local;
-use shared::tracker::Habit as ProtoHabit;
+use shared::tracker::{CreateGroup, Encouragement, Habit as ProtoHabit, PublishTracker, Theme as ProtoTheme};
 use prost::Message;
+use std::collections::HashMap;
 
 const TRACKERS_KEY: &str = "addict_trackers";
+const CHECK_INS_KEY: &str = "addict_check_ins";
+const CELEBRATED_KEY: &str = "addict_celebrated_achievements";
+const NOTIFICATIONS_KEY: &str = "addict_notifications";
+pub const GALLERY_PAGE_SIZE: i64 = 12;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GalleryPage {
+    pub themes: Vec<Theme>,
+    pub total: i64,
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct ThemeStore {
     pub themes: RwSignal<Vec<Theme>>,
+    pub save_error: RwSignal<Option<String>>,
 }
 
 impl ThemeStore {
+    /// Starts empty; `events::connect` loads themes and keeps them current.
     pub fn new() -> Self {
-        let store = Self {
+        Self {
             themes: RwSignal::new(Vec::new()),
+            save_error: RwSignal::new(None),
+        }
+    }
+
+    pub fn refresh(&self) {
+        let store = *self;
+        spawn_local(async move {
+            if let Ok(res) = Request::get("/api/themes").send().await
+                && let Ok(themes) = res.json::<Vec<Theme>>().await
+            {
+                store.themes.set(themes);
+            }
+        });
+    }
+
+    /// Adds or replaces a theme, e.g. one streamed from `/api/events`.
+    pub fn upsert(&self, theme: Theme) {
+        self.themes.update(|all| match all.iter_mut().find(|t| t.id == theme.id) {
+            Some(existing) => *existing = theme,
+            None => all.push(theme),
+        });
+    }
+
+    /// Drops a deleted theme, e.g. one another client deleted.
+    pub fn remove(&self, id: uuid::Uuid) {
+        self.themes.update(|all| all.retain(|t| t.id != id));
+    }
+
+    /// Saves `theme` (tokens plus `css_override`) and adds the server's compiled copy to the store.
+    pub fn add_theme(&self, theme: Theme, on_saved: impl FnOnce() + 'static) {
+        self.send_theme(Request::post("/api/themes"), theme, on_saved);
+    }
+
+    /// Saves changes to an existing theme its owner (or an admin) edited.
+    pub fn update_theme(&self, theme: Theme, on_saved: impl FnOnce() + 'static) {
+        self.send_theme(Request::put(&format!("/api/themes/{}", theme.id)), theme, on_saved);
+    }
+
+    pub async fn delete_theme(&self, id: uuid::Uuid) -> Result<(), String> {
+        let res = Request::delete(&format!("/api/themes/{}", id)).send().await.map_err(|e| e.to_string())?;
+        if !res.ok() {
+            return Err(read_error(res).await);
+        }
+        self.remove(id);
+        Ok(())
+    }
+
+    fn send_theme(&self, request: gloo_net::http::RequestBuilder, theme: Theme, on_saved: impl FnOnce() + 'static) {
+        let proto = ProtoTheme {
+            id: theme.id.to_string(),
+            name: theme.name.clone(),
+            css: theme.css_override.clone(),
+            icon_limit: theme.icon_limit,
+            tokens: theme.tokens.clone(),
         };
-        
+
+        let mut buf = Vec::new();
+        proto.encode(&mut buf).unwrap();
+
+        let store = *self;
+        store.save_error.set(None);
         spawn_local(async move {
-            if let Ok(res) = Request::get("/api/themes").send().await {
-                if let Ok(themes) = res.json::<Vec<Theme>>().await {
-                    store.themes.set(themes);
-                }
+            let result = request
+                .header("Content-Type", "application/octet-stream")
+                .body(buf)
+                .unwrap()
+                .send()
+                .await;
+
+            match result {
+                Ok(res) if res.ok() => match res.json::<Theme>().await {
+                    Ok(saved) => {
+                        // The event stream may have brought it already.
+                        store.upsert(saved);
+                        on_saved();
+                    }
+                    Err(e) => leptos::logging::error!("Failed to parse saved theme: {:?}", e),
+                },
+                Ok(res) => store.save_error.set(Some(read_error(res).await)),
+                Err(e) => store.save_error.set(Some(e.to_string())),
+            }
+        });
+    }
+
+    /// Fetches one gallery page. Loaded themes are merged into `themes` so cards can find them.
+    pub async fn fetch_page(&self, search: String, sort: &str, page: i64) -> Option<GalleryPage> {
+        let page_str = page.to_string();
+        let per_page = GALLERY_PAGE_SIZE.to_string();
+        let res = Request::get("/api/themes")
+            .query([("q", search.as_str()), ("sort", sort), ("page", &page_str), ("per_page", &per_page)])
+            .send()
+            .await
+            .ok()?;
+        let total = res
+            .headers()
+            .get("x-total-count")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let themes = res.json::<Vec<Theme>>().await.ok()?;
+
+        for theme in &themes {
+            self.upsert(theme.clone());
+        }
+        Some(GalleryPage { themes, total })
+    }
+
+    /// Up-votes a theme. Returns false when the server refused, e.g. too many votes this hour.
+    pub async fn vote(&self, id: uuid::Uuid) -> bool {
+        Request::post(&format!("/api/themes/{}/vote", id))
+            .send()
+            .await
+            .is_ok_and(|res| res.ok())
+    }
+}
+
+/// The account behind the session cookie. Creating habits and themes needs one.
+#[derive(Clone, Copy, Debug)]
+pub struct AuthStore {
+    /// `None` while logged out, and until `/api/auth/me` has answered.
+    pub user: RwSignal<Option<User>>,
+}
+
+impl AuthStore {
+    pub fn new() -> Self {
+        let store = Self { user: RwSignal::new(None) };
+        spawn_local(async move {
+            if let Ok(res) = Request::get("/api/auth/me").send().await
+                && res.ok()
+                && let Ok(user) = res.json::<User>().await
+            {
+                store.user.set(Some(user));
             }
         });
-        
         store
     }
+
+    /// Has a login link mailed to `email`.
+    pub async fn request_login(&self, email: String) -> Result<(), String> {
+        let body = serde_json::json!({ "email": email }).to_string();
+        let res = with_proof(Method::POST, "/api/auth/login", body.as_bytes())
+            .await?
+            .header("Content-Type", "application/json")
+            .body(body)
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !res.ok() {
+            return Err(read_error(res).await);
+        }
+        Ok(())
+    }
+
+    /// Trades the token from a login link for a session.
+    pub async fn redeem(&self, token: String) -> Result<(), String> {
+        let res = Request::post("/api/auth/session")
+            .json(&serde_json::json!({ "token": token }))
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        self.user.set(Some(read_json::<User>(res).await?));
+        Ok(())
+    }
+
+    pub async fn logout(&self) {
+        let _ = Request::post("/api/auth/logout").send().await;
+        self.user.set(None);
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct HabitStore {
     pub habits: RwSignal<Vec<Habit>>,
     pub loading: RwSignal<bool>,
+    pub save_error: RwSignal<Option<ApiError>>,
 }
 
 impl HabitStore {
+    /// Starts empty and loading; `events::connect` loads habits and keeps them current.
     pub fn new() -> Self {
-        let store = Self {
+        Self {
             habits: RwSignal::new(Vec::new()),
             loading: RwSignal::new(true),
-        };
-        
-        // Initial fetch from server
+            save_error: RwSignal::new(None),
+        }
+    }
+
+    pub fn refresh(&self) {
+        let store = *self;
         spawn_local(async move {
             match Request::get("/api/habits").send().await {
                 Ok(response) => {
@@ -60,50 +235,195 @@ impl HabitStore {
             }
             store.loading.set(false);
         });
-        
-        store
     }
 
-    pub fn add_habit(&self, habit: Habit) {
-        let h = habit.clone();
-        
-        let proto = ProtoHabit {
-            id: h.id.to_string(),
-            slug: h.slug.clone(),
-            name: h.name.clone(),
-            icon: h.icon.clone(),
-            unit_name: h.unit_name.clone(),
-        };
-        
-        let mut buf = Vec::new();
-        proto.encode(&mut buf).unwrap();
+    /// Adds or replaces a habit, e.g. one streamed from `/api/events`.
+    pub fn upsert(&self, habit: Habit) {
+        self.habits.update(|all| match all.iter_mut().find(|h| h.id == habit.id) {
+            Some(existing) => *existing = habit,
+            None => all.push(habit),
+        });
+    }
+
+    /// Drops a deleted habit, e.g. one another client deleted.
+    pub fn remove(&self, id: uuid::Uuid) {
+        self.habits.update(|all| all.retain(|h| h.id != id));
+    }
+
+    /// Saves `habit` and adds the server's copy, whose slug may have been generated, to the store.
+    pub fn add_habit(&self, habit: Habit, on_saved: impl FnOnce() + 'static) {
+        let buf = encode_habit(&habit);
 
+        let store = *self;
+        store.save_error.set(None);
         spawn_local(async move {
-            let _ = Request::post("/api/habits")
+            let result = Request::post("/api/habits")
                 .header("Content-Type", "application/octet-stream")
                 .body(buf)
                 .unwrap()
                 .send()
                 .await;
+
+            let error = match result {
+                Ok(res) if res.ok() => match res.json::<Habit>().await {
+                    Ok(saved) => {
+                        // The event stream may have brought it already.
+                        store.upsert(saved);
+                        on_saved();
+                        return;
+                    }
+                    Err(e) => e.to_string(),
+                },
+                Ok(res) => match res.json::<ApiError>().await {
+                    Ok(error) => {
+                        store.save_error.set(Some(error));
+                        return;
+                    }
+                    Err(e) => e.to_string(),
+                },
+                Err(e) => e.to_string(),
+            };
+            store.save_error.set(Some(ApiError { code: "network".to_string(), field: None, message: error }));
+        });
+    }
+
+    /// Uploads a PNG or SVG icon for a saved habit and marks it as having one.
+    pub async fn upload_icon(&self, id: uuid::Uuid, content_type: String, bytes: Vec<u8>) -> Result<(), String> {
+        let res = Request::put(&format!("/api/habits/{}/icon", id))
+            .header("Content-Type", &content_type)
+            .body(bytes)
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !res.ok() {
+            return Err(read_error(res).await);
+        }
+        self.habits.update(|habits| {
+            if let Some(h) = habits.iter_mut().find(|h| h.id == id) {
+                h.has_icon_image = true;
+            }
         });
-        self.habits.update(|h| h.push(habit));
+        Ok(())
+    }
+
+    pub async fn check_slug(&self, slug: String) -> Option<SlugAvailability> {
+        Request::get("/api/habits/slug-available")
+            .query([("slug", slug.as_str())])
+            .send()
+            .await
+            .ok()?
+            .json::<SlugAvailability>()
+            .await
+            .ok()
+    }
+
+    /// Full-text searches habits on the server, optionally limited to one tag.
+    pub async fn search(&self, q: String, tag: String) -> Option<Vec<Habit>> {
+        Request::get("/api/habits")
+            .query([("q", q.as_str()), ("tag", tag.as_str())])
+            .send()
+            .await
+            .ok()?
+            .json::<Vec<Habit>>()
+            .await
+            .ok()
     }
 
+    /// Saves edits to a habit its owner (or an admin) changed. The slug stays.
+    pub async fn update_habit(&self, habit: Habit) -> Result<(), String> {
+        let res = Request::put(&format!("/api/habits/{}", habit.id))
+            .header("Content-Type", "application/octet-stream")
+            .body(encode_habit(&habit))
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        self.upsert(read_json::<Habit>(res).await?);
+        Ok(())
+    }
+
+    pub async fn delete_habit(&self, id: uuid::Uuid) -> Result<(), String> {
+        let res = Request::delete(&format!("/api/habits/{}", id)).send().await.map_err(|e| e.to_string())?;
+        if !res.ok() {
+            return Err(read_error(res).await);
+        }
+        self.remove(id);
+        Ok(())
+    }
+
+    /// Hides a habit from the library, or brings it back. Admins only.
+    pub async fn set_hidden(&self, id: uuid::Uuid, hidden: bool) -> Result<(), String> {
+        let res = Request::put(&format!("/api/habits/{}/hidden", id))
+            .json(&serde_json::json!({ "hidden": hidden }))
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !res.ok() {
+            return Err(read_error(res).await);
+        }
+        self.update_local(id, |h| h.hidden = hidden);
+        Ok(())
+    }
+
+    /// Merges a duplicate into another habit. Admins only.
+    pub async fn merge(&self, id: uuid::Uuid, into: uuid::Uuid) -> Result<(), String> {
+        let res = Request::post(&format!("/api/habits/{}/merge", id))
+            .json(&serde_json::json!({ "into": into }))
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !res.ok() {
+            return Err(read_error(res).await);
+        }
+        self.update_local(id, |h| h.merged_into = Some(into));
+        Ok(())
+    }
+
+    fn update_local(&self, id: uuid::Uuid, change: impl FnOnce(&mut Habit)) {
+        self.habits.update(|all| {
+            if let Some(h) = all.iter_mut().find(|h| h.id == id) {
+                change(h);
+            }
+        });
+    }
+
+    /// The habit `id` stands for, which may be the one it was merged into.
     pub fn get_habit(&self, id: uuid::Uuid) -> Option<Habit> {
-        self.habits.get().iter().find(|h| h.id == id).cloned()
+        self.habits.with(|all| crate::model::resolve_habit(all, id).cloned())
     }
 }
 
+fn encode_habit(habit: &Habit) -> Vec<u8> {
+    let proto = ProtoHabit {
+        id: habit.id.to_string(),
+        slug: habit.slug.clone(),
+        name: habit.name.clone(),
+        icon: habit.icon.clone(),
+        unit_name: habit.unit_name.clone(),
+        category: habit.category.clone(),
+        tags: habit.tags.clone(),
+    };
+    proto.encode_to_vec()
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct TrackerStore {
     pub trackers: RwSignal<Vec<Tracker>>,
+    /// What a relapse needs to move the tracker's push schedule.
+    habits: HabitStore,
+    notifications: NotificationStore,
 }
 
 impl TrackerStore {
-    pub fn new() -> Self {
+    pub fn new(habits: HabitStore, notifications: NotificationStore) -> Self {
         let stored_trackers: Vec<Tracker> = LocalStorage::get(TRACKERS_KEY).unwrap_or_default();
         Self {
             trackers: RwSignal::new(stored_trackers),
+            habits,
+            notifications,
         }
     }
 
@@ -112,21 +432,406 @@ impl TrackerStore {
     }
 
     pub fn add_tracker(&self, tracker: Tracker) {
+        report_theme_usage(&tracker);
         self.trackers.update(|t| t.push(tracker));
         self.save();
     }
 
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
     pub fn update_tracker(&self, tracker: Tracker) {
+        report_theme_usage(&tracker);
+        let id = tracker.id;
         self.trackers.update(|t| {
             if let Some(index) = t.iter().position(|x| x.id == tracker.id) {
                 t[index] = tracker;
             }
         });
         self.save();
+        self.sync_shared(id);
+    }
+
+    pub fn log_event(&self, id: uuid::Uuid, event: TrackerEvent) {
+        let relapse = matches!(event, TrackerEvent::Relapse { .. });
+        self.trackers.update(|t| {
+            if let Some(tracker) = t.iter_mut().find(|x| x.id == id) {
+                tracker.events.push(event);
+            }
+        });
+        self.save();
+        if relapse {
+            self.sync_shared(id);
+            // Milestones now count from the relapse.
+            crate::notifications::sync_push(self.get(id).into_iter().collect(), self.habits, self.notifications);
+        }
+    }
+
+    /// Sends a changed tracker's server copy, if it has one, so its groups see the change.
+    fn sync_shared(&self, id: uuid::Uuid) {
+        let Some(tracker) = self.get(id).filter(|t| t.server_secret.is_some()) else { return };
+        spawn_local(async move {
+            let result = with_secret(Request::put(&format!("/api/trackers/{}", tracker.id)), &tracker)
+                .header("Content-Type", "application/octet-stream")
+                .body(tracker.group_proto().encode_to_vec());
+            if let Ok(request) = result
+                && let Err(e) = request.send().await
+            {
+                leptos::logging::error!("Failed to sync tracker: {:?}", e);
+            }
+        });
+    }
+
+    /// Takes on a change another device made to one of our trackers' server copies.
+    pub fn apply_shared(&self, shared: &SharedTracker) {
+        let mut changed = false;
+        self.trackers.update(|t| {
+            if let Some(tracker) = t.iter_mut().find(|x| x.id == shared.id) {
+                changed = tracker.apply_shared(shared);
+            }
+        });
+        if changed {
+            self.save();
+        }
     }
 
     pub fn delete_tracker(&self, id: uuid::Uuid) {
+        if let Some(tracker) = self.trackers.with_untracked(|t| t.iter().find(|x| x.id == id).cloned()) {
+            // Best effort: the server copy would otherwise stay in its groups.
+            for link in tracker.groups.clone() {
+                let tracker = tracker.clone();
+                spawn_local(async move {
+                    let _ = send_leave(&link.invite_code, &tracker).await;
+                });
+            }
+            if tracker.leaderboard.is_some() {
+                spawn_local(async move {
+                    let _ = send_unpublish(&tracker).await;
+                });
+            }
+        }
         self.trackers.update(|t| t.retain(|x| x.id != id));
         self.save();
     }
+
+    /// Starts a group around the tracker's habit with the tracker as first member.
+    pub async fn create_group(&self, tracker_id: uuid::Uuid, name: String) -> Result<Group, String> {
+        let tracker = self.get(tracker_id).ok_or("Tracker not found")?;
+        let proto = CreateGroup { name, tracker: Some(tracker.group_proto()) };
+        let body = proto.encode_to_vec();
+        let res = with_secret(with_proof(Method::POST, "/api/groups", &body).await?, &tracker)
+            .header("Content-Type", "application/octet-stream")
+            .body(body)
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        self.record_membership(tracker_id, read_json::<Membership>(res).await?)
+    }
+
+    pub async fn join_group(&self, tracker_id: uuid::Uuid, invite_code: &str) -> Result<Group, String> {
+        let tracker = self.get(tracker_id).ok_or("Tracker not found")?;
+        let body = tracker.group_proto().encode_to_vec();
+        let res = with_secret(with_proof(Method::POST, &format!("/api/groups/{}/members", invite_code), &body).await?, &tracker)
+            .header("Content-Type", "application/octet-stream")
+            .body(body)
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        self.record_membership(tracker_id, read_json::<Membership>(res).await?)
+    }
+
+    pub async fn leave_group(&self, tracker_id: uuid::Uuid, invite_code: &str) -> Result<(), String> {
+        let tracker = self.get(tracker_id).ok_or("Tracker not found")?;
+        send_leave(invite_code, &tracker).await?;
+        self.trackers.update(|t| {
+            if let Some(tracker) = t.iter_mut().find(|x| x.id == tracker_id) {
+                tracker.groups.retain(|g| g.invite_code != invite_code);
+            }
+        });
+        self.save();
+        Ok(())
+    }
+
+    /// Lists the tracker on its habit's leaderboard as `nickname`, or renames its entry.
+    pub async fn publish(&self, tracker_id: uuid::Uuid, nickname: String) -> Result<(), String> {
+        let tracker = self.get(tracker_id).ok_or("Tracker not found")?;
+        let proto = PublishTracker { tracker: Some(tracker.group_proto()), nickname: nickname.trim().to_string() };
+        let body = proto.encode_to_vec();
+        let res = with_secret(with_proof(Method::PUT, &format!("/api/trackers/{}/leaderboard", tracker_id), &body).await?, &tracker)
+            .header("Content-Type", "application/octet-stream")
+            .body(body)
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let publication = read_json::<Publication>(res).await?;
+        self.trackers.update(|t| {
+            if let Some(tracker) = t.iter_mut().find(|x| x.id == tracker_id) {
+                if publication.tracker_secret.is_some() {
+                    tracker.server_secret = publication.tracker_secret;
+                }
+                tracker.leaderboard = Some(LeaderboardListing { entry_id: publication.entry_id, nickname: proto.nickname });
+            }
+        });
+        self.save();
+        Ok(())
+    }
+
+    pub async fn unpublish(&self, tracker_id: uuid::Uuid) -> Result<(), String> {
+        let tracker = self.get(tracker_id).ok_or("Tracker not found")?;
+        send_unpublish(&tracker).await?;
+        self.trackers.update(|t| {
+            if let Some(tracker) = t.iter_mut().find(|x| x.id == tracker_id) {
+                tracker.leaderboard = None;
+            }
+        });
+        self.save();
+        Ok(())
+    }
+
+    /// Everything left on our published tracker, hidden messages included.
+    pub async fn own_encouragements(&self, tracker_id: uuid::Uuid) -> Result<Encouragements, String> {
+        let tracker = self.get(tracker_id).ok_or("Tracker not found")?;
+        let url = format!("/api/trackers/{}/encouragements", tracker_id);
+        let res = with_secret(Request::get(&url), &tracker).send().await.map_err(|e| e.to_string())?;
+        read_json(res).await
+    }
+
+    pub async fn set_encouragement_hidden(&self, tracker_id: uuid::Uuid, id: uuid::Uuid, hidden: bool) -> Result<(), String> {
+        let tracker = self.get(tracker_id).ok_or("Tracker not found")?;
+        let url = format!("/api/trackers/{}/encouragements/{}/hidden", tracker_id, id);
+        let res = with_secret(Request::put(&url), &tracker)
+            .json(&serde_json::json!({ "hidden": hidden }))
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if res.ok() {
+            Ok(())
+        } else {
+            Err(read_error(res).await)
+        }
+    }
+
+    pub async fn delete_encouragement(&self, tracker_id: uuid::Uuid, id: uuid::Uuid) -> Result<(), String> {
+        let tracker = self.get(tracker_id).ok_or("Tracker not found")?;
+        let url = format!("/api/trackers/{}/encouragements/{}", tracker_id, id);
+        let res = with_secret(Request::delete(&url), &tracker).send().await.map_err(|e| e.to_string())?;
+        if res.ok() {
+            Ok(())
+        } else {
+            Err(read_error(res).await)
+        }
+    }
+
+    fn get(&self, id: uuid::Uuid) -> Option<Tracker> {
+        self.trackers.with_untracked(|t| t.iter().find(|x| x.id == id).cloned())
+    }
+
+    fn record_membership(&self, tracker_id: uuid::Uuid, membership: Membership) -> Result<Group, String> {
+        let group = membership.group;
+        self.trackers.update(|t| {
+            if let Some(tracker) = t.iter_mut().find(|x| x.id == tracker_id) {
+                if membership.tracker_secret.is_some() {
+                    tracker.server_secret = membership.tracker_secret;
+                }
+                tracker.groups.retain(|g| g.invite_code != group.invite_code);
+                tracker.groups.push(GroupLink { invite_code: group.invite_code.clone(), name: group.name.clone() });
+            }
+        });
+        self.save();
+        Ok(group)
+    }
+}
+
+fn with_secret(request: gloo_net::http::RequestBuilder, tracker: &Tracker) -> gloo_net::http::RequestBuilder {
+    match &tracker.server_secret {
+        Some(secret) => request.header("X-Tracker-Secret", secret),
+        None => request,
+    }
+}
+
+/// Starts a `method` request to `path` with a fresh challenge solved for
+/// `body`, for writes anyone may make without logging in.
+async fn with_proof(method: Method, path: &str, body: &[u8]) -> Result<gloo_net::http::RequestBuilder, String> {
+    let request = gloo_net::http::RequestBuilder::new(path).method(method.clone());
+    let res = Request::get("/api/pow").send().await.map_err(|e| e.to_string())?;
+    let challenge = read_json::<shared::pow::Challenge>(res).await?;
+    if challenge.difficulty == 0 {
+        return Ok(request);
+    }
+    let nonce = shared::pow::solve(&challenge.challenge, challenge.difficulty, method.as_str(), path, body);
+    Ok(request.header(shared::pow::HEADER, &shared::pow::header_value(&challenge.challenge, nonce)))
+}
+
+async fn send_leave(invite_code: &str, tracker: &Tracker) -> Result<(), String> {
+    let url = format!("/api/groups/{}/members/{}", invite_code, tracker.id);
+    let res = with_secret(Request::delete(&url), tracker).send().await.map_err(|e| e.to_string())?;
+    // Already gone is as good as left.
+    if res.ok() || res.status() == 404 {
+        Ok(())
+    } else {
+        Err(read_error(res).await)
+    }
+}
+
+async fn send_unpublish(tracker: &Tracker) -> Result<(), String> {
+    let url = format!("/api/trackers/{}/leaderboard", tracker.id);
+    let res = with_secret(Request::delete(&url), tracker).send().await.map_err(|e| e.to_string())?;
+    if res.ok() || res.status() == 404 {
+        Ok(())
+    } else {
+        Err(read_error(res).await)
+    }
+}
+
+async fn read_error(res: gloo_net::http::Response) -> String {
+    let text = res.text().await.unwrap_or_default();
+    match serde_json::from_str::<ApiError>(&text) {
+        Ok(error) => error.message,
+        // Theme and icon uploads answer with plain text.
+        Err(_) if !text.trim().is_empty() => text,
+        Err(_) => format!("Request failed ({})", res.status()),
+    }
+}
+
+async fn read_json<T: serde::de::DeserializeOwned>(res: gloo_net::http::Response) -> Result<T, String> {
+    if !res.ok() {
+        return Err(read_error(res).await);
+    }
+    res.json::<T>().await.map_err(|e| e.to_string())
+}
+
+pub async fn fetch_group(invite_code: &str) -> Result<Group, String> {
+    let res = Request::get(&format!("/api/groups/{}", invite_code)).send().await.map_err(|e| e.to_string())?;
+    read_json(res).await
+}
+
+/// A habit's leaderboard, `sort`ed by `"streak"` or `"units"`.
+pub async fn fetch_leaderboard(slug: &str, sort: &str) -> Result<Vec<LeaderboardEntry>, String> {
+    let res = Request::get(&format!("/api/habits/{}/leaderboard", slug))
+        .query([("sort", sort)])
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    read_json(res).await
+}
+
+/// What visitors see under a published tracker.
+pub async fn fetch_encouragements(entry_id: uuid::Uuid) -> Result<Encouragements, String> {
+    let res = Request::get(&format!("/api/leaderboard/{}/encouragements", entry_id))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    read_json(res).await
+}
+
+/// Leaves a message or reaction; answers with what visitors now see.
+pub async fn send_encouragement(entry_id: uuid::Uuid, encouragement: Encouragement) -> Result<Encouragements, String> {
+    let body = encouragement.encode_to_vec();
+    let res = with_proof(Method::POST, &format!("/api/leaderboard/{}/encouragements", entry_id), &body)
+        .await?
+        .header("Content-Type", "application/octet-stream")
+        .body(body)
+        .map_err(|e| e.to_string())?
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    read_json(res).await
+}
+
+/// Reports a leaderboard entry; enough reports take it off the board.
+pub async fn flag_entry(entry_id: uuid::Uuid) -> Result<(), String> {
+    let res = Request::post(&format!("/api/leaderboard/{}/flags", entry_id)).send().await.map_err(|e| e.to_string())?;
+    if res.ok() {
+        Ok(())
+    } else {
+        Err(read_error(res).await)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CheckInStore {
+    pub check_ins: RwSignal<Vec<CheckIn>>,
+}
+
+impl CheckInStore {
+    pub fn new() -> Self {
+        let stored: Vec<CheckIn> = LocalStorage::get(CHECK_INS_KEY).unwrap_or_default();
+        Self {
+            check_ins: RwSignal::new(stored),
+        }
+    }
+
+    /// Saves `check_in`, replacing any earlier answer for the same day.
+    pub fn record(&self, check_in: CheckIn) {
+        self.check_ins.update(|all| {
+            all.retain(|c| c.date != check_in.date);
+            all.push(check_in);
+            all.sort_by_key(|c| c.date);
+        });
+        let _ = LocalStorage::set(CHECK_INS_KEY, self.check_ins.get_untracked());
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct NotificationStore {
+    pub settings: RwSignal<NotificationSettings>,
+}
+
+impl NotificationStore {
+    pub fn new() -> Self {
+        let stored: NotificationSettings = LocalStorage::get(NOTIFICATIONS_KEY).unwrap_or_default();
+        Self {
+            settings: RwSignal::new(stored),
+        }
+    }
+
+    pub fn update(&self, f: impl FnOnce(&mut NotificationSettings)) {
+        self.settings.update(f);
+        let _ = LocalStorage::set(NOTIFICATIONS_KEY, self.settings.get_untracked());
+    }
+
+    /// Opting in starts from now, so milestones already passed stay quiet.
+    pub fn set_enabled(&self, tracker_id: uuid::Uuid, enabled: bool) {
+        self.update(|s| {
+            s.trackers.retain(|id| *id != tracker_id);
+            s.last_notified.remove(&tracker_id);
+            if enabled {
+                s.trackers.push(tracker_id);
+                s.last_notified.insert(tracker_id, chrono::Utc::now());
+            }
+        });
+    }
+}
+
+/// Achievement ids per tracker whose unlock animation has already played.
+/// Kept apart from the trackers so marking one doesn't re-render the card.
+pub fn celebrated_achievements(tracker_id: uuid::Uuid) -> Vec<String> {
+    let all: HashMap<uuid::Uuid, Vec<String>> = LocalStorage::get(CELEBRATED_KEY).unwrap_or_default();
+    all.get(&tracker_id).cloned().unwrap_or_default()
+}
+
+pub fn mark_celebrated(tracker_id: uuid::Uuid, ids: Vec<String>) {
+    let mut all: HashMap<uuid::Uuid, Vec<String>> = LocalStorage::get(CELEBRATED_KEY).unwrap_or_default();
+    all.insert(tracker_id, ids);
+    let _ = LocalStorage::set(CELEBRATED_KEY, all);
+}
+
+/// Tells the server which theme a tracker uses so the gallery can rank themes by usage.
+/// The server counts each tracker once per theme, so repeats are harmless.
+fn report_theme_usage(tracker: &Tracker) {
+    let TrackerType::Abstinence { theme_id: Some(theme_id), .. } = &tracker.tracker_type else {
+        return;
+    };
+    let url = format!("/api/themes/{}/usage", theme_id);
+    let body = tracker.id.to_string();
+    spawn_local(async move {
+        let _ = Request::post(&url)
+            .header("Content-Type", "text/plain")
+            .body(body)
+            .unwrap()
+            .send()
+            .await;
+    });
 }
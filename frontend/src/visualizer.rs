@@ -0,0 +1,414 @@
+//! Canvas renderer for the tracker visualizer.
+//!
+//! Every icon is a [`Particle`] drawn from a single `requestAnimationFrame`
+//! loop, so tens of thousands of icons cost one DOM node instead of one each.
+//! The motion mirrors the `roam` keyframes themes use for the DOM renderer;
+//! the other [`VisualizerMode`]s draw the layouts from [`crate::layouts`].
+
+use leptos::prelude::*;
+use leptos::wasm_bindgen::{closure::Closure, JsCast};
+use leptos::web_sys;
+use crate::layouts::{self, Decoration, Layout, Pile, Placement};
+use crate::rng::Rng;
+use shared::tracker::{ThemeTokens, VisualizerAnimation, VisualizerMode};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Upper bound for the canvas renderer when the theme sets no `icon_limit`.
+pub const MAX_CANVAS_ICONS: usize = 50_000;
+/// Height of the canvas in CSS pixels.
+const CANVAS_HEIGHT: f64 = 300.0;
+/// Root font size the token `icon_size_rem` is relative to.
+const REM_PX: f64 = 16.0;
+
+/// The theme tokens the canvas renderer uses in place of theme CSS.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CanvasStyle {
+    pub animation: VisualizerAnimation,
+    pub glow_color: String,
+    /// The theme's mode for trackers that have not picked one.
+    pub mode: VisualizerMode,
+}
+
+impl CanvasStyle {
+    pub fn from_tokens(tokens: Option<&ThemeTokens>) -> Self {
+        let defaults = ThemeTokens::winamp();
+        let tokens = tokens.unwrap_or(&defaults);
+        let accent = tokens.palette.as_ref().map(|p| p.accent.clone()).unwrap_or_default();
+        Self {
+            animation: tokens.visualizer.unwrap_or_else(VisualizerAnimation::roaming),
+            glow_color: if shared::theme_tokens::is_color(&accent) { accent } else { "#00ff00".to_string() },
+            mode: VisualizerMode::try_from(tokens.default_mode).unwrap_or(VisualizerMode::Roam),
+        }
+    }
+}
+
+/// One icon: its resting place (fractions of the canvas) plus the roaming
+/// offsets and timing the DOM renderer passes as `--tx`, `--ty` and `--tr`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub x: f64,
+    pub y: f64,
+    pub tx: f64,
+    pub ty: f64,
+    pub tr: f64,
+    pub duration: f64,
+    pub delay: f64,
+}
+
+impl Particle {
+    pub fn random(rng: &mut Rng) -> Self {
+        Self {
+            x: rng.next_f64(),
+            y: rng.next_f64(),
+            tx: rng.next_f64() * 0.4 - 0.2,
+            ty: rng.next_f64() * 0.4 - 0.2,
+            tr: rng.next_f64() * 360.0,
+            duration: 1.0 + rng.next_f64(),
+            delay: rng.next_f64() * 2.0,
+        }
+    }
+
+    /// Offset (fractions of the canvas) and rotation in degrees `t` seconds in.
+    /// Like the CSS animation it alternates direction and eases each keyframe.
+    pub fn offset_at(&self, t: f64, anim: &VisualizerAnimation) -> (f64, f64, f64) {
+        let period = (anim.duration_seconds.max(0.5)) * self.duration;
+        let progress = (t / period + self.delay).max(0.0);
+        let cycle = progress.floor();
+        let mut frac = progress - cycle;
+        if cycle as u64 % 2 == 1 {
+            frac = 1.0 - frac;
+        }
+
+        let scale = anim.roam_scale;
+        let mid = (self.tx * scale, self.ty * scale, self.tr);
+        let end = (self.tx * scale * -0.5, self.ty * scale * 0.5, 0.0);
+        let (from, to, s) = if frac < 0.5 {
+            ((0.0, 0.0, 0.0), mid, frac * 2.0)
+        } else {
+            (mid, end, (frac - 0.5) * 2.0)
+        };
+        let e = s * s * (3.0 - 2.0 * s);
+        (
+            from.0 + (to.0 - from.0) * e,
+            from.1 + (to.1 - from.1) * e,
+            from.2 + (to.2 - from.2) * e,
+        )
+    }
+}
+
+/// Modes offered in the pickers, with their labels.
+pub const MODES: [(VisualizerMode, &str); 5] = [
+    (VisualizerMode::Roam, "Roam"),
+    (VisualizerMode::Jar, "Jar"),
+    (VisualizerMode::Stack, "Stack"),
+    (VisualizerMode::Grid, "Grid"),
+    (VisualizerMode::Physics, "Physics"),
+];
+
+/// Grows or shrinks `particles` to `target`. Icon `i` always comes from the
+/// same `seed` stream, so existing icons stay put.
+pub fn resize_particles(particles: &mut Vec<Particle>, target: usize, seed: u64) {
+    if particles.len() > target {
+        particles.truncate(target);
+    }
+    while particles.len() < target {
+        particles.push(Particle::random(&mut Rng::for_index(seed, particles.len())));
+    }
+}
+
+/// Draws `count` icons on a canvas in `mode`. Calls `on_unsupported` when no 2D
+/// context is available so the caller can fall back to DOM rendering.
+#[component]
+pub fn CanvasVisualizer(
+    #[prop(into)] count: Signal<f64>,
+    icon: String,
+    image: Option<String>,
+    limit: Option<usize>,
+    #[prop(into)] style: Signal<CanvasStyle>,
+    #[prop(into)] mode: Signal<VisualizerMode>,
+    /// Cells per row in grid mode.
+    grid_columns: usize,
+    /// Layout seed, see [`crate::model::Tracker::layout_seed`].
+    seed: u64,
+    #[prop(into)] on_unsupported: Callback<()>,
+) -> impl IntoView {
+    let canvas_ref = NodeRef::<leptos::html::Canvas>::new();
+    let particles = Rc::new(RefCell::new(Vec::<Particle>::new()));
+    let cap = limit.unwrap_or(MAX_CANVAS_ICONS).min(MAX_CANVAS_ICONS);
+
+    {
+        let particles = particles.clone();
+        Effect::new(move |_| {
+            let target = (count.get().floor().max(0.0) as usize).min(cap);
+            resize_particles(&mut particles.borrow_mut(), target, seed);
+        });
+    }
+
+    Effect::new(move |started: Option<bool>| {
+        if started == Some(true) {
+            return true;
+        }
+        let Some(canvas) = canvas_ref.get() else { return false };
+        let Some(ctx) = context_2d(&canvas) else {
+            on_unsupported.run(());
+            return true;
+        };
+        let scene = Scene { particles: particles.clone(), mode, grid_columns, layout: None, pile: None, last_frame: None };
+        start_loop(canvas, ctx, scene, icon.clone(), image.clone(), style);
+        true
+    });
+
+    view! { <canvas class="viz-canvas" node_ref=canvas_ref></canvas> }
+}
+
+fn context_2d(canvas: &web_sys::HtmlCanvasElement) -> Option<web_sys::CanvasRenderingContext2d> {
+    canvas.get_context("2d").ok().flatten()?.dyn_into().ok()
+}
+
+/// Pre-renders the icon once so each frame is only `drawImage` calls.
+fn make_sprite(icon: &str, image: Option<&str>, size: f64, glow: Option<&str>) -> Option<web_sys::HtmlCanvasElement> {
+    let document = web_sys::window()?.document()?;
+    let sprite: web_sys::HtmlCanvasElement = document.create_element("canvas").ok()?.dyn_into().ok()?;
+    // Leave room around the glyph for the glow.
+    let side = (size * 1.5).ceil();
+    sprite.set_width(side as u32);
+    sprite.set_height(side as u32);
+    let ctx = context_2d(&sprite)?;
+    if let Some(color) = glow {
+        ctx.set_shadow_color(color);
+        ctx.set_shadow_blur(size * 0.25);
+    }
+
+    match image {
+        Some(src) => {
+            let img = web_sys::HtmlImageElement::new().ok()?;
+            let loaded_img = img.clone();
+            let onload = Closure::once_into_js(move || {
+                let offset = (side - size) / 2.0;
+                let _ = ctx.draw_image_with_html_image_element_and_dw_and_dh(&loaded_img, offset, offset, size, size);
+            });
+            img.set_onload(Some(onload.unchecked_ref()));
+            img.set_src(src);
+        }
+        None => {
+            ctx.set_font(&format!("{}px sans-serif", size));
+            ctx.set_text_align("center");
+            ctx.set_text_baseline("middle");
+            let _ = ctx.fill_text(icon, side / 2.0, side / 2.0);
+        }
+    }
+    Some(sprite)
+}
+
+/// Mode, icon count, and canvas width, height and icon size as bits.
+type LayoutKey = (VisualizerMode, usize, u64, u64, u64);
+
+/// What the render loop draws from. The roam particles double as the
+/// per-icon randomness for the pile, so an icon keeps its column across frames.
+struct Scene {
+    particles: Rc<RefCell<Vec<Particle>>>,
+    mode: Signal<VisualizerMode>,
+    grid_columns: usize,
+    /// The last static layout and the key it was built for.
+    layout: Option<(LayoutKey, Layout)>,
+    pile: Option<Pile>,
+    last_frame: Option<f64>,
+}
+
+impl Scene {
+    /// Placements for this frame, or `None` in roam mode.
+    fn placements(&mut self, now_ms: f64, width: f64, height: f64, size: f64, out: &mut Vec<Placement>) -> Option<&[Decoration]> {
+        let dt = self.last_frame.map_or(0.0, |last| ((now_ms - last) / 1000.0).clamp(0.0, 0.1));
+        self.last_frame = Some(now_ms);
+        let particles = self.particles.borrow();
+        let n = particles.len();
+        let mode = self.mode.get_untracked();
+
+        if mode != VisualizerMode::Physics {
+            self.pile = None;
+        }
+        match mode {
+            VisualizerMode::Roam => None,
+            VisualizerMode::Physics => {
+                let pile = match self.pile.take() {
+                    Some(pile) if pile.fits(width, height, n) && pile.len() <= n => pile,
+                    // Resized or shrunk: rebuild with every icon already at rest
+                    // and room to grow before the next rebuild.
+                    _ => Pile::new(width, height, size, (n + n / 2).max(16)),
+                };
+                let pile = self.pile.insert(pile);
+                let settled = pile.len() == 0;
+                for p in &particles[pile.len()..] {
+                    pile.add(p.x, !settled);
+                }
+                pile.step(dt);
+                pile.placements(out);
+                Some(&[])
+            }
+            _ => {
+                let key = (mode, n, width.to_bits(), height.to_bits(), size.to_bits());
+                if self.layout.as_ref().is_none_or(|(k, _)| *k != key) {
+                    let layout = match mode {
+                        VisualizerMode::Jar => layouts::jar(n, width, height, size),
+                        VisualizerMode::Stack => layouts::stack(n, width, height, size),
+                        _ => layouts::grid(n, self.grid_columns, width, height, size),
+                    };
+                    self.layout = Some((key, layout));
+                }
+                let (_, layout) = self.layout.as_ref()?;
+                out.extend_from_slice(&layout.placements);
+                Some(&layout.decorations)
+            }
+        }
+    }
+}
+
+fn draw_decorations(ctx: &web_sys::CanvasRenderingContext2d, decorations: &[Decoration], color: &str, width: f64, dpr: f64) {
+    ctx.set_stroke_style_str(color);
+    ctx.set_fill_style_str(color);
+    ctx.set_line_width(2.0 * dpr);
+    for decoration in decorations {
+        match decoration {
+            Decoration::Jar { left, top, width, height } => {
+                // Open-topped jar: two walls and a rounded base.
+                let radius = width * 0.1;
+                let (right, bottom) = (left + width, top + height);
+                ctx.begin_path();
+                ctx.move_to(*left, *top);
+                ctx.line_to(*left, bottom - radius);
+                let _ = ctx.arc_to(*left, bottom, left + radius, bottom, radius);
+                ctx.line_to(right - radius, bottom);
+                let _ = ctx.arc_to(right, bottom, right, bottom - radius, radius);
+                ctx.line_to(right, *top);
+                ctx.stroke();
+            }
+            Decoration::Marker { y, label } => {
+                ctx.set_global_alpha(0.4);
+                ctx.begin_path();
+                ctx.move_to(0.0, *y);
+                ctx.line_to(width, *y);
+                ctx.stroke();
+                ctx.set_global_alpha(1.0);
+                ctx.set_font(&format!("{}px monospace", 12.0 * dpr));
+                ctx.set_text_align("left");
+                ctx.set_text_baseline("bottom");
+                let _ = ctx.fill_text(label, 4.0 * dpr, y - 2.0 * dpr);
+            }
+        }
+    }
+}
+
+type FrameCallback = Closure<dyn FnMut(f64)>;
+
+fn start_loop(
+    canvas: web_sys::HtmlCanvasElement,
+    ctx: web_sys::CanvasRenderingContext2d,
+    mut scene: Scene,
+    icon: String,
+    image: Option<String>,
+    style: Signal<CanvasStyle>,
+) {
+    let Some(window) = web_sys::window() else { return };
+    let dpr = window.device_pixel_ratio().max(1.0);
+    let mut sprite_for: Option<(u64, Option<String>)> = None;
+    let mut sprite = None;
+    let mut placements = Vec::new();
+
+    let frame: Rc<RefCell<Option<FrameCallback>>> = Rc::new(RefCell::new(None));
+    let next = frame.clone();
+    *frame.borrow_mut() = Some(Closure::new(move |now_ms: f64| {
+        // The card was removed; dropping the closure ends the loop.
+        if !canvas.is_connected() {
+            next.borrow_mut().take();
+            return;
+        }
+
+        let CanvasStyle { animation: anim, glow_color, .. } = style.get_untracked();
+        let size = anim.icon_size_rem * REM_PX * dpr;
+        let glow = anim.glow.then(|| glow_color.clone());
+        let key = Some((size.to_bits(), glow.clone()));
+        if sprite_for != key {
+            sprite = make_sprite(&icon, image.as_deref(), size, glow.as_deref());
+            sprite_for = key;
+        }
+
+        let width = (canvas.client_width() as f64 * dpr).round();
+        let height = (CANVAS_HEIGHT * dpr).round();
+        if canvas.width() != width as u32 || canvas.height() != height as u32 {
+            canvas.set_width(width as u32);
+            canvas.set_height(height as u32);
+        }
+
+        let _ = ctx.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+        ctx.clear_rect(0.0, 0.0, width, height);
+
+        placements.clear();
+        let decorations = scene.placements(now_ms, width, height, size, &mut placements);
+        if let Some(decorations) = decorations {
+            draw_decorations(&ctx, decorations, &glow_color, width, dpr);
+        }
+
+        if let Some(sprite) = &sprite {
+            let side = sprite.width() as f64;
+            if decorations.is_some() {
+                for p in &placements {
+                    // The sprite is padded around an icon of `size`; scale both together.
+                    let drawn = side * p.size / size;
+                    let (sin, cos) = p.rotation.to_radians().sin_cos();
+                    let _ = ctx.set_transform(cos, sin, -sin, cos, p.x, p.y);
+                    let _ = ctx.draw_image_with_html_canvas_element_and_dw_and_dh(sprite, -drawn / 2.0, -drawn / 2.0, drawn, drawn);
+                }
+            } else {
+                let t = now_ms / 1000.0;
+                for p in scene.particles.borrow().iter() {
+                    let (dx, dy, rot) = p.offset_at(t, &anim);
+                    let (sin, cos) = rot.to_radians().sin_cos();
+                    let _ = ctx.set_transform(cos, sin, -sin, cos, (p.x + dx) * width, (p.y + dy) * height);
+                    let _ = ctx.draw_image_with_html_canvas_element(sprite, -side / 2.0, -side / 2.0);
+                }
+            }
+        }
+
+        if let Some(cb) = next.borrow().as_ref() {
+            let _ = web_sys::window()
+                .unwrap()
+                .request_animation_frame(cb.as_ref().unchecked_ref());
+        }
+    }));
+
+    if let Some(cb) = frame.borrow().as_ref() {
+        let _ = window.request_animation_frame(cb.as_ref().unchecked_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resizing_keeps_existing_particles() {
+        let mut particles = Vec::new();
+        resize_particles(&mut particles, 3, 7);
+        let first = particles.clone();
+        resize_particles(&mut particles, 5, 7);
+        assert_eq!(&particles[..3], &first[..]);
+        resize_particles(&mut particles, 2, 7);
+        assert_eq!(particles, first[..2]);
+        // Shrinking and growing back lands every icon where it was.
+        resize_particles(&mut particles, 5, 7);
+        let mut fresh = Vec::new();
+        resize_particles(&mut fresh, 5, 7);
+        assert_eq!(particles, fresh);
+        assert_ne!(fresh[0], fresh[1]);
+    }
+
+    #[test]
+    fn roaming_starts_at_rest_and_peaks_halfway() {
+        let p = Particle { x: 0.5, y: 0.5, tx: 0.2, ty: -0.1, tr: 90.0, duration: 1.0, delay: 0.0 };
+        let anim = VisualizerAnimation::roaming();
+        assert_eq!(p.offset_at(0.0, &anim), (0.0, 0.0, 0.0));
+        let (dx, dy, rot) = p.offset_at(anim.duration_seconds / 2.0, &anim);
+        assert!((dx - 0.2).abs() < 1e-9 && (dy + 0.1).abs() < 1e-9 && (rot - 90.0).abs() < 1e-9);
+    }
+}
@@ -1,3 +1,11 @@
 fn main() {
-    prost_build::compile_protos(&["proto/tracker.proto"], &["proto/"]).unwrap();
+    prost_build::Config::new()
+        // Tokens are stored as JSONB and sent to the frontend as JSON.
+        .type_attribute(".tracker.ThemeTokens", "#[derive(serde::Serialize, serde::Deserialize)] #[serde(default)]")
+        .type_attribute(".tracker.Palette", "#[derive(serde::Serialize, serde::Deserialize)] #[serde(default)]")
+        .type_attribute(".tracker.VisualizerAnimation", "#[derive(serde::Serialize, serde::Deserialize)] #[serde(default)]")
+        // Trackers keep their chosen mode in local storage as JSON.
+        .type_attribute(".tracker.VisualizerMode", "#[derive(serde::Serialize, serde::Deserialize)]")
+        .compile_protos(&["proto/tracker.proto"], &["proto/"])
+        .unwrap();
 }
@@ -0,0 +1,169 @@
+//! Rules-based achievements, evaluated against a tracker's history.
+//!
+//! The catalogue is static so ids stay stable: they are stored by clients and
+//! travel in share links.
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveTime, Utc};
+
+/// What an achievement is judged on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct History {
+    pub start: DateTime<Utc>,
+    pub units_per_day: f64,
+    pub cravings: Vec<DateTime<Utc>>,
+    pub relapses: Vec<DateTime<Utc>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule {
+    /// The current streak has lasted this many hours.
+    CleanHours(i64),
+    /// Units avoided since the start date.
+    UnitsAvoided(f64),
+    /// A full Saturday and Sunday, in the user's time zone, without a relapse.
+    CleanWeekend,
+    /// Cravings logged since the last relapse, i.e. resisted.
+    CravingsResisted(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Achievement {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub icon: &'static str,
+    pub rule: Rule,
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement { id: "first-day", title: "First 24h", description: "A full day without it.", icon: "🌅", rule: Rule::CleanHours(24) },
+    Achievement { id: "first-week", title: "One Week", description: "Seven days clean.", icon: "📅", rule: Rule::CleanHours(7 * 24) },
+    Achievement { id: "first-month", title: "One Month", description: "Thirty days clean.", icon: "🗓️", rule: Rule::CleanHours(30 * 24) },
+    Achievement { id: "first-year", title: "One Year", description: "A whole year clean.", icon: "🏆", rule: Rule::CleanHours(365 * 24) },
+    Achievement { id: "units-100", title: "Century", description: "100 units avoided.", icon: "💯", rule: Rule::UnitsAvoided(100.0) },
+    Achievement { id: "units-1000", title: "Thousand", description: "1,000 units avoided.", icon: "🏔️", rule: Rule::UnitsAvoided(1_000.0) },
+    Achievement { id: "units-10000", title: "Ten Thousand", description: "10,000 units avoided.", icon: "🚀", rule: Rule::UnitsAvoided(10_000.0) },
+    Achievement { id: "weekend", title: "Weekend Survivor", description: "Made it through a whole weekend.", icon: "🎉", rule: Rule::CleanWeekend },
+    Achievement { id: "cravings-10", title: "Iron Will", description: "Logged 10 cravings without relapsing.", icon: "🛡️", rule: Rule::CravingsResisted(10) },
+];
+
+pub fn find(id: &str) -> Option<&'static Achievement> {
+    ACHIEVEMENTS.iter().find(|a| a.id == id)
+}
+
+impl History {
+    /// When the current streak began: the start date or the latest relapse before `now`.
+    pub fn streak_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.relapses.iter().filter(|r| **r <= now).fold(self.start, |latest, r| latest.max(*r))
+    }
+
+    fn units_avoided(&self, now: DateTime<Utc>) -> f64 {
+        ((now - self.start).num_minutes() as f64 / (24.0 * 60.0) * self.units_per_day).max(0.0)
+    }
+
+    /// Whether a Saturday-to-Monday span in `offset` fits between the start
+    /// date and `now` with no relapse in it.
+    fn clean_weekend(&self, now: DateTime<Utc>, offset: FixedOffset) -> bool {
+        let start = self.start.with_timezone(&offset);
+        let days_to_saturday = (6 - start.weekday().num_days_from_sunday() as i64).rem_euclid(7);
+        let mut saturday = start.date_naive() + Duration::days(days_to_saturday);
+        // A start after midnight on a Saturday misses that weekend.
+        if saturday == start.date_naive() && start.time() > NaiveTime::MIN {
+            saturday += Duration::days(7);
+        }
+        loop {
+            let Some(from) = saturday.and_time(NaiveTime::MIN).and_local_timezone(offset).single() else {
+                return false;
+            };
+            let to = from + Duration::days(2);
+            if to > now {
+                return false;
+            }
+            if !self.relapses.iter().any(|r| *r >= from && *r < to) {
+                return true;
+            }
+            saturday += Duration::days(7);
+        }
+    }
+
+    fn is_unlocked(&self, rule: Rule, now: DateTime<Utc>, offset: FixedOffset) -> bool {
+        match rule {
+            Rule::CleanHours(hours) => now - self.streak_start(now) >= Duration::hours(hours),
+            Rule::UnitsAvoided(units) => self.units_avoided(now) >= units,
+            Rule::CleanWeekend => self.clean_weekend(now, offset),
+            Rule::CravingsResisted(n) => {
+                let since = self.streak_start(now);
+                self.cravings.iter().filter(|c| **c >= since && **c <= now).count() >= n
+            }
+        }
+    }
+}
+
+/// Achievements unlocked as of `now`, in catalogue order. Weekends are
+/// judged in the user's UTC `offset`.
+pub fn unlocked(history: &History, now: DateTime<Utc>, offset: FixedOffset) -> Vec<&'static Achievement> {
+    ACHIEVEMENTS.iter().filter(|a| history.is_unlocked(a.rule, now, offset)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        // 2026-10-02 is a Friday.
+        Utc.with_ymd_and_hms(2026, 10, day, hour, 0, 0).unwrap()
+    }
+
+    fn ids(history: &History, now: DateTime<Utc>) -> Vec<&'static str> {
+        unlocked(history, now, FixedOffset::east_opt(0).unwrap()).iter().map(|a| a.id).collect()
+    }
+
+    #[test]
+    fn streaks_restart_after_a_relapse() {
+        let mut history = History { start: at(1, 12), units_per_day: 2.0, cravings: Vec::new(), relapses: Vec::new() };
+        assert!(ids(&history, at(2, 11)).is_empty());
+        assert_eq!(ids(&history, at(2, 12)), vec!["first-day"]);
+
+        history.relapses.push(at(2, 18));
+        assert!(!ids(&history, at(3, 12)).contains(&"first-day"));
+        assert!(ids(&history, at(3, 18)).contains(&"first-day"));
+    }
+
+    #[test]
+    fn units_count_from_the_start_despite_relapses() {
+        let history = History { start: at(1, 0), units_per_day: 10.0, cravings: Vec::new(), relapses: vec![at(5, 0)] };
+        assert!(ids(&history, at(11, 0)).contains(&"units-100"));
+        assert!(!ids(&history, at(10, 23)).contains(&"units-100"));
+    }
+
+    #[test]
+    fn a_weekend_needs_saturday_and_sunday_clean() {
+        // Started Friday noon; the weekend ends at Monday 00:00 (the 5th).
+        let mut history = History { start: at(2, 12), units_per_day: 1.0, cravings: Vec::new(), relapses: Vec::new() };
+        assert!(!ids(&history, at(4, 23)).contains(&"weekend"));
+        assert!(ids(&history, at(5, 0)).contains(&"weekend"));
+
+        // A Sunday relapse spoils that weekend, but the next one counts.
+        history.relapses.push(at(4, 20));
+        assert!(!ids(&history, at(11, 0)).contains(&"weekend"));
+        assert!(ids(&history, at(12, 0)).contains(&"weekend"));
+    }
+
+    #[test]
+    fn resisted_cravings_reset_with_a_relapse() {
+        let cravings = (0..10).map(|h| at(3, h)).collect();
+        let mut history = History { start: at(1, 0), units_per_day: 1.0, cravings, relapses: Vec::new() };
+        assert!(ids(&history, at(4, 0)).contains(&"cravings-10"));
+        history.relapses.push(at(3, 5));
+        assert!(!ids(&history, at(4, 0)).contains(&"cravings-10"));
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        for (i, a) in ACHIEVEMENTS.iter().enumerate() {
+            assert!(ACHIEVEMENTS[i + 1..].iter().all(|b| b.id != a.id));
+            assert_eq!(find(a.id), Some(a));
+        }
+    }
+}
@@ -0,0 +1,668 @@
+//! A small CSS parser for theme stylesheets.
+//!
+//! It understands just enough of CSS to validate user themes, report syntax
+//! errors with line numbers, and rewrite selectors so a stylesheet only
+//! applies inside a given container.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CssError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CssError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Declaration {
+    pub property: String,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AtBody {
+    /// Statement at-rules such as `@import "x.css";`.
+    None,
+    /// Grouping at-rules such as `@media` and `@keyframes`.
+    Rules(Vec<Rule>),
+    /// Descriptor blocks such as `@font-face`.
+    Declarations(Vec<Declaration>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rule {
+    Style {
+        selector: String,
+        declarations: Vec<Declaration>,
+    },
+    At {
+        name: String,
+        prelude: String,
+        body: AtBody,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stylesheet {
+    pub rules: Vec<Rule>,
+}
+
+impl Stylesheet {
+    pub fn parse(css: &str) -> Result<Self, CssError> {
+        let mut parser = Parser { src: css, pos: 0 };
+        let rules = parser.parse_rules(false)?;
+        Ok(Self { rules })
+    }
+
+    /// Prefixes every selector with `scope`, mapping `html`, `body` and `:root` onto the scope itself.
+    pub fn scope(&mut self, scope: &str) {
+        map_selectors(&mut self.rules, &|s| scope_selector(s, scope));
+    }
+
+    /// Reverses [`Stylesheet::scope`] so a saved theme can be edited as a plain stylesheet.
+    pub fn unscope(&mut self, scope: &str) {
+        map_selectors(&mut self.rules, &|s| unscope_selector(s, scope));
+    }
+
+    /// Appends `-{suffix}` to every `@keyframes` name and to the `animation`
+    /// values that refer to them. Keyframe names are global, so without this a
+    /// scoped stylesheet could still redefine animations the rest of the page uses.
+    pub fn suffix_keyframes(&mut self, suffix: &str) {
+        let tail = format!("-{}", suffix);
+        let mut defined = Vec::new();
+        keyframes_names(&self.rules, &mut defined);
+        rename_keyframes(&mut self.rules, &|name| {
+            (defined.iter().any(|d| d == name) && !name.ends_with(&tail)).then(|| format!("{}{}", name, tail))
+        });
+    }
+
+    /// Reverses [`Stylesheet::suffix_keyframes`].
+    pub fn unsuffix_keyframes(&mut self, suffix: &str) {
+        let tail = format!("-{}", suffix);
+        let mut defined = Vec::new();
+        keyframes_names(&self.rules, &mut defined);
+        rename_keyframes(&mut self.rules, &|name| {
+            defined.iter().any(|d| d == name).then(|| name.strip_suffix(&tail)).flatten().map(str::to_string)
+        });
+    }
+
+    pub fn to_css(&self) -> String {
+        let mut out = String::new();
+        write_rules(&mut out, &self.rules, 0);
+        out
+    }
+}
+
+/// Parses `css` and returns it with every selector confined to `scope`.
+pub fn scope_css(css: &str, scope: &str) -> Result<String, CssError> {
+    let mut sheet = Stylesheet::parse(css)?;
+    sheet.scope(scope);
+    Ok(sheet.to_css())
+}
+
+/// Parses `css` and strips `scope` from its selectors.
+pub fn unscope_css(css: &str, scope: &str) -> Result<String, CssError> {
+    let mut sheet = Stylesheet::parse(css)?;
+    sheet.unscope(scope);
+    Ok(sheet.to_css())
+}
+
+/// The selector saved tracker themes are confined to; the card sets a matching `data-theme` attribute.
+pub fn theme_scope(theme_id: &str) -> String {
+    format!("[data-theme=\"{}\"]", theme_id)
+}
+
+/// Turns a saved theme's stylesheet back into what its author wrote: selectors
+/// unscoped and keyframe names without the theme id.
+pub fn unscope_theme_css(css: &str, theme_id: &str) -> Result<String, CssError> {
+    let mut sheet = Stylesheet::parse(css)?;
+    sheet.unscope(&theme_scope(theme_id));
+    sheet.unsuffix_keyframes(theme_id);
+    Ok(sheet.to_css())
+}
+
+fn is_keyframes(name: &str) -> bool {
+    name.eq_ignore_ascii_case("keyframes") || name.to_ascii_lowercase().ends_with("-keyframes")
+}
+
+fn holds_rules(name: &str) -> bool {
+    is_keyframes(name)
+        || ["media", "supports", "container", "layer", "document", "scope"]
+            .iter()
+            .any(|n| name.eq_ignore_ascii_case(n))
+}
+
+fn map_selectors(rules: &mut [Rule], f: &dyn Fn(&str) -> String) {
+    for rule in rules {
+        match rule {
+            Rule::Style { selector, .. } => {
+                *selector = split_top_level(selector, ',')
+                    .into_iter()
+                    .map(|s| f(s.trim()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+            }
+            Rule::At { name, body: AtBody::Rules(inner), .. } if !is_keyframes(name) => {
+                map_selectors(inner, f)
+            }
+            Rule::At { .. } => {}
+        }
+    }
+}
+
+/// Collects the names `@keyframes` rules define, quotes stripped.
+fn keyframes_names(rules: &[Rule], names: &mut Vec<String>) {
+    for rule in rules {
+        match rule {
+            Rule::At { name, prelude, .. } if is_keyframes(name) => names.push(unquote(prelude.trim()).to_string()),
+            Rule::At { body: AtBody::Rules(inner), .. } => keyframes_names(inner, names),
+            _ => {}
+        }
+    }
+}
+
+/// Renames keyframes and their uses in `animation` and `animation-name` wherever
+/// `f` returns a new name.
+fn rename_keyframes(rules: &mut [Rule], f: &dyn Fn(&str) -> Option<String>) {
+    for rule in rules {
+        match rule {
+            Rule::Style { declarations, .. } => {
+                for d in declarations.iter_mut().filter(|d| is_animation_property(&d.property)) {
+                    if let Some(value) = rename_animation_value(&d.value, f) {
+                        d.value = value;
+                    }
+                }
+            }
+            Rule::At { name, prelude, .. } if is_keyframes(name) => {
+                if let Some(renamed) = rename_token(prelude.trim(), f) {
+                    *prelude = renamed;
+                }
+            }
+            Rule::At { body: AtBody::Rules(inner), .. } => rename_keyframes(inner, f),
+            Rule::At { .. } => {}
+        }
+    }
+}
+
+fn is_animation_property(property: &str) -> bool {
+    let property = property.to_ascii_lowercase();
+    // Vendor prefixes such as `-webkit-animation`.
+    let unprefixed = match property.strip_prefix('-').and_then(|p| p.split_once('-')) {
+        Some((_, rest)) => rest,
+        None => property.as_str(),
+    };
+    matches!(unprefixed, "animation" | "animation-name")
+}
+
+/// Returns the value with renamed keyframes, or `None` when nothing changed.
+fn rename_animation_value(value: &str, f: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    let mut changed = false;
+    let animations: Vec<String> = split_top_level(value, ',')
+        .into_iter()
+        .map(|animation| {
+            animation
+                .split_whitespace()
+                .map(|token| match rename_token(token, f) {
+                    Some(renamed) => {
+                        changed = true;
+                        renamed
+                    }
+                    None => token.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    changed.then(|| animations.join(", "))
+}
+
+/// Renames one name, keeping any quotes around it.
+fn rename_token(token: &str, f: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    let name = unquote(token);
+    let renamed = f(name)?;
+    Some(if name.len() == token.len() {
+        renamed
+    } else {
+        format!("{q}{}{q}", renamed, q = &token[..1])
+    })
+}
+
+fn unquote(s: &str) -> &str {
+    for q in ['"', '\''] {
+        if let Some(inner) = s.strip_prefix(q).and_then(|s| s.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    s
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+fn scope_selector(selector: &str, scope: &str) -> String {
+    if let Some(rest) = selector.strip_prefix(scope)
+        && !rest.starts_with(is_ident_char)
+    {
+        return selector.to_string();
+    }
+
+    for root in ["html", "body", ":root"] {
+        if let Some(rest) = selector.strip_prefix(root) {
+            match rest.chars().next() {
+                None => return scope.to_string(),
+                Some(c) if is_ident_char(c) => {}
+                Some(c) if c.is_whitespace() || matches!(c, '>' | '+' | '~') => {
+                    // `html body .x` should collapse fully onto the scope.
+                    let rest = rest.trim_start();
+                    if matches!(rest.chars().next(), Some('>' | '+' | '~')) {
+                        return format!("{} {}", scope, rest);
+                    }
+                    return scope_selector(rest, scope);
+                }
+                // Compound selectors such as `body.dark` or `:root:hover`.
+                Some(_) => return format!("{}{}", scope, rest),
+            }
+        }
+    }
+
+    format!("{} {}", scope, selector)
+}
+
+fn unscope_selector(selector: &str, scope: &str) -> String {
+    match selector.strip_prefix(scope) {
+        Some("") => "body".to_string(),
+        Some(rest) if rest.starts_with(char::is_whitespace) => rest.trim_start().to_string(),
+        Some(rest) if !rest.starts_with(is_ident_char) => format!("body{}", rest),
+        _ => selector.to_string(),
+    }
+}
+
+/// Splits on `sep` outside of parentheses, brackets and strings.
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            (None, c) if c == sep && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn write_rules(out: &mut String, rules: &[Rule], indent: usize) {
+    let pad = "    ".repeat(indent);
+    for rule in rules {
+        match rule {
+            Rule::Style { selector, declarations } => {
+                out.push_str(&format!("{}{} {{\n", pad, selector));
+                write_declarations(out, declarations, indent + 1);
+                out.push_str(&format!("{}}}\n", pad));
+            }
+            Rule::At { name, prelude, body } => {
+                let head = if prelude.is_empty() {
+                    format!("{}@{}", pad, name)
+                } else {
+                    format!("{}@{} {}", pad, name, prelude)
+                };
+                match body {
+                    AtBody::None => out.push_str(&format!("{};\n", head)),
+                    AtBody::Rules(inner) => {
+                        out.push_str(&format!("{} {{\n", head));
+                        write_rules(out, inner, indent + 1);
+                        out.push_str(&format!("{}}}\n", pad));
+                    }
+                    AtBody::Declarations(decls) => {
+                        out.push_str(&format!("{} {{\n", head));
+                        write_declarations(out, decls, indent + 1);
+                        out.push_str(&format!("{}}}\n", pad));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn write_declarations(out: &mut String, declarations: &[Declaration], indent: usize) {
+    let pad = "    ".repeat(indent);
+    for d in declarations {
+        out.push_str(&format!("{}{}: {};\n", pad, d.property, d.value));
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, pos: usize, message: &str) -> CssError {
+        CssError {
+            line: self.src[..pos.min(self.src.len())].matches('\n').count() + 1,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek() {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), CssError> {
+        loop {
+            let rest = &self.src[self.pos..];
+            if let Some(comment) = rest.strip_prefix("/*") {
+                let start = self.pos;
+                match comment.find("*/") {
+                    Some(end) => self.pos += end + 4,
+                    None => return Err(self.error(start, "unterminated comment")),
+                }
+            } else if rest.starts_with("<!--") || rest.starts_with("-->") {
+                self.pos += if rest.starts_with("<!--") { 4 } else { 3 };
+            } else if self.peek().is_some_and(char::is_whitespace) {
+                self.bump();
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads until one of `stops` at nesting depth zero, without consuming it.
+    /// Comments are dropped and strings are kept verbatim.
+    fn read_until(&mut self, stops: &[char]) -> Result<(String, Option<char>), CssError> {
+        let mut text = String::new();
+        let mut parens: Vec<(char, usize)> = Vec::new();
+        loop {
+            let rest = &self.src[self.pos..];
+            let Some(c) = rest.chars().next() else {
+                if let Some((_, at)) = parens.last() {
+                    return Err(self.error(*at, "unclosed bracket"));
+                }
+                return Ok((text.trim().to_string(), None));
+            };
+
+            if let Some(comment) = rest.strip_prefix("/*") {
+                let start = self.pos;
+                match comment.find("*/") {
+                    Some(end) => {
+                        self.pos += end + 4;
+                        text.push(' ');
+                        continue;
+                    }
+                    None => return Err(self.error(start, "unterminated comment")),
+                }
+            }
+
+            if parens.is_empty() && stops.contains(&c) {
+                return Ok((text.trim().to_string(), Some(c)));
+            }
+
+            match c {
+                '"' | '\'' => {
+                    let start = self.pos;
+                    text.push(c);
+                    self.bump();
+                    loop {
+                        match self.peek() {
+                            None | Some('\n') => {
+                                return Err(self.error(start, "unterminated string"));
+                            }
+                            Some('\\') => {
+                                text.push('\\');
+                                self.bump();
+                                if let Some(escaped) = self.peek() {
+                                    text.push(escaped);
+                                    self.bump();
+                                }
+                            }
+                            Some(q) if q == c => {
+                                text.push(q);
+                                self.bump();
+                                break;
+                            }
+                            Some(other) => {
+                                text.push(other);
+                                self.bump();
+                            }
+                        }
+                    }
+                    continue;
+                }
+                '\\' => {
+                    text.push(c);
+                    self.bump();
+                    if let Some(escaped) = self.peek() {
+                        text.push(escaped);
+                        self.bump();
+                    }
+                    continue;
+                }
+                '(' => parens.push((')', self.pos)),
+                '[' => parens.push((']', self.pos)),
+                ')' | ']' => match parens.last() {
+                    Some((close, _)) if *close == c => {
+                        parens.pop();
+                    }
+                    _ => return Err(self.error(self.pos, &format!("unexpected '{}'", c))),
+                },
+                _ => {}
+            }
+
+            text.push(c);
+            self.bump();
+        }
+    }
+
+    fn parse_rules(&mut self, nested: bool) -> Result<Vec<Rule>, CssError> {
+        let mut rules = Vec::new();
+        loop {
+            self.skip_whitespace_and_comments()?;
+            match self.peek() {
+                None if nested => return Err(self.error(self.pos, "missing '}'")),
+                None => return Ok(rules),
+                Some('}') if nested => {
+                    self.bump();
+                    return Ok(rules);
+                }
+                Some('}') => return Err(self.error(self.pos, "unexpected '}'")),
+                Some('@') => rules.push(self.parse_at_rule()?),
+                Some(_) => rules.push(self.parse_style_rule()?),
+            }
+        }
+    }
+
+    fn parse_style_rule(&mut self) -> Result<Rule, CssError> {
+        let start = self.pos;
+        let (selector, stop) = self.read_until(&['{', '}', ';'])?;
+        if stop != Some('{') {
+            return Err(self.error(start, "expected '{' after selector"));
+        }
+        if selector.is_empty() {
+            return Err(self.error(start, "missing selector"));
+        }
+        self.bump();
+        let declarations = self.parse_declarations()?;
+        Ok(Rule::Style { selector, declarations })
+    }
+
+    fn parse_at_rule(&mut self) -> Result<Rule, CssError> {
+        let start = self.pos;
+        self.bump();
+        let name_start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+            self.bump();
+        }
+        let name = self.src[name_start..self.pos].to_string();
+        if name.is_empty() {
+            return Err(self.error(start, "expected at-rule name after '@'"));
+        }
+
+        let (prelude, stop) = self.read_until(&['{', ';', '}'])?;
+        let body = match stop {
+            Some(';') => {
+                self.bump();
+                AtBody::None
+            }
+            Some('{') => {
+                self.bump();
+                if holds_rules(&name) {
+                    AtBody::Rules(self.parse_rules(true)?)
+                } else {
+                    AtBody::Declarations(self.parse_declarations()?)
+                }
+            }
+            _ => return Err(self.error(start, &format!("expected ';' or '{{' after @{}", name))),
+        };
+        Ok(Rule::At { name, prelude, body })
+    }
+
+    fn parse_declarations(&mut self) -> Result<Vec<Declaration>, CssError> {
+        let mut declarations = Vec::new();
+        loop {
+            self.skip_whitespace_and_comments()?;
+            let start = self.pos;
+            match self.peek() {
+                None => return Err(self.error(start, "missing '}'")),
+                Some('}') => {
+                    self.bump();
+                    return Ok(declarations);
+                }
+                Some(';') => {
+                    self.bump();
+                    continue;
+                }
+                Some(_) => {}
+            }
+
+            let (text, stop) = self.read_until(&[';', '}', '{'])?;
+            if stop == Some('{') {
+                return Err(self.error(start, "unexpected '{' inside a declaration block"));
+            }
+            let Some((property, value)) = text.split_once(':') else {
+                return Err(self.error(start, &format!("expected ':' in \"{}\"", text)));
+            };
+            let property = property.trim();
+            let value = value.trim();
+            if property.is_empty() || property.contains(char::is_whitespace) {
+                return Err(self.error(start, &format!("invalid property name \"{}\"", property)));
+            }
+            if value.is_empty() {
+                return Err(self.error(start, &format!("missing value for \"{}\"", property)));
+            }
+            declarations.push(Declaration {
+                property: property.to_string(),
+                value: value.to_string(),
+            });
+            if stop == Some(';') {
+                self.bump();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_plain_and_root_selectors() {
+        let css = "body { color: #0f0; }\n.viz-item, h1 { font-size: 2rem; }";
+        let scoped = scope_css(css, ".preview").unwrap();
+        assert!(scoped.contains(".preview {\n    color: #0f0;\n}"));
+        assert!(scoped.contains(".preview .viz-item, .preview h1 {"));
+    }
+
+    #[test]
+    fn scopes_inside_media_but_not_keyframes() {
+        let css = "@media (max-width: 600px) { .a { top: 0; } }\n@keyframes roam { 0% { top: 0; } to { top: 1px; } }";
+        let scoped = scope_css(css, ".p").unwrap();
+        assert!(scoped.contains("    .p .a {"));
+        assert!(scoped.contains("    0% {"));
+        assert!(!scoped.contains(".p 0%"));
+    }
+
+    #[test]
+    fn keeps_strings_and_nested_parens() {
+        let css = ".a::before { content: \"}{;\"; } .b:is(.c, .d) { top: calc((1px + 2px) * 2); }";
+        let scoped = scope_css(css, ".p").unwrap();
+        assert!(scoped.contains("content: \"}{;\";"));
+        assert!(scoped.contains(".p .b:is(.c, .d) {"));
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let err = Stylesheet::parse(".a {\n  color red;\n}").unwrap_err();
+        assert_eq!(err.line, 2);
+
+        let err = Stylesheet::parse(".a { color: red;\n\n").unwrap_err();
+        assert_eq!(err.message, "missing '}'");
+
+        let err = Stylesheet::parse("/* oops\n.a {}").unwrap_err();
+        assert_eq!(err.line, 1);
+    }
+
+    #[test]
+    fn unscope_reverses_theme_scope() {
+        let scope = theme_scope("abc");
+        let css = "body { color: red; }\n.habit-card.full-view, h1 { top: 0; }\n";
+        let scoped = scope_css(css, &scope).unwrap();
+        assert!(scoped.starts_with("[data-theme=\"abc\"] {"));
+        assert_eq!(unscope_css(&scoped, &scope).unwrap(), Stylesheet::parse(css).unwrap().to_css());
+    }
+
+    #[test]
+    fn suffixes_keyframes_and_the_animations_using_them() {
+        let css = "@keyframes roam { to { top: 1px; } }\n\
+                   @media (min-width: 1px) { @-webkit-keyframes \"spin\" { to { top: 0; } } }\n\
+                   .a { animation: roam 2s infinite, fade 1s; -webkit-animation-name: 'spin'; }";
+        let mut sheet = Stylesheet::parse(css).unwrap();
+        sheet.suffix_keyframes("abc");
+        let out = sheet.to_css();
+        assert!(out.contains("@keyframes roam-abc {"));
+        assert!(out.contains("@-webkit-keyframes \"spin-abc\" {"));
+        // `fade` is defined elsewhere on the page and is left alone.
+        assert!(out.contains("animation: roam-abc 2s infinite, fade 1s;"));
+        assert!(out.contains("-webkit-animation-name: 'spin-abc';"));
+
+        let mut again = Stylesheet::parse(&out).unwrap();
+        again.suffix_keyframes("abc");
+        assert_eq!(again.to_css(), out);
+
+        let mut back = Stylesheet::parse(&out).unwrap();
+        back.unsuffix_keyframes("abc");
+        assert_eq!(back.to_css(), Stylesheet::parse(css).unwrap().to_css());
+    }
+
+    #[test]
+    fn scoping_is_idempotent() {
+        let once = scope_css("html body .x { top: 0; }", ".p").unwrap();
+        assert!(once.starts_with(".p .x {"));
+        assert_eq!(scope_css(&once, ".p").unwrap(), once);
+    }
+}
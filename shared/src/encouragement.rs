@@ -0,0 +1,87 @@
+//! Rules for encouragement left on published trackers, shared by the form
+//! and the server.
+
+use crate::tracker::Encouragement;
+
+/// The reactions on offer; anything else is refused.
+pub const REACTIONS: [&str; 6] = ["👏", "💪", "🎉", "❤️", "🔥", "🙌"];
+pub const MAX_MESSAGE_LEN: usize = 140;
+pub const MAX_FROM_LEN: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncouragementError {
+    Empty,
+    MessageAndReaction,
+    MessageTooLong,
+    UnknownReaction,
+    FromTooLong,
+}
+
+impl EncouragementError {
+    /// The field at fault, used in API error bodies.
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            EncouragementError::Empty | EncouragementError::MessageAndReaction => None,
+            EncouragementError::MessageTooLong => Some("message"),
+            EncouragementError::UnknownReaction => Some("reaction"),
+            EncouragementError::FromTooLong => Some("from"),
+        }
+    }
+}
+
+impl std::fmt::Display for EncouragementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncouragementError::Empty => write!(f, "write a message or pick a reaction"),
+            EncouragementError::MessageAndReaction => write!(f, "send a message or a reaction, not both"),
+            EncouragementError::MessageTooLong => write!(f, "message must be at most {} characters", MAX_MESSAGE_LEN),
+            EncouragementError::UnknownReaction => write!(f, "unknown reaction"),
+            EncouragementError::FromTooLong => write!(f, "name must be at most {} characters", MAX_FROM_LEN),
+        }
+    }
+}
+
+impl Encouragement {
+    /// The encouragement with whitespace tidied, if it is exactly one message
+    /// or one known reaction.
+    pub fn normalized(&self) -> Result<Self, EncouragementError> {
+        let message = self.message.split_whitespace().collect::<Vec<_>>().join(" ");
+        let reaction = self.reaction.trim().to_string();
+        let from = self.from.trim().to_string();
+        match (message.is_empty(), reaction.is_empty()) {
+            (true, true) => return Err(EncouragementError::Empty),
+            (false, false) => return Err(EncouragementError::MessageAndReaction),
+            _ => {}
+        }
+        if message.chars().count() > MAX_MESSAGE_LEN {
+            return Err(EncouragementError::MessageTooLong);
+        }
+        if !reaction.is_empty() && !REACTIONS.contains(&reaction.as_str()) {
+            return Err(EncouragementError::UnknownReaction);
+        }
+        if from.chars().count() > MAX_FROM_LEN {
+            return Err(EncouragementError::FromTooLong);
+        }
+        Ok(Self { message, reaction, from })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enc(message: &str, reaction: &str, from: &str) -> Encouragement {
+        Encouragement { message: message.to_string(), reaction: reaction.to_string(), from: from.to_string() }
+    }
+
+    #[test]
+    fn exactly_one_message_or_known_reaction() {
+        assert_eq!(enc("  keep\n going! ", "", " Jo ").normalized(), Ok(enc("keep going!", "", "Jo")));
+        assert_eq!(enc("", "🔥", "").normalized(), Ok(enc("", "🔥", "")));
+        assert_eq!(enc(" ", "", "Jo").normalized(), Err(EncouragementError::Empty));
+        assert_eq!(enc("hi", "🔥", "").normalized(), Err(EncouragementError::MessageAndReaction));
+        assert_eq!(enc("", "💩", "").normalized(), Err(EncouragementError::UnknownReaction));
+        assert_eq!(enc(&"a".repeat(141), "", "").normalized(), Err(EncouragementError::MessageTooLong));
+        assert_eq!(enc("hi", "", &"b".repeat(31)).normalized(), Err(EncouragementError::FromTooLong));
+    }
+}
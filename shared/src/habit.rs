@@ -0,0 +1,263 @@
+//! Normalisation rules for habit metadata shared by the form and the server.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+pub const MAX_TAGS: usize = 10;
+pub const MAX_TAG_LEN: usize = 32;
+pub const MAX_CATEGORY_LEN: usize = 40;
+pub const MAX_SLUG_LEN: usize = 48;
+
+/// Top-level paths the app or server already answers; a habit slug becomes
+/// `/{slug}`, so none of these may be used.
+pub const RESERVED_SLUGS: &[&str] = &[
+    "habits", "themes", "new-tracker", "api", "feeds", "pkg", "assets", "static",
+    "index-html", "style-css", "favicon-ico", "robots-txt", "admin", "login", "logout",
+    "stats",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlugError {
+    Empty,
+    TooLong,
+    InvalidCharacters,
+    Reserved,
+}
+
+impl SlugError {
+    /// Stable machine-readable code, used in API error bodies.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SlugError::Empty => "slug_empty",
+            SlugError::TooLong => "slug_too_long",
+            SlugError::InvalidCharacters => "slug_invalid",
+            SlugError::Reserved => "slug_reserved",
+        }
+    }
+}
+
+impl std::fmt::Display for SlugError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SlugError::Empty => write!(f, "slug is required"),
+            SlugError::TooLong => write!(f, "slug must be at most {} characters", MAX_SLUG_LEN),
+            SlugError::InvalidCharacters => {
+                write!(f, "slug may only contain lowercase letters, digits and single hyphens")
+            }
+            SlugError::Reserved => write!(f, "slug is reserved by the app"),
+        }
+    }
+}
+
+/// Uploaded image icons larger than this are rejected.
+pub const MAX_ICON_IMAGE_BYTES: usize = 32 * 1024;
+
+/// True when `icon` is exactly one user-perceived character and that character
+/// is an emoji, so the visualizer can lay it out like a single glyph. ZWJ
+/// sequences, skin tones, flags and keycaps all count as one.
+pub fn is_single_emoji(icon: &str) -> bool {
+    let mut graphemes = icon.graphemes(true);
+    let (Some(grapheme), None) = (graphemes.next(), graphemes.next()) else {
+        return false;
+    };
+    // Keycaps like 1️⃣ start with a plain digit, so look for the enclosing mark too.
+    grapheme.chars().any(|c| is_pictographic(c) || c == '\u{20E3}')
+}
+
+/// The first user-perceived character of `icon`, for habits saved before icons were validated.
+pub fn display_icon(icon: &str) -> &str {
+    icon.trim().graphemes(true).next().unwrap_or_default()
+}
+
+fn is_pictographic(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x00A9 | 0x00AE | 0x203C | 0x2049 | 0x2122 | 0x2139
+            | 0x2194..=0x21AA
+            | 0x231A..=0x23FF
+            | 0x24C2
+            | 0x25AA..=0x27BF
+            | 0x2934..=0x2935
+            | 0x2B05..=0x2B55
+            | 0x3030 | 0x303D | 0x3297 | 0x3299
+            | 0x1F000..=0x1FAFF
+    )
+}
+
+/// Builds a URL slug from a habit name: transliterated to ASCII, lowercased,
+/// with runs of anything else collapsed into single hyphens.
+pub fn slugify(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.chars() {
+        let ascii = transliterate(c);
+        for a in ascii.chars().flat_map(char::to_lowercase) {
+            if a.is_ascii_alphanumeric() {
+                out.push(a);
+            } else if !out.is_empty() && !out.ends_with('-') {
+                out.push('-');
+            }
+        }
+    }
+    let out: String = out.chars().take(MAX_SLUG_LEN).collect();
+    out.trim_end_matches('-').to_string()
+}
+
+pub fn validate_slug(slug: &str) -> Result<(), SlugError> {
+    if slug.is_empty() {
+        return Err(SlugError::Empty);
+    }
+    if slug.len() > MAX_SLUG_LEN {
+        return Err(SlugError::TooLong);
+    }
+    let well_formed = slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-')
+        && !slug.contains("--");
+    if !well_formed {
+        return Err(SlugError::InvalidCharacters);
+    }
+    if RESERVED_SLUGS.contains(&slug) {
+        return Err(SlugError::Reserved);
+    }
+    Ok(())
+}
+
+fn transliterate(c: char) -> String {
+    let s = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ą' | 'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => "a",
+        'æ' | 'Æ' => "ae",
+        'ç' | 'ć' | 'č' | 'Ç' | 'Ć' | 'Č' => "c",
+        'ď' | 'đ' | 'Ď' | 'Đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' | 'È' | 'É' | 'Ê' | 'Ë' | 'Ę' | 'Ě' => "e",
+        'ì' | 'í' | 'î' | 'ï' | 'Ì' | 'Í' | 'Î' | 'Ï' => "i",
+        'ł' | 'Ł' => "l",
+        'ñ' | 'ń' | 'ň' | 'Ñ' | 'Ń' | 'Ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ő' | 'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ő' => "o",
+        'œ' | 'Œ' => "oe",
+        'ř' | 'Ř' => "r",
+        'ś' | 'š' | 'Ś' | 'Š' => "s",
+        'ß' => "ss",
+        'ť' | 'Ť' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ů' | 'ű' | 'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ů' | 'Ű' => "u",
+        'ý' | 'ÿ' | 'Ý' => "y",
+        'ź' | 'ż' | 'ž' | 'Ź' | 'Ż' | 'Ž' => "z",
+        'а' | 'А' => "a",
+        'б' | 'Б' => "b",
+        'в' | 'В' => "v",
+        'г' | 'Г' => "g",
+        'ґ' | 'Ґ' => "g",
+        'д' | 'Д' => "d",
+        'е' | 'Е' | 'э' | 'Э' => "e",
+        'є' | 'Є' => "ye",
+        'ё' | 'Ё' => "yo",
+        'ж' | 'Ж' => "zh",
+        'з' | 'З' => "z",
+        'и' | 'И' => "i",
+        'і' | 'І' => "i",
+        'ї' | 'Ї' => "yi",
+        'й' | 'Й' | 'ы' | 'Ы' => "y",
+        'к' | 'К' => "k",
+        'л' | 'Л' => "l",
+        'м' | 'М' => "m",
+        'н' | 'Н' => "n",
+        'о' | 'О' => "o",
+        'п' | 'П' => "p",
+        'р' | 'Р' => "r",
+        'с' | 'С' => "s",
+        'т' | 'Т' => "t",
+        'у' | 'У' => "u",
+        'ф' | 'Ф' => "f",
+        'х' | 'Х' => "kh",
+        'ц' | 'Ц' => "ts",
+        'ч' | 'Ч' => "ch",
+        'ш' | 'Ш' => "sh",
+        'щ' | 'Щ' => "shch",
+        'ъ' | 'Ъ' | 'ь' | 'Ь' => "",
+        'ю' | 'Ю' => "yu",
+        'я' | 'Я' => "ya",
+        _ => return c.to_string(),
+    };
+    s.to_string()
+}
+
+/// Lowercases a tag and collapses anything that isn't a letter or digit into
+/// single hyphens. Returns `None` when nothing usable is left.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let mut out = String::new();
+    for c in tag.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            out.push(c);
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    let out: String = out.trim_end_matches('-').chars().take(MAX_TAG_LEN).collect();
+    let out = out.trim_end_matches('-');
+    (!out.is_empty()).then(|| out.to_string())
+}
+
+/// Normalises, de-duplicates and caps a list of tags, keeping the first occurrence order.
+pub fn normalize_tags<'a>(tags: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for tag in tags.into_iter().filter_map(normalize_tag) {
+        if !out.contains(&tag) {
+            out.push(tag);
+        }
+        if out.len() == MAX_TAGS {
+            break;
+        }
+    }
+    out
+}
+
+/// Splits comma separated form input into normalised tags.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    normalize_tags(input.split(','))
+}
+
+pub fn normalize_category(category: &str) -> String {
+    let words: Vec<&str> = category.split_whitespace().collect();
+    words.join(" ").chars().take(MAX_CATEGORY_LEN).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_normalised_and_deduplicated() {
+        assert_eq!(
+            parse_tags(" Alcohol , social drinking,alcohol,,  Night/Out! "),
+            vec!["alcohol", "social-drinking", "night-out"]
+        );
+        assert_eq!(normalize_tag("---"), None);
+    }
+
+    #[test]
+    fn slugs_are_generated_from_names() {
+        assert_eq!(slugify("My Habit/../x"), "my-habit-x");
+        assert_eq!(slugify("  Crème Brûlée!! "), "creme-brulee");
+        assert_eq!(slugify("Пиво"), "pivo");
+        assert_eq!(slugify("🍺🍺"), "");
+        assert!(slugify(&"a".repeat(100)).len() <= MAX_SLUG_LEN);
+    }
+
+    #[test]
+    fn icons_must_be_a_single_emoji() {
+        for ok in ["🍺", "👍🏽", "👨‍👩‍👧", "🇺🇦", "1️⃣", "☕"] {
+            assert!(is_single_emoji(ok), "{} should be accepted", ok);
+        }
+        for bad in ["", "🍺🍺", "beer", "a", "🍺 "] {
+            assert!(!is_single_emoji(bad), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn slug_validation_rejects_routes_and_bad_characters() {
+        assert_eq!(validate_slug("beer"), Ok(()));
+        assert_eq!(validate_slug("habits"), Err(SlugError::Reserved));
+        assert_eq!(validate_slug("stats"), Err(SlugError::Reserved));
+        assert_eq!(validate_slug("My Habit/../x"), Err(SlugError::InvalidCharacters));
+        assert_eq!(validate_slug("-beer"), Err(SlugError::InvalidCharacters));
+        assert_eq!(validate_slug(""), Err(SlugError::Empty));
+    }
+}
@@ -1,3 +1,12 @@
+pub mod achievements;
+pub mod css;
+pub mod encouragement;
+pub mod habit;
+pub mod hash;
+pub mod milestones;
+pub mod pow;
+pub mod theme_tokens;
+
 pub mod tracker {
     include!(concat!(env!("OUT_DIR"), "/tracker.rs"));
 }
@@ -0,0 +1,218 @@
+//! Milestone moments worth a notification ("30 days clean", "1,000 beers
+//! avoided") and when to deliver them. Shared so the browser and the push job
+//! agree on what is due.
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveTime, TimeZone, Timelike, Utc};
+
+/// Day counts worth a notification; after the last one, every year.
+pub const DAY_MILESTONES: [i64; 8] = [1, 3, 7, 14, 30, 90, 180, 365];
+
+/// The next round number above `count`: 10, 25, 50, 100, 250, 500, 1000, …
+pub fn next_unit_milestone(count: f64) -> f64 {
+    let mut magnitude = 10.0;
+    loop {
+        for m in [1.0, 2.5, 5.0] {
+            if m * magnitude > count {
+                return m * magnitude;
+            }
+        }
+        magnitude *= 10.0;
+    }
+}
+
+/// `12345` → `"12,345"`.
+pub fn format_thousands(value: u64) -> String {
+    let digits = value.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// What a tracker's milestones are computed from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Schedule {
+    pub start: DateTime<Utc>,
+    pub units_per_day: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MilestoneKind {
+    Days(i64),
+    Units(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Milestone {
+    pub at: DateTime<Utc>,
+    pub kind: MilestoneKind,
+}
+
+impl Milestone {
+    /// "Beer · 30 days clean!", "Beer · 1,000 beers avoided!".
+    pub fn title(&self, habit_name: &str, unit_name: &str) -> String {
+        let what = match self.kind {
+            MilestoneKind::Days(1) => "24 hours clean!".to_string(),
+            MilestoneKind::Days(d) if d % 365 == 0 => {
+                let years = d / 365;
+                format!("{} year{} clean!", years, if years == 1 { "" } else { "s" })
+            }
+            MilestoneKind::Days(d) => format!("{} days clean!", d),
+            MilestoneKind::Units(u) => format!("{} {} avoided!", format_thousands(u as u64), unit_name.to_lowercase()),
+        };
+        format!("{} · {}", habit_name, what)
+    }
+}
+
+impl Schedule {
+    /// Units avoided between the start date and `at`, never negative.
+    pub fn units_at(&self, at: DateTime<Utc>) -> f64 {
+        ((at - self.start).num_minutes() as f64 / (24.0 * 60.0) * self.units_per_day).max(0.0)
+    }
+
+    /// When the count passes `target` units at the current rate. Projections
+    /// centuries out are not worth showing and overflow chrono.
+    pub fn projected_date(&self, target: f64, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.units_per_day <= 0.0 {
+            return None;
+        }
+        let remaining = (target - self.units_at(now)).max(0.0);
+        let secs = remaining / self.units_per_day * 86_400.0;
+        (secs < 100.0 * 365.0 * 86_400.0).then(|| now + Duration::seconds(secs.ceil() as i64))
+    }
+
+    fn next_day_milestone(&self, after: DateTime<Utc>) -> Milestone {
+        let days = DAY_MILESTONES
+            .into_iter()
+            .find(|d| self.start + Duration::days(*d) > after)
+            .unwrap_or_else(|| ((after - self.start).num_days() / 365 + 1) * 365);
+        Milestone { at: self.start + Duration::days(days), kind: MilestoneKind::Days(days) }
+    }
+
+    /// The first day or unit milestone strictly after `after`.
+    pub fn next_milestone(&self, after: DateTime<Utc>) -> Milestone {
+        let day = self.next_day_milestone(after);
+        let target = next_unit_milestone(self.units_at(after));
+        match self.projected_date(target, after) {
+            Some(at) if at < day.at => Milestone { at, kind: MilestoneKind::Units(target) },
+            _ => day,
+        }
+    }
+
+    /// The milestone to notify about as of `now`, if any. Several passed since
+    /// `last_notified` (e.g. after a week offline) collapse into the latest.
+    pub fn due(&self, quiet: QuietHours, last_notified: DateTime<Utc>, now: DateTime<Utc>, offset: FixedOffset) -> Option<Milestone> {
+        let mut after = last_notified;
+        let mut latest = None;
+        loop {
+            let milestone = self.next_milestone(after);
+            if quiet.deliver_at(milestone.at, offset) > now {
+                return latest;
+            }
+            after = milestone.at;
+            latest = Some(milestone);
+        }
+    }
+}
+
+/// Local hours during which notifications wait; the window may wrap past
+/// midnight, and equal hours disable it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuietHours {
+    pub start: u8,
+    pub end: u8,
+}
+
+impl QuietHours {
+    fn contains(&self, hour: u32) -> bool {
+        let (start, end) = (self.start as u32, self.end as u32);
+        match start.cmp(&end) {
+            std::cmp::Ordering::Equal => false,
+            std::cmp::Ordering::Less => hour >= start && hour < end,
+            std::cmp::Ordering::Greater => hour >= start || hour < end,
+        }
+    }
+
+    /// When a notification due `at` may be shown: `at`, or the end of the quiet
+    /// hours it falls in, in the user's `offset`.
+    pub fn deliver_at(&self, at: DateTime<Utc>, offset: FixedOffset) -> DateTime<Utc> {
+        let local = at.with_timezone(&offset);
+        if !self.contains(local.hour()) {
+            return at;
+        }
+        let end = NaiveTime::from_hms_opt(self.end.min(23) as u32, 0, 0).unwrap_or(NaiveTime::MIN);
+        let mut day = local.date_naive();
+        if local.time() >= end {
+            day += Duration::days(1);
+        }
+        offset.from_local_datetime(&day.and_time(end)).single().map_or(at, |d| d.with_timezone(&Utc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn round_numbers_and_separators() {
+        assert_eq!(next_unit_milestone(0.0), 10.0);
+        assert_eq!(next_unit_milestone(10.0), 25.0);
+        assert_eq!(next_unit_milestone(9_999.0), 10_000.0);
+        assert_eq!(format_thousands(999), "999");
+        assert_eq!(format_thousands(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn picks_the_earlier_of_day_and_unit_milestones() {
+        let slow = Schedule { start: at(1, 12), units_per_day: 0.5 };
+        let m = slow.next_milestone(at(1, 13));
+        assert_eq!(m, Milestone { at: at(2, 12), kind: MilestoneKind::Days(1) });
+        assert_eq!(m.title("Beer", "Beers"), "Beer · 24 hours clean!");
+
+        // Twelve a day reaches 10 beers before the first full day.
+        let fast = Schedule { start: at(1, 12), units_per_day: 12.0 };
+        let m = fast.next_milestone(at(1, 13));
+        assert_eq!(m, Milestone { at: at(2, 8), kind: MilestoneKind::Units(10.0) });
+        assert_eq!(m.title("Beer", "Beers"), "Beer · 10 beers avoided!");
+
+        let idle = Schedule { start: at(1, 12), units_per_day: 0.0 };
+        let m = idle.next_milestone(at(1, 12) + Duration::days(400));
+        assert_eq!(m.title("Beer", "Beers"), "Beer · 2 years clean!");
+    }
+
+    #[test]
+    fn quiet_hours_hold_notifications_until_morning() {
+        let quiet = QuietHours { start: 22, end: 8 };
+        let utc = FixedOffset::east_opt(0).unwrap();
+        assert_eq!(quiet.deliver_at(at(5, 21), utc), at(5, 21));
+        assert_eq!(quiet.deliver_at(at(5, 23), utc), at(6, 8));
+        assert_eq!(quiet.deliver_at(at(6, 3), utc), at(6, 8));
+
+        // 23:00 UTC is 01:00 at UTC+2, still quiet until 08:00 local (06:00 UTC).
+        let plus_two = FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(quiet.deliver_at(at(5, 23), plus_two), at(6, 6));
+        assert_eq!(QuietHours { start: 0, end: 0 }.deliver_at(at(5, 3), utc), at(5, 3));
+    }
+
+    #[test]
+    fn each_milestone_is_due_once() {
+        let schedule = Schedule { start: at(1, 12), units_per_day: 0.5 };
+        let quiet = QuietHours { start: 22, end: 8 };
+        let utc = FixedOffset::east_opt(0).unwrap();
+
+        assert_eq!(schedule.due(quiet, at(1, 12), at(2, 11), utc), None);
+        let m = schedule.due(quiet, at(1, 12), at(2, 13), utc).unwrap();
+        assert_eq!(m.at, at(2, 12));
+        assert_eq!(schedule.due(quiet, m.at, at(2, 14), utc), None);
+        // A week later only the latest passed milestone is due.
+        assert_eq!(schedule.due(quiet, m.at, at(9, 14), utc).map(|m| m.kind), Some(MilestoneKind::Days(7)));
+    }
+}
@@ -0,0 +1,93 @@
+//! Proof of work for anonymous writes, shared by the server and the app.
+//!
+//! The server hands out a signed challenge. The client looks for a nonce
+//! whose hash, together with the challenge and the exact request (method,
+//! path and body), starts with `difficulty` zero bits, and sends both in the
+//! `HEADER`. One request costs a browser a fraction of a second; a flood
+//! costs a script that much per request.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const HEADER: &str = "x-proof-of-work";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Challenge {
+    pub challenge: String,
+    /// Zero when the server doesn't ask for proof.
+    pub difficulty: u32,
+}
+
+/// What a proof is for: one method on one path, with one body.
+fn request_hash(method: &str, path: &str, body: &[u8]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(method.as_bytes())
+        .chain_update(b" ")
+        .chain_update(path.as_bytes())
+        .chain_update(b"\n")
+        .chain_update(body)
+        .finalize()
+        .into()
+}
+
+fn digest(challenge: &str, nonce: u64, request_hash: &[u8]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(challenge.as_bytes())
+        .chain_update(b":")
+        .chain_update(nonce.to_le_bytes())
+        .chain_update(request_hash)
+        .finalize()
+        .into()
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// Finds a nonce proving work for sending `body` to `method` `path`.
+pub fn solve(challenge: &str, difficulty: u32, method: &str, path: &str, body: &[u8]) -> u64 {
+    let request_hash = request_hash(method, path, body);
+    (0..u64::MAX)
+        .find(|nonce| leading_zero_bits(&digest(challenge, *nonce, &request_hash)) >= difficulty)
+        .unwrap_or_default()
+}
+
+pub fn verify(challenge: &str, nonce: u64, difficulty: u32, method: &str, path: &str, body: &[u8]) -> bool {
+    leading_zero_bits(&digest(challenge, nonce, &request_hash(method, path, body))) >= difficulty
+}
+
+/// The header value for a solved challenge.
+pub fn header_value(challenge: &str, nonce: u64) -> String {
+    format!("{}:{}", challenge, nonce)
+}
+
+/// Splits a header value into its challenge and nonce.
+pub fn parse_header(value: &str) -> Option<(&str, u64)> {
+    let (challenge, nonce) = value.rsplit_once(':')?;
+    Some((challenge, nonce.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solutions_verify_for_their_request_only() {
+        let nonce = solve("123.abc", 8, "POST", "/api/groups", b"hello");
+        assert!(verify("123.abc", nonce, 8, "POST", "/api/groups", b"hello"));
+        assert!(!verify("123.abc", nonce, 8, "POST", "/api/groups", b"hellp"));
+        assert!(!verify("123.abd", nonce, 8, "POST", "/api/groups", b"hello"));
+        assert!(!verify("123.abc", nonce, 8, "PUT", "/api/groups", b"hello"));
+        assert!(!verify("123.abc", nonce, 8, "POST", "/api/groups/abc/members", b"hello"));
+        assert_eq!(parse_header(&header_value("123.abc", nonce)), Some(("123.abc", nonce)));
+        assert_eq!(parse_header("123.abc"), None);
+        assert_eq!(leading_zero_bits(&[0, 0b0001_0000, 0xff]), 11);
+    }
+}
@@ -0,0 +1,219 @@
+//! Compiles [`ThemeTokens`] into a stylesheet.
+//!
+//! Token values end up inside CSS, so every free-form string is checked
+//! against a narrow grammar before it is written out.
+
+use crate::tracker::{BorderStyle, Palette, ThemeTokens, VisualizerAnimation, VisualizerMode};
+
+const MAX_BORDER_WIDTH_PX: i32 = 20;
+const MAX_BORDER_RADIUS_PX: i32 = 64;
+
+impl Palette {
+    pub fn winamp() -> Self {
+        Self {
+            background: "#000000".to_string(),
+            surface: "#111111".to_string(),
+            text: "#00ff00".to_string(),
+            accent: "#00ff00".to_string(),
+            muted: "#333333".to_string(),
+        }
+    }
+}
+
+impl VisualizerAnimation {
+    pub fn roaming() -> Self {
+        Self {
+            duration_seconds: 15.0,
+            roam_scale: 1.0,
+            icon_size_rem: 1.2,
+            glow: false,
+        }
+    }
+}
+
+impl ThemeTokens {
+    /// Tokens matching the look of the default Winamp theme.
+    pub fn winamp() -> Self {
+        Self {
+            palette: Some(Palette::winamp()),
+            font_stack: "\"Courier New\", Courier, monospace".to_string(),
+            border_style: BorderStyle::Solid as i32,
+            border_width_px: 2,
+            border_radius_px: 0,
+            visualizer: Some(VisualizerAnimation::roaming()),
+            default_mode: VisualizerMode::Roam as i32,
+        }
+    }
+
+    /// Checks every token, returning a message naming the first bad one.
+    pub fn validate(&self) -> Result<(), String> {
+        let palette = self.palette.clone().unwrap_or_else(Palette::winamp);
+        for (name, value) in [
+            ("background", &palette.background),
+            ("surface", &palette.surface),
+            ("text", &palette.text),
+            ("accent", &palette.accent),
+            ("muted", &palette.muted),
+        ] {
+            if !is_color(value) {
+                return Err(format!("{} colour \"{}\" is not a hex or named colour", name, value));
+            }
+        }
+        if !self.font_stack.is_empty() && !is_font_stack(&self.font_stack) {
+            return Err(format!("font stack \"{}\" contains unsupported characters", self.font_stack));
+        }
+        if BorderStyle::try_from(self.border_style).is_err() {
+            return Err("unknown border style".to_string());
+        }
+        if VisualizerMode::try_from(self.default_mode).is_err() {
+            return Err("unknown visualizer mode".to_string());
+        }
+        if !(0..=MAX_BORDER_WIDTH_PX).contains(&self.border_width_px) {
+            return Err(format!("border width must be 0-{}px", MAX_BORDER_WIDTH_PX));
+        }
+        if !(0..=MAX_BORDER_RADIUS_PX).contains(&self.border_radius_px) {
+            return Err(format!("border radius must be 0-{}px", MAX_BORDER_RADIUS_PX));
+        }
+        let viz = self.visualizer.unwrap_or_else(VisualizerAnimation::roaming);
+        if !(0.5..=120.0).contains(&viz.duration_seconds) {
+            return Err("animation duration must be 0.5-120 seconds".to_string());
+        }
+        if !(0.0..=5.0).contains(&viz.roam_scale) {
+            return Err("roam scale must be 0-5".to_string());
+        }
+        if !(0.25..=10.0).contains(&viz.icon_size_rem) {
+            return Err("icon size must be 0.25-10rem".to_string());
+        }
+        Ok(())
+    }
+
+    /// Compiles the tokens into an unscoped stylesheet. Invalid values fall back to defaults.
+    pub fn to_css(&self) -> String {
+        let defaults = Palette::winamp();
+        let palette = self.palette.clone().unwrap_or_else(Palette::winamp);
+        let color = |value: &str, fallback: &str| -> String {
+            if is_color(value) { value.to_string() } else { fallback.to_string() }
+        };
+        let bg = color(&palette.background, &defaults.background);
+        let surface = color(&palette.surface, &defaults.surface);
+        let text = color(&palette.text, &defaults.text);
+        let accent = color(&palette.accent, &defaults.accent);
+        let muted = color(&palette.muted, &defaults.muted);
+
+        let font = if is_font_stack(&self.font_stack) {
+            self.font_stack.clone()
+        } else {
+            "inherit".to_string()
+        };
+        let style = match BorderStyle::try_from(self.border_style).unwrap_or(BorderStyle::Solid) {
+            BorderStyle::Solid => "solid",
+            BorderStyle::Dashed => "dashed",
+            BorderStyle::Dotted => "dotted",
+            BorderStyle::Double => "double",
+            BorderStyle::None => "none",
+        };
+        let width = self.border_width_px.clamp(0, MAX_BORDER_WIDTH_PX);
+        let radius = self.border_radius_px.clamp(0, MAX_BORDER_RADIUS_PX);
+
+        let default_viz = VisualizerAnimation::roaming();
+        let viz = self.visualizer.unwrap_or_else(VisualizerAnimation::roaming);
+        let finite_or = |v: f64, min: f64, max: f64, fallback: f64| {
+            if v.is_finite() { v.clamp(min, max) } else { fallback }
+        };
+        let duration = finite_or(viz.duration_seconds, 0.5, 120.0, default_viz.duration_seconds);
+        let scale = finite_or(viz.roam_scale, 0.0, 5.0, default_viz.roam_scale);
+        let size = finite_or(viz.icon_size_rem, 0.25, 10.0, default_viz.icon_size_rem);
+        let glow = if viz.glow {
+            format!("\n    filter: drop-shadow(0 0 5px {});", accent)
+        } else {
+            String::new()
+        };
+
+        format!(
+            r#"body {{
+    background-color: {bg};
+    color: {text};
+    font-family: {font};
+}}
+.habit-card {{
+    background: {surface};
+    color: {text};
+    border: {width}px {style} {accent};
+    border-radius: {radius}px;
+}}
+.habit-header {{
+    border-bottom: 1px {style} {muted};
+}}
+.habit-stats {{
+    color: {accent};
+}}
+.winamp-btn, .winamp-btn-small {{
+    background-color: {bg};
+    color: {accent};
+    border: 1px {style} {accent};
+    border-radius: {radius}px;
+    box-shadow: 2px 2px 0px {accent};
+    font-family: {font};
+}}
+.heatmap {{
+    --heatmap-clean: {accent};
+    --heatmap-untracked: {surface};
+}}
+.visualizer-container {{
+    background: {bg};
+    border-top: 1px {style} {muted};
+}}
+.viz-item {{
+    font-size: {size}rem;
+    animation: tokens-roam {duration}s infinite alternate ease-in-out;{glow}
+}}
+@keyframes tokens-roam {{
+    0% {{ transform: translate(0, 0) rotate(0deg); }}
+    50% {{ transform: translate(calc(var(--tx, 0px) * {scale}), calc(var(--ty, 0px) * {scale})) rotate(var(--tr, 0deg)); }}
+    100% {{ transform: translate(calc(var(--tx, 0px) * {scale} * -0.5), calc(var(--ty, 0px) * {scale} * 0.5)) rotate(0deg); }}
+}}
+"#
+        )
+    }
+}
+
+/// Accepts `#rgb`, `#rrggbb`, `#rrggbbaa` and plain colour keywords like `teal`.
+pub fn is_color(value: &str) -> bool {
+    match value.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => !value.is_empty() && value.len() <= 32 && value.chars().all(|c| c.is_ascii_alphabetic()),
+    }
+}
+
+fn is_font_stack(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 200
+        && value.matches('"').count().is_multiple_of(2)
+        && value.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | ',' | '-' | '_' | '"'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::Stylesheet;
+
+    #[test]
+    fn default_tokens_compile_to_valid_css() {
+        let tokens = ThemeTokens::winamp();
+        assert!(tokens.validate().is_ok());
+        assert!(Stylesheet::parse(&tokens.to_css()).is_ok());
+    }
+
+    #[test]
+    fn rejects_values_that_could_break_out_of_a_declaration() {
+        let mut tokens = ThemeTokens::winamp();
+        tokens.palette.as_mut().unwrap().accent = "red; } body { display: none".to_string();
+        assert!(tokens.validate().is_err());
+        assert!(!tokens.to_css().contains("display: none"));
+
+        let mut tokens = ThemeTokens::winamp();
+        tokens.font_stack = "x; background: url(//evil)".to_string();
+        assert!(tokens.validate().is_err());
+        assert!(!tokens.to_css().contains("evil"));
+    }
+}